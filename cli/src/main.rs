@@ -3,6 +3,8 @@ mod usb_util;
 //mod cmd;
 
 use std::io::{self, BufRead};
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
 //use cmd::{cmd_from_str_parser, Cmd};
 
 use ellocopo2::*;
@@ -19,6 +21,32 @@ fn main() {
     let stdin = io::stdin();
     for line in stdin.lock().lines() {
         let line = line.unwrap();
+
+        match command(&line) {
+            Command::ReadChunked(path) => {
+                match read_chunked(&usb_e.dh, path) {
+                    Ok(data) => println!("{} bytes: {:x?}", data.len(), data),
+                    Err(e) => println!("error: {}", e),
+                }
+                continue;
+            }
+            Command::WriteChunked(args) => {
+                let res = args.split_once(' ')
+                    .ok_or("usage: wc /path file".to_string())
+                    .and_then(|(path, file)| {
+                        let data = std::fs::read(file.trim()).map_err(|e| e.to_string())?;
+                        write_chunked(&usb_e.dh, path, &data)
+                    });
+                println!("{:?}", res);
+                continue;
+            }
+            Command::Auth(lvl) => {
+                println!("{:?}", authenticate(&usb_e.dh, lvl));
+                continue;
+            }
+            Command::Request => (),
+        }
+
         let res = interpret(&line, &mut buf);
         println!("{:?}", res);
         if let Err(_) = res {
@@ -33,10 +61,194 @@ fn main() {
         let mut parser = ParseMsg::new();
         let msg = parser.try_parse(&buf[..sz]).unwrap();
         println!("msg: {:?}", &msg);
+        print_error(&msg);
+
+        if let Msg(AnswerCode::OK_PENDING, _, Value::U16(seq)) = msg {
+            println!("pending, seq: {}", seq);
+            let mut done_buf = [0x0u8;MAX_MSG_SZ];
+            match wait_completion(&usb_e.dh, &mut done_buf, seq) {
+                Some(done) => {
+                    println!("done: {:?}", &done);
+                    print_error(&Msg(done.code, "", done.value));
+                }
+                None => println!("no completion for seq {} in {} ms", seq, PENDING_TIMEOUT_MS),
+            }
+        }
+    }
+}
+
+/// Line of stdin, anything but CLI's own commands is a request for `interpret`
+#[derive(Debug, PartialEq)]
+enum Command<'a> {
+    /// `rc /path` - read by chunks
+    ReadChunked(&'a str),
+    /// `wc /path file` - write file by chunks
+    WriteChunked(&'a str),
+    /// `auth lvl` - raise session level with secret from ELLOCOPO_SECRET, `auth` - drop it
+    Auth(&'a str),
+    Request,
+}
+
+fn command(line: &str) -> Command<'_> {
+    let line = line.trim();
+    let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+    match word {
+        "rc" => Command::ReadChunked(rest.trim()),
+        "wc" => Command::WriteChunked(rest.trim()),
+        "auth" => Command::Auth(rest.trim()),
+        _ => Command::Request,
+    }
+}
+
+/// Wait for `ASYNC_DONE` frame of pending request `seq`
+fn wait_completion<'a>(dh: &libusb::DeviceHandle, buf: &'a mut [u8], seq: u16) -> Option<Completion<'a>> {
+    let deadline = Instant::now() + Duration::from_millis(PENDING_TIMEOUT_MS);
+    while Instant::now() < deadline {
+        let sz = match read_cmd(dh, buf) {
+            Ok(sz) => sz,
+            // read_cmd timeout, keep waiting
+            Err(_) => continue,
+        };
+        let mut parser = ParseMsg::new();
+        match parser.try_parse(&buf[..sz]) {
+            Ok(Msg(AnswerCode::ASYNC_DONE, _, v)) => match Completion::try_from(v) {
+                Ok(done) if done.seq == seq => {
+                    // Reparse to hand out borrow of whole buffer
+                    let Msg(_, _, v) = ParseMsg::new().try_parse(&buf[..sz]).ok()?;
+                    return Completion::try_from(v).ok();
+                }
+                Ok(done) => println!("completion of other request: {:?}", done),
+                Err(e) => println!("malformed completion: {:?}", e),
+            },
+            Ok(msg) => println!("unexpected msg: {:?}", msg),
+            Err(e) => println!("parse error: {:?}", e),
+        }
+    }
+    None
+}
+
+/// Read BYTES/STR value of any size with READ_CHUNK requests
+fn read_chunked(dh: &libusb::DeviceHandle, path: &str) -> Result<Vec<u8>, String> {
+    use ellocopo2::chunk::ChunkReader;
+
+    let mut buf = [0x0u8;MAX_MSG_SZ];
+    let mut reader = ChunkReader::new(path);
+    let mut data = Vec::new();
+    while let Some(sz) = reader.request(&mut buf) {
+        write_cmd(dh, &buf[..sz]).map_err(|_| "write failed")?;
+        let sz = read_cmd(dh, &mut buf).map_err(|_| "read failed")?;
+        let msg = ParseMsg::new().try_parse(&buf[..sz]).map_err(|e| format!("{:?}", e))?;
+        if msg.0 != AnswerCode::OK_READ_CHUNK {
+            print_error(&msg);
+            return Err(format!("chunk at {} failed", data.len()));
+        }
+        let chunk = reader.accept(msg.2).map_err(|e| format!("{:?}", e))?;
+        data.extend_from_slice(chunk.data);
+    }
+    Ok(data)
+}
+
+/// Write BYTES value of any size with WRITE_CHUNK requests
+fn write_chunked(dh: &libusb::DeviceHandle, path: &str, data: &[u8]) -> Result<(), String> {
+    use ellocopo2::chunk::ChunkWriter;
+
+    let mut buf = [0x0u8;MAX_MSG_SZ];
+    let mut writer = ChunkWriter::new(path, Value::BYTES(data));
+    while let Some(sz) = writer.request(&mut buf) {
+        write_cmd(dh, &buf[..sz]).map_err(|_| "write failed")?;
+        let sz = read_cmd(dh, &mut buf).map_err(|_| "read failed")?;
+        let msg = ParseMsg::new().try_parse(&buf[..sz]).map_err(|e| format!("{:?}", e))?;
+        if msg.0 != AnswerCode::OK_WRITE_CHUNK {
+            print_error(&msg);
+            return Err("chunk write failed".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Send request and parse answer into `buf`
+fn exchange<'a>(dh: &libusb::DeviceHandle, buf: &'a mut [u8], sz: usize) -> Result<Msg<'a>, String> {
+    write_cmd(dh, &buf[..sz]).map_err(|_| "write failed")?;
+    let sz = read_cmd(dh, buf).map_err(|_| "read failed")?;
+    ParseMsg::new().try_parse(&buf[..sz]).map_err(|e| format!("{:?}", e))
+}
+
+/// CHALLENGE for `lvl`, then AUTH with HMAC of the nonce, empty `lvl` drops session
+fn authenticate(dh: &libusb::DeviceHandle, lvl: &str) -> Result<PrivLvl, String> {
+    let mut buf = [0x0u8;MAX_MSG_SZ];
+    if lvl.is_empty() {
+        let sz = RequestBuilder::new(&mut buf).code(RequestCode::AUTH).path("/").build()?;
+        return match exchange(dh, &mut buf, sz)? {
+            Msg(AnswerCode::OK_AUTH, _, Value::U8(l)) => PrivLvl::try_from(l).map_err(|_| "bad level".to_string()),
+            msg => Err(format!("{:?}", msg)),
+        };
+    }
+
+    let lvl = lvl.parse::<u8>().ok()
+        .and_then(|l| PrivLvl::try_from(l).ok())
+        .ok_or(format!("bad level: {}", lvl))?;
+    let secret = std::env::var("ELLOCOPO_SECRET").map_err(|_| "ELLOCOPO_SECRET is not set")?;
+
+    let sz = RequestBuilder::new(&mut buf)
+        .code(RequestCode::CHALLENGE)
+        .path("/")
+        .payload(Value::U8(lvl as u8))
+        .build()?;
+    let response = match exchange(dh, &mut buf, sz)? {
+        Msg(AnswerCode::OK_CHALLENGE, _, Value::BYTES(nonce)) => auth::mac(secret.as_bytes(), nonce, lvl),
+        msg => {
+            print_error(&msg);
+            return Err("challenge refused".to_string());
+        }
+    };
+
+    let sz = RequestBuilder::new(&mut buf)
+        .code(RequestCode::AUTH)
+        .path("/")
+        .payload(Value::BYTES(&response))
+        .build()?;
+    match exchange(dh, &mut buf, sz)? {
+        Msg(AnswerCode::OK_AUTH, _, Value::U8(l)) => PrivLvl::try_from(l).map_err(|_| "bad level".to_string()),
+        msg => {
+            print_error(&msg);
+            Err("authentication failed".to_string())
+        }
+    }
+}
+
+fn print_error(msg: &Msg) {
+    let Msg(code, _, v) = *msg;
+    match code {
+        AnswerCode::OK_READ | AnswerCode::OK_WRITE | AnswerCode::OK_RESET
+            | AnswerCode::OK_CAS | AnswerCode::OK_WRITE_VERIFY
+            | AnswerCode::OK_BEGIN | AnswerCode::OK_COMMIT | AnswerCode::OK_ABORT
+            | AnswerCode::OK_READ_CHUNK | AnswerCode::OK_WRITE_CHUNK
+            | AnswerCode::OK_CHALLENGE | AnswerCode::OK_AUTH
+            | AnswerCode::OK_PENDING | AnswerCode::ASYNC_DONE => return,
+        AnswerCode::ERR_MISMATCH => return println!("error: {:?}, current value: {:?}", code, v),
+        _ => (),
+    }
+    match ErrDetail::try_from(v) {
+        Ok(detail) => println!("error: {}", AnswerErr(code, detail)),
+        Err(_) => println!("error: {:?}, malformed detail: {:?}", code, v),
     }
 }
 
 fn interpret(i: &str, buf: &mut [u8]) -> Result<usize, String> {
+    let txn = match i.trim() {
+        "begin" => Some(RequestCode::BEGIN),
+        "commit" => Some(RequestCode::COMMIT),
+        "abort" => Some(RequestCode::ABORT),
+        _ => None,
+    };
+    if let Some(code) = txn {
+        return RequestBuilder::new(buf)
+            .code(code)
+            .path("/")
+            .build()
+            .map_err(|e| e.to_string());
+    }
+
     let args: Vec<&str> =  { 
         if let Some(idx) = i.find(' ') {
             let parts = i.split_at(idx);
@@ -54,10 +266,38 @@ fn interpret(i: &str, buf: &mut [u8]) -> Result<usize, String> {
                || path.starts_with("w") 
                || path.starts_with("W") {
         (RequestCode::WRITE, &path[1..])
+    } else if path.starts_with("!") {
+        (RequestCode::RESET, &path[1..])
+    } else if path.starts_with("v") || path.starts_with("V") {
+        (RequestCode::WRITE_VERIFY, &path[1..])
+    } else if path.starts_with("?") {
+        (RequestCode::CAS, &path[1..])
     } else {
         (RequestCode::READ, path)
     };
     
+    // ?path expected new
+    if code == RequestCode::CAS {
+        let (expected, new) = args.get(1)
+            .and_then(|a| a.trim().split_once(' '))
+            .ok_or("CAS needs expected and new values")?;
+        let (mut expected_buf, mut new_buf) = (Vec::new(), Vec::new());
+        let expected = parse_value(expected, &mut expected_buf).map_err(|e| format!("{}", e))?;
+        let new = parse_value(new.trim(), &mut new_buf).map_err(|e| format!("{}", e))?;
+
+        if TypeTag::from(&expected) != TypeTag::from(&new) {
+            return Err(format!("CAS values of different types: {:?}, {:?}", expected, new));
+        }
+
+        println!("{:?}::{}::{:?} -> {:?}", code, &path, &expected, &new);
+
+        return RequestBuilder::new(buf)
+            .path(path)
+            .cas(expected, new)
+            .build()
+            .map_err(|e| e.to_string());
+    }
+
     let mut tmp_buf = Vec::new();
     let val = if args.len() == 1 {
         Value::UNIT(())
@@ -124,6 +364,25 @@ mod tests {
         usb_e
     }
 
+    #[test]
+    fn commands() {
+        assert_eq!(command("rc /io/file/data"), Command::ReadChunked("/io/file/data"));
+        assert_eq!(command("wc /io/file/data fw.bin"), Command::WriteChunked("/io/file/data fw.bin"));
+        assert_eq!(command("auth 2"), Command::Auth("2"));
+        assert_eq!(command("auth"), Command::Auth(""));
+        for line in ["begin", "commit", " abort ", "/ctrl/record", "w/calib/k 1", "?/calib/k 1 2"] {
+            assert_eq!(command(line), Command::Request, "{:?}", line);
+        }
+
+        let mut buf = [0x00u8;MAX_MSG_SZ];
+        for (line, code) in [("begin", RequestCode::BEGIN), ("commit", RequestCode::COMMIT), ("abort", RequestCode::ABORT)] {
+            interpret(line, &mut buf).unwrap();
+            // Header code and path "/" right after header
+            assert_eq!(buf[3], code as u8, "{}", line);
+            assert_eq!((buf[1], buf[5]), (1, b'/'), "{}", line);
+        }
+    }
+
     #[test]
     fn vis_test() {
         let dev = fixture_init();
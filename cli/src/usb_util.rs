@@ -15,6 +15,8 @@ const EP_DATA_IN: u8 = 0x82;
 
 const BULK_TRANSFER_TIMEOUT_MS: u64 = 500;
 const DATA_TIMEOUT_MS: u64 = 300;
+/// How long to wait for completion of OK_PENDING request
+pub const PENDING_TIMEOUT_MS: u64 = 30_000;
 
 pub struct LibUsbEntity<'a> {
     pub d: Device<'a>,
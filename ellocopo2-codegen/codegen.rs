@@ -1 +1 @@
-pub mod msg { # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Acc { B_W ( i32 ) , B_R , K_W ( i32 ) , K_R , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Ecg { B_W ( i32 ) , B_R , K_W ( i32 ) , K_R , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Reo { B_W ( i32 ) , B_R , K_W ( i32 ) , K_R , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Calib { Acc ( Acc ) , Ecg ( Ecg ) , Reo ( Reo ) , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Conf { Cyclic_W ( bool ) , Cyclic_R , Time_W ( u32 ) , Time_R , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Ctrl < 'a > { Erase_W ( ( ) ) , Event_W ( & 'a [ u8 ] ) , GotoLoader_W ( ( ) ) , Record_W ( bool ) , Record_R , Vis_W ( bool ) , Vis_R , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Dbg { Flags_R , Last_R , LastCode_R , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Desc { Serial_R , Type_R , Version_R , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum File { Len_W ( u32 ) , Len_R , Max_R , Pos_W ( u32 ) , Pos_R , Start_W ( ( ) ) , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Store < 'a > { Idx_W ( u8 ) , Idx_R , Line_W ( & 'a [ u8 ] ) , Line_R , Max_R , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Io < 'a > { File ( File ) , Status_W ( u32 ) , Status_R , Store ( Store < 'a > ) , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Ecgf { Frq_W ( u8 ) , Frq_R , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Reof { Frq_W ( u8 ) , Frq_R , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Signal { Ecgf ( Ecgf ) , Reof ( Reof ) , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum State { Current_R , StopReason_R , Voltage_R , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Survey < 'a > { Birth_W ( & 'a str ) , Birth_R , Id_W ( u32 ) , Id_R , Name_W ( & 'a str ) , Name_R , PatientId_W ( & 'a str ) , PatientId_R , Patronymic_W ( & 'a str ) , Patronymic_R , Sex_W ( & 'a str ) , Sex_R , StartTime_W ( u32 ) , StartTime_R , Surname_W ( & 'a str ) , Surname_R , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Test < 'a > { Echo_W ( & 'a [ u8 ] ) , Echo_R , Error_W ( & 'a str ) , Error_R , Test1_W ( & 'a [ u8 ] ) , Test1_R , Test2_W ( & 'a [ u8 ] ) , Test2_R , Test3_W ( & 'a [ u8 ] ) , Test3_R , Test4_W ( & 'a [ u8 ] ) , Test4_R , Test5_W ( & 'a [ u8 ] ) , Test5_R , } # [ allow ( non_camel_case_types ) ] # [ derive ( Debug , Clone , Copy , PartialEq , Eq ) ] pub enum Msg < 'a > { Calib ( Calib ) , Conf ( Conf ) , Ctrl ( Ctrl < 'a > ) , Dbg ( Dbg ) , Desc ( Desc ) , Io ( Io < 'a > ) , Signal ( Signal ) , State ( State ) , Survey ( Survey < 'a > ) , Test ( Test < 'a > ) , Time_W ( u32 ) , Time_R , } } pub use msg :: * ; pub fn req2msg < 'a > ( code : RequestCode , path : & str , v : Value < 'a > , sys_lvl : PrivLvl ) -> DispatchResult < 'a > { if code == RequestCode :: READ { let _ : ( ) = map_ty_error ! ( v ) ; } match path { "/build/compiler" => { impl_arm ! ( FAST , RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , cb_build_compiler_r ( ) ) } "/build/git" => { impl_arm ! ( FAST , RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , cb_build_git_r ( ) ) } "/build/host" => { impl_arm ! ( FAST , RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , cb_build_host_r ( ) ) } "/build/profile" => { impl_arm ! ( FAST , RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , cb_build_profile_r ( ) ) } "/build/target" => { impl_arm ! ( FAST , RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , cb_build_target_r ( ) ) } "/build/time" => { impl_arm ! ( FAST , RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , cb_build_time_r ( ) ) } "/build/version" => { impl_arm ! ( FAST , RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , cb_build_version_r ( ) ) } "/calib/acc/b" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Calib ( Calib :: Acc ( Acc :: B_W ( map_ty_error ! ( v ) ) ) ) , Msg :: Calib ( Calib :: Acc ( Acc :: B_R ) ) ) } "/calib/acc/k" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Calib ( Calib :: Acc ( Acc :: K_W ( map_ty_error ! ( v ) ) ) ) , Msg :: Calib ( Calib :: Acc ( Acc :: K_R ) ) ) } "/calib/ecg/b" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Calib ( Calib :: Ecg ( Ecg :: B_W ( map_ty_error ! ( v ) ) ) ) , Msg :: Calib ( Calib :: Ecg ( Ecg :: B_R ) ) ) } "/calib/ecg/k" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Calib ( Calib :: Ecg ( Ecg :: K_W ( map_ty_error ! ( v ) ) ) ) , Msg :: Calib ( Calib :: Ecg ( Ecg :: K_R ) ) ) } "/calib/reo/b" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Calib ( Calib :: Reo ( Reo :: B_W ( map_ty_error ! ( v ) ) ) ) , Msg :: Calib ( Calib :: Reo ( Reo :: B_R ) ) ) } "/calib/reo/k" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Calib ( Calib :: Reo ( Reo :: K_W ( map_ty_error ! ( v ) ) ) ) , Msg :: Calib ( Calib :: Reo ( Reo :: K_R ) ) ) } "/conf/cyclic" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Conf ( Conf :: Cyclic_W ( map_ty_error ! ( v ) ) ) , Msg :: Conf ( Conf :: Cyclic_R ) ) } "/conf/time" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Conf ( Conf :: Time_W ( map_ty_error ! ( v ) ) ) , Msg :: Conf ( Conf :: Time_R ) ) } "/ctrl/erase" => { impl_arm ! ( WO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Ctrl ( Ctrl :: Erase_W ( map_ty_error ! ( v ) ) ) ) } "/ctrl/event" => { impl_arm ! ( WO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Ctrl ( Ctrl :: Event_W ( map_ty_error ! ( v ) ) ) ) } "/ctrl/goto_loader" => { impl_arm ! ( WO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Ctrl ( Ctrl :: GotoLoader_W ( map_ty_error ! ( v ) ) ) ) } "/ctrl/record" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Ctrl ( Ctrl :: Record_W ( map_ty_error ! ( v ) ) ) , Msg :: Ctrl ( Ctrl :: Record_R ) ) } "/ctrl/vis" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Ctrl ( Ctrl :: Vis_W ( map_ty_error ! ( v ) ) ) , Msg :: Ctrl ( Ctrl :: Vis_R ) ) } "/dbg/flags" => { impl_arm ! ( RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Dbg ( Dbg :: Flags_R ) ) } "/dbg/last" => { impl_arm ! ( RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Dbg ( Dbg :: Last_R ) ) } "/dbg/last_code" => { impl_arm ! ( RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Dbg ( Dbg :: LastCode_R ) ) } "/desc/serial" => { impl_arm ! ( RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Desc ( Desc :: Serial_R ) ) } "/desc/type" => { impl_arm ! ( RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Desc ( Desc :: Type_R ) ) } "/desc/version" => { impl_arm ! ( RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Desc ( Desc :: Version_R ) ) } "/io/file/len" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Io ( Io :: File ( File :: Len_W ( map_ty_error ! ( v ) ) ) ) , Msg :: Io ( Io :: File ( File :: Len_R ) ) ) } "/io/file/max" => { impl_arm ! ( RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Io ( Io :: File ( File :: Max_R ) ) ) } "/io/file/pos" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Io ( Io :: File ( File :: Pos_W ( map_ty_error ! ( v ) ) ) ) , Msg :: Io ( Io :: File ( File :: Pos_R ) ) ) } "/io/file/start" => { impl_arm ! ( WO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Io ( Io :: File ( File :: Start_W ( map_ty_error ! ( v ) ) ) ) ) } "/io/status" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Io ( Io :: Status_W ( map_ty_error ! ( v ) ) ) , Msg :: Io ( Io :: Status_R ) ) } "/io/store/idx" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Io ( Io :: Store ( Store :: Idx_W ( map_ty_error ! ( v ) ) ) ) , Msg :: Io ( Io :: Store ( Store :: Idx_R ) ) ) } "/io/store/line" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Io ( Io :: Store ( Store :: Line_W ( map_ty_error ! ( v ) ) ) ) , Msg :: Io ( Io :: Store ( Store :: Line_R ) ) ) } "/io/store/max" => { impl_arm ! ( RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Io ( Io :: Store ( Store :: Max_R ) ) ) } "/signal/ecgf/frq" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Signal ( Signal :: Ecgf ( Ecgf :: Frq_W ( map_ty_error ! ( v ) ) ) ) , Msg :: Signal ( Signal :: Ecgf ( Ecgf :: Frq_R ) ) ) } "/signal/reof/frq" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Signal ( Signal :: Reof ( Reof :: Frq_W ( map_ty_error ! ( v ) ) ) ) , Msg :: Signal ( Signal :: Reof ( Reof :: Frq_R ) ) ) } "/state/current" => { impl_arm ! ( RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: State ( State :: Current_R ) ) } "/state/stop_reason" => { impl_arm ! ( RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: State ( State :: StopReason_R ) ) } "/state/voltage" => { impl_arm ! ( RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: State ( State :: Voltage_R ) ) } "/survey/birth" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Survey ( Survey :: Birth_W ( map_ty_error ! ( v ) ) ) , Msg :: Survey ( Survey :: Birth_R ) ) } "/survey/id" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Survey ( Survey :: Id_W ( map_ty_error ! ( v ) ) ) , Msg :: Survey ( Survey :: Id_R ) ) } "/survey/name" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Survey ( Survey :: Name_W ( map_ty_error ! ( v ) ) ) , Msg :: Survey ( Survey :: Name_R ) ) } "/survey/patient_id" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Survey ( Survey :: PatientId_W ( map_ty_error ! ( v ) ) ) , Msg :: Survey ( Survey :: PatientId_R ) ) } "/survey/patronymic" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Survey ( Survey :: Patronymic_W ( map_ty_error ! ( v ) ) ) , Msg :: Survey ( Survey :: Patronymic_R ) ) } "/survey/sex" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Survey ( Survey :: Sex_W ( map_ty_error ! ( v ) ) ) , Msg :: Survey ( Survey :: Sex_R ) ) } "/survey/start_time" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Survey ( Survey :: StartTime_W ( map_ty_error ! ( v ) ) ) , Msg :: Survey ( Survey :: StartTime_R ) ) } "/survey/surname" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Survey ( Survey :: Surname_W ( map_ty_error ! ( v ) ) ) , Msg :: Survey ( Survey :: Surname_R ) ) } "/test/echo" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Test ( Test :: Echo_W ( map_ty_error ! ( v ) ) ) , Msg :: Test ( Test :: Echo_R ) ) } "/test/error" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Test ( Test :: Error_W ( map_ty_error ! ( v ) ) ) , Msg :: Test ( Test :: Error_R ) ) } "/test/test1" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Test ( Test :: Test1_W ( map_ty_error ! ( v ) ) ) , Msg :: Test ( Test :: Test1_R ) ) } "/test/test2" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Test ( Test :: Test2_W ( map_ty_error ! ( v ) ) ) , Msg :: Test ( Test :: Test2_R ) ) } "/test/test3" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Test ( Test :: Test3_W ( map_ty_error ! ( v ) ) ) , Msg :: Test ( Test :: Test3_R ) ) } "/test/test4" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Test ( Test :: Test4_W ( map_ty_error ! ( v ) ) ) , Msg :: Test ( Test :: Test4_R ) ) } "/test/test5" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Test ( Test :: Test5_W ( map_ty_error ! ( v ) ) ) , Msg :: Test ( Test :: Test5_R ) ) } "/time" => { impl_arm ! ( RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , Msg :: Time_W ( map_ty_error ! ( v ) ) , Msg :: Time_R ) } _ => { DispatchResult :: Err ( AnswerCode :: ERR_PATH ) } } }
\ No newline at end of file
+# [doc = r" `@protocol_version` of the scheme"] pub const PROTOCOL_VERSION : & str = "1.0" ; pub const PROTOCOL_VERSION_MAJOR : u16 = 1u16 ; pub const PROTOCOL_VERSION_MINOR : u16 = 0u16 ; # [doc = r" `@visibility` of the scheme"] pub const VISIBILITY : & str = "public" ; pub mod msg { # [doc = " Requests to `/calib/acc`"] # [doc = ""] # [doc = " Калибровка <k, b>"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Acc { # [doc = " Write `/calib/acc/b`"] B_W (i32) , # [doc = " Read `/calib/acc/b`"] B_R , # [doc = " Write `/calib/acc/k`"] K_W (i32) , # [doc = " Read `/calib/acc/k`"] K_R , } # [doc = " Requests to `/calib/ecg`"] # [doc = ""] # [doc = " Калибровка <k, b>"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Ecg { # [doc = " Write `/calib/ecg/b`"] B_W (i32) , # [doc = " Read `/calib/ecg/b`"] B_R , # [doc = " Write `/calib/ecg/k`"] K_W (i32) , # [doc = " Read `/calib/ecg/k`"] K_R , } # [doc = " Requests to `/calib/reo`"] # [doc = ""] # [doc = " Калибровка <k, b>"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Reo { # [doc = " Write `/calib/reo/b`"] B_W (i32) , # [doc = " Read `/calib/reo/b`"] B_R , # [doc = " Write `/calib/reo/k`"] K_W (i32) , # [doc = " Read `/calib/reo/k`"] K_R , } # [doc = " Requests to `/calib`"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Calib { Acc (Acc) , Ecg (Ecg) , Reo (Reo) , } # [doc = " Requests to `/conf`"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Conf { # [doc = " Write `/conf/cyclic`"] Cyclic_W (bool) , # [doc = " Read `/conf/cyclic`"] Cyclic_R , # [doc = " Write `/conf/time`"] Time_W (u32) , # [doc = " Read `/conf/time`"] Time_R , } # [doc = " Requests to `/ctrl`"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Ctrl < 'a > { # [doc = " Write `/ctrl/erase`"] Erase_W (()) , # [doc = " Write `/ctrl/event`"] Event_W (& 'a [u8]) , # [doc = " Write `/ctrl/goto_loader`"] GotoLoader_W (()) , # [doc = " Write `/ctrl/record`"] Record_W (bool) , # [doc = " Read `/ctrl/record`"] Record_R , # [doc = " Write `/ctrl/vis`"] Vis_W (bool) , # [doc = " Read `/ctrl/vis`"] Vis_R , } # [doc = " Requests to `/dbg`"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Dbg { # [doc = " Read `/dbg/flags`"] Flags_R , # [doc = " Read `/dbg/last`"] Last_R , # [doc = " Read `/dbg/last_code`"] LastCode_R , } # [doc = " Requests to `/desc`"] # [doc = ""] # [doc = " Общая инфорация об устройстве"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Desc { # [doc = " Read `/desc/serial`"] Serial_R , # [doc = " Read `/desc/type`"] Type_R , # [doc = " Read `/desc/version`"] # [doc = ""] # [doc = " Версия ПО"] Version_R , } # [doc = " Requests to `/io/file`"] # [doc = ""] # [doc = " pos, len, max в блоках"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum File { # [doc = " Write `/io/file/len`"] Len_W (u32) , # [doc = " Read `/io/file/len`"] Len_R , # [doc = " Read `/io/file/max`"] Max_R , # [doc = " Write `/io/file/pos`"] Pos_W (u32) , # [doc = " Read `/io/file/pos`"] Pos_R , # [doc = " Write `/io/file/start`"] Start_W (()) , } # [doc = " Requests to `/io/store`"] # [doc = ""] # [doc = " Max line size 64, max line count 64"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Store < 'a > { # [doc = " Write `/io/store/idx`"] Idx_W (u8) , # [doc = " Read `/io/store/idx`"] Idx_R , # [doc = " Write `/io/store/line`"] Line_W (& 'a [u8]) , # [doc = " Read `/io/store/line`"] Line_R , # [doc = " Read `/io/store/max`"] Max_R , } # [doc = " Requests to `/io`"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Io < 'a > { File (File) , # [doc = " Write `/io/status`"] Status_W (u32) , # [doc = " Read `/io/status`"] Status_R , Store (Store < 'a >) , } # [doc = " Requests to `/signal/ecgf`"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Ecgf { # [doc = " Write `/signal/ecgf/frq`"] # [doc = ""] # [doc = " Частота дискретизации ЭКГ, Гц"] Frq_W (u8) , # [doc = " Read `/signal/ecgf/frq`"] # [doc = ""] # [doc = " Частота дискретизации ЭКГ, Гц"] Frq_R , } # [doc = " Requests to `/signal/reof`"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Reof { # [doc = " Write `/signal/reof/frq`"] Frq_W (u8) , # [doc = " Read `/signal/reof/frq`"] Frq_R , } # [doc = " Requests to `/signal`"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Signal { Ecgf (Ecgf) , Reof (Reof) , } # [doc = " Requests to `/state`"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum State { # [doc = " Read `/state/current`"] Current_R , # [doc = " Read `/state/stop_reason`"] StopReason_R , # [doc = " Read `/state/voltage`"] Voltage_R , } # [doc = " Requests to `/survey`"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Survey < 'a > { # [doc = " Write `/survey/birth`"] Birth_W (& 'a str) , # [doc = " Read `/survey/birth`"] Birth_R , # [doc = " Write `/survey/id`"] Id_W (u32) , # [doc = " Read `/survey/id`"] Id_R , # [doc = " Write `/survey/name`"] Name_W (& 'a str) , # [doc = " Read `/survey/name`"] Name_R , # [doc = " Write `/survey/patient_id`"] PatientId_W (& 'a str) , # [doc = " Read `/survey/patient_id`"] PatientId_R , # [doc = " Write `/survey/patronymic`"] Patronymic_W (& 'a str) , # [doc = " Read `/survey/patronymic`"] Patronymic_R , # [doc = " Write `/survey/sex`"] Sex_W (& 'a str) , # [doc = " Read `/survey/sex`"] Sex_R , # [doc = " Write `/survey/start_time`"] StartTime_W (u32) , # [doc = " Read `/survey/start_time`"] StartTime_R , # [doc = " Write `/survey/surname`"] Surname_W (& 'a str) , # [doc = " Read `/survey/surname`"] Surname_R , } # [doc = " Requests to `/test/slot/0/pin/<index>`"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Pin { # [doc = " Write `/test/slot/0/pin/0/level`"] Level_W (bool) , # [doc = " Read `/test/slot/0/pin/0/level`"] Level_R , } # [doc = " Requests to `/test/slot/<index>`"] # [doc = ""] # [doc = " Тестовый массив секций /test/slot/0../test/slot/3"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Slot < 'a > { # [doc = " Write `/test/slot/0/data`"] Data_W (& 'a [u8]) , # [doc = " Read `/test/slot/0/data`"] Data_R , Pin (u8 , Pin) , # [doc = " Write `/test/slot/0/used`"] Used_W (bool) , # [doc = " Read `/test/slot/0/used`"] Used_R , } # [doc = " Requests to `/test`"] # [doc = ""] # [doc = " Тестовые регистры"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Test < 'a > { # [doc = " Write `/test/echo`"] # [doc = ""] # [doc = " Эхо тест"] Echo_W (& 'a [u8]) , # [doc = " Read `/test/echo`"] # [doc = ""] # [doc = " Эхо тест"] Echo_R , # [doc = " Write `/test/error`"] Error_W (& 'a str) , # [doc = " Read `/test/error`"] Error_R , Slot (u8 , Slot < 'a >) , # [doc = " Write `/test/test1`"] Test1_W (& 'a [u8]) , # [doc = " Read `/test/test1`"] Test1_R , # [doc = " Write `/test/test2`"] Test2_W (& 'a [u8]) , # [doc = " Read `/test/test2`"] Test2_R , # [doc = " Write `/test/test3`"] Test3_W (& 'a [u8]) , # [doc = " Read `/test/test3`"] Test3_R , # [doc = " Write `/test/test4`"] Test4_W (& 'a [u8]) , # [doc = " Read `/test/test4`"] Test4_R , # [doc = " Write `/test/test5`"] Test5_W (& 'a [u8]) , # [doc = " Read `/test/test5`"] Test5_R , } # [doc = " Requests of the scheme"] # [doc = ""] # [doc = " Это файл описания протокола и только! А не самого устройства c его форматами и функциональностью!"] # [doc = " Стандартный @access = RO, Если у поля нет вложенных значений, значит указан его тип"] # [doc = " [sign][path_sz][payload_sz][op][ty][path...][payload...]"] # [doc = " header max len = 256, payload max len = 256, msg max len = 512"] # [doc = " max path len = 256 - 5, max payload data len = 256"] # [allow (non_camel_case_types)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum Msg < 'a > { Calib (Calib) , Conf (Conf) , Ctrl (Ctrl < 'a >) , Dbg (Dbg) , Desc (Desc) , Io (Io < 'a >) , Signal (Signal) , State (State) , Survey (Survey < 'a >) , Test (Test < 'a >) , # [doc = " Write `/time`"] Time_W (u32) , # [doc = " Read `/time`"] Time_R , } } pub use msg :: * ; # [doc = r" `@fast` registers, answered right in `req2msg` instead of becoming `Msg`"] # [doc = r""] # [doc = r" Every one of them has to be implemented, a register the device"] # [doc = r" can't serve answers `Err(AnswerCode::ERR_UNIMPL)` explicitly."] # [allow (non_snake_case)] pub trait Handler { # [doc = " `@fast` read of `/build/compiler`"] fn build_compiler_read (& mut self) -> Result < & 'static str , AnswerCode > ; # [doc = " `@fast` read of `/build/git`"] fn build_git_read (& mut self) -> Result < & 'static str , AnswerCode > ; # [doc = " `@fast` read of `/build/host`"] fn build_host_read (& mut self) -> Result < & 'static str , AnswerCode > ; # [doc = " `@fast` read of `/build/profile`"] fn build_profile_read (& mut self) -> Result < & 'static str , AnswerCode > ; # [doc = " `@fast` read of `/build/target`"] fn build_target_read (& mut self) -> Result < & 'static str , AnswerCode > ; # [doc = " `@fast` read of `/build/time`"] fn build_time_read (& mut self) -> Result < & 'static str , AnswerCode > ; # [doc = " `@fast` read of `/build/version`"] fn build_version_read (& mut self) -> Result < & 'static str , AnswerCode > ; } # [doc = r" Handler of write-only dispatch, refuses reads it's never asked for"] # [allow (dead_code)] struct WriteOnly ; # [allow (non_snake_case)] impl Handler for WriteOnly { fn build_compiler_read (& mut self) -> Result < & 'static str , AnswerCode > { Err (AnswerCode :: ERR_ACCESS) } fn build_git_read (& mut self) -> Result < & 'static str , AnswerCode > { Err (AnswerCode :: ERR_ACCESS) } fn build_host_read (& mut self) -> Result < & 'static str , AnswerCode > { Err (AnswerCode :: ERR_ACCESS) } fn build_profile_read (& mut self) -> Result < & 'static str , AnswerCode > { Err (AnswerCode :: ERR_ACCESS) } fn build_target_read (& mut self) -> Result < & 'static str , AnswerCode > { Err (AnswerCode :: ERR_ACCESS) } fn build_time_read (& mut self) -> Result < & 'static str , AnswerCode > { Err (AnswerCode :: ERR_ACCESS) } fn build_version_read (& mut self) -> Result < & 'static str , AnswerCode > { Err (AnswerCode :: ERR_ACCESS) } } # [doc = r" Full path of every register, scheme order"] pub const PATHS : [& str ; 71usize] = ["/build/compiler" , "/build/git" , "/build/host" , "/build/profile" , "/build/target" , "/build/time" , "/build/version" , "/calib/acc/b" , "/calib/acc/k" , "/calib/ecg/b" , "/calib/ecg/k" , "/calib/reo/b" , "/calib/reo/k" , "/conf/cyclic" , "/conf/time" , "/ctrl/erase" , "/ctrl/event" , "/ctrl/goto_loader" , "/ctrl/record" , "/ctrl/vis" , "/dbg/flags" , "/dbg/last" , "/dbg/last_code" , "/desc/serial" , "/desc/type" , "/desc/version" , "/io/file/len" , "/io/file/max" , "/io/file/pos" , "/io/file/start" , "/io/status" , "/io/store/idx" , "/io/store/line" , "/io/store/max" , "/signal/ecgf/frq" , "/signal/reof/frq" , "/state/current" , "/state/stop_reason" , "/state/voltage" , "/survey/birth" , "/survey/id" , "/survey/name" , "/survey/patient_id" , "/survey/patronymic" , "/survey/sex" , "/survey/start_time" , "/survey/surname" , "/test/echo" , "/test/error" , "/test/slot/0/data" , "/test/slot/0/pin/0/level" , "/test/slot/0/pin/1/level" , "/test/slot/0/used" , "/test/slot/1/data" , "/test/slot/1/pin/0/level" , "/test/slot/1/pin/1/level" , "/test/slot/1/used" , "/test/slot/2/data" , "/test/slot/2/pin/0/level" , "/test/slot/2/pin/1/level" , "/test/slot/2/used" , "/test/slot/3/data" , "/test/slot/3/pin/0/level" , "/test/slot/3/pin/1/level" , "/test/slot/3/used" , "/test/test1" , "/test/test2" , "/test/test3" , "/test/test4" , "/test/test5" , "/time"] ; # [doc = r" Index of register at `path` in `PATHS`, by perfect hash of full paths"] pub fn path2idx (path : & str) -> Option < usize > { const SLOTS : [u16 ; 512usize] = [0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 34u16 , 31u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 67u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 60u16 , 9u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 41u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 35u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 10u16 , 0u16 , 62u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 15u16 , 21u16 , 0u16 , 0u16 , 0u16 , 4u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 1u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 42u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 26u16 , 0u16 , 22u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 63u16 , 19u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 66u16 , 24u16 , 0u16 , 52u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 47u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 13u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 6u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 64u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 56u16 , 0u16 , 0u16 , 0u16 , 43u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 46u16 , 70u16 , 0u16 , 58u16 , 49u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 51u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 45u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 54u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 57u16 , 0u16 , 0u16 , 0u16 , 0u16 , 38u16 , 0u16 , 0u16 , 0u16 , 28u16 , 0u16 , 0u16 , 0u16 , 0u16 , 65u16 , 0u16 , 32u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 16u16 , 0u16 , 17u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 2u16 , 71u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 11u16 , 0u16 , 0u16 , 69u16 , 20u16 , 0u16 , 0u16 , 0u16 , 0u16 , 55u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 14u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 59u16 , 0u16 , 27u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 39u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 29u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 3u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 53u16 , 0u16 , 0u16 , 5u16 , 7u16 , 0u16 , 18u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 12u16 , 0u16 , 0u16 , 33u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 25u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 61u16 , 0u16 , 0u16 , 0u16 , 0u16 , 68u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 40u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 23u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 50u16 , 0u16 , 0u16 , 37u16 , 0u16 , 48u16 , 0u16 , 36u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 30u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 44u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 8u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16 , 0u16] ; let b = path . as_bytes () ; let key = & b [b . len () . saturating_sub (13usize) ..] ; let i = SLOTS [fnv1a (44u32 ^ b . len () as u32 , key) as usize & 511usize] as usize ; if i != 0 && PATHS [i - 1] == path { Some (i - 1) } else { None } } # [allow (unused_variables)] pub fn req2msg < 'a , H : Handler > (h : & mut H , code : RequestCode , path : & str , v : Value < 'a > , sys_lvl : PrivLvl) -> DispatchResult < msg :: Msg < 'a > , & 'static [msg :: Msg < 'static >] > { if code == RequestCode :: READ { let _ : () = map_ty_error ! (v) ; } if code == RequestCode :: RESET { let _ : () = map_ty_error ! (v) ; return match reset2msgs (path) { Ok (_) if sys_lvl < reset_priv (path) => DispatchResult :: Err (AnswerCode :: ERR_PRIV) , Ok (defaults) => DispatchResult :: Reset (defaults) , Err (e) => DispatchResult :: Err (e) , } ; } match path2idx (path) { Some (0usize) => { impl_arm ! (FAST , RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , match h . build_compiler_read () { Ok (v) => Value :: STR (v) , Err (e) => return DispatchResult :: Err (e) }) } Some (1usize) => { impl_arm ! (FAST , RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , match h . build_git_read () { Ok (v) => Value :: STR (v) , Err (e) => return DispatchResult :: Err (e) }) } Some (2usize) => { impl_arm ! (FAST , RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , match h . build_host_read () { Ok (v) => Value :: STR (v) , Err (e) => return DispatchResult :: Err (e) }) } Some (3usize) => { impl_arm ! (FAST , RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , match h . build_profile_read () { Ok (v) => Value :: STR (v) , Err (e) => return DispatchResult :: Err (e) }) } Some (4usize) => { impl_arm ! (FAST , RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , match h . build_target_read () { Ok (v) => Value :: STR (v) , Err (e) => return DispatchResult :: Err (e) }) } Some (5usize) => { impl_arm ! (FAST , RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , match h . build_time_read () { Ok (v) => Value :: STR (v) , Err (e) => return DispatchResult :: Err (e) }) } Some (6usize) => { impl_arm ! (FAST , RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , match h . build_version_read () { Ok (v) => Value :: STR (v) , Err (e) => return DispatchResult :: Err (e) }) } Some (7usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: SECUR_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Calib (msg :: Calib :: Acc (msg :: Acc :: B_W (map_ty_error ! (v)))) , msg :: Msg :: Calib (msg :: Calib :: Acc (msg :: Acc :: B_R))) } Some (8usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: SECUR_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Calib (msg :: Calib :: Acc (msg :: Acc :: K_W (map_ty_error ! (v)))) , msg :: Msg :: Calib (msg :: Calib :: Acc (msg :: Acc :: K_R))) } Some (9usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: SECUR_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Calib (msg :: Calib :: Ecg (msg :: Ecg :: B_W (map_ty_error ! (v)))) , msg :: Msg :: Calib (msg :: Calib :: Ecg (msg :: Ecg :: B_R))) } Some (10usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: SECUR_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Calib (msg :: Calib :: Ecg (msg :: Ecg :: K_W (map_ty_error ! (v)))) , msg :: Msg :: Calib (msg :: Calib :: Ecg (msg :: Ecg :: K_R))) } Some (11usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: SECUR_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Calib (msg :: Calib :: Reo (msg :: Reo :: B_W (map_ty_error ! (v)))) , msg :: Msg :: Calib (msg :: Calib :: Reo (msg :: Reo :: B_R))) } Some (12usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: SECUR_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Calib (msg :: Calib :: Reo (msg :: Reo :: K_W (map_ty_error ! (v)))) , msg :: Msg :: Calib (msg :: Calib :: Reo (msg :: Reo :: K_R))) } Some (13usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Conf (msg :: Conf :: Cyclic_W (map_ty_error ! (v))) , msg :: Msg :: Conf (msg :: Conf :: Cyclic_R)) } Some (14usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Conf (msg :: Conf :: Time_W (map_ty_error ! (v))) , msg :: Msg :: Conf (msg :: Conf :: Time_R)) } Some (15usize) => { impl_arm ! (WO , code , sys_lvl , PrivLvl :: NORMAL_LVL , msg :: Msg :: Ctrl (msg :: Ctrl :: Erase_W (map_ty_error ! (v)))) } Some (16usize) => { impl_arm ! (WO , code , sys_lvl , PrivLvl :: NORMAL_LVL , msg :: Msg :: Ctrl (msg :: Ctrl :: Event_W (map_ty_error ! (v)))) } Some (17usize) => { impl_arm ! (WO , code , sys_lvl , PrivLvl :: DEVEL_LVL , msg :: Msg :: Ctrl (msg :: Ctrl :: GotoLoader_W (map_ty_error ! (v)))) } Some (18usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Ctrl (msg :: Ctrl :: Record_W (map_ty_error ! (v))) , msg :: Msg :: Ctrl (msg :: Ctrl :: Record_R)) } Some (19usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Ctrl (msg :: Ctrl :: Vis_W (map_ty_error ! (v))) , msg :: Msg :: Ctrl (msg :: Ctrl :: Vis_R)) } Some (20usize) => { impl_arm ! (RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , msg :: Msg :: Dbg (msg :: Dbg :: Flags_R)) } Some (21usize) => { impl_arm ! (RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , msg :: Msg :: Dbg (msg :: Dbg :: Last_R)) } Some (22usize) => { impl_arm ! (RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , msg :: Msg :: Dbg (msg :: Dbg :: LastCode_R)) } Some (23usize) => { impl_arm ! (RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , msg :: Msg :: Desc (msg :: Desc :: Serial_R)) } Some (24usize) => { impl_arm ! (RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , msg :: Msg :: Desc (msg :: Desc :: Type_R)) } Some (25usize) => { impl_arm ! (RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , msg :: Msg :: Desc (msg :: Desc :: Version_R)) } Some (26usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Io (msg :: Io :: File (msg :: File :: Len_W (map_ty_error ! (v)))) , msg :: Msg :: Io (msg :: Io :: File (msg :: File :: Len_R))) } Some (27usize) => { impl_arm ! (RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , msg :: Msg :: Io (msg :: Io :: File (msg :: File :: Max_R))) } Some (28usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Io (msg :: Io :: File (msg :: File :: Pos_W (map_ty_error ! (v)))) , msg :: Msg :: Io (msg :: Io :: File (msg :: File :: Pos_R))) } Some (29usize) => { impl_arm ! (WO , code , sys_lvl , PrivLvl :: NORMAL_LVL , msg :: Msg :: Io (msg :: Io :: File (msg :: File :: Start_W (map_ty_error ! (v))))) } Some (30usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Io (msg :: Io :: Status_W (map_ty_error ! (v))) , msg :: Msg :: Io (msg :: Io :: Status_R)) } Some (31usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Io (msg :: Io :: Store (msg :: Store :: Idx_W (map_ty_error ! (v)))) , msg :: Msg :: Io (msg :: Io :: Store (msg :: Store :: Idx_R))) } Some (32usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Io (msg :: Io :: Store (msg :: Store :: Line_W (map_ty_error ! (v)))) , msg :: Msg :: Io (msg :: Io :: Store (msg :: Store :: Line_R))) } Some (33usize) => { impl_arm ! (RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , msg :: Msg :: Io (msg :: Io :: Store (msg :: Store :: Max_R))) } Some (34usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Signal (msg :: Signal :: Ecgf (msg :: Ecgf :: Frq_W (map_range_error ! (map_ty_error ! (v) , | x : u8 | (1 ..= 200) . contains (& x))))) , msg :: Msg :: Signal (msg :: Signal :: Ecgf (msg :: Ecgf :: Frq_R))) } Some (35usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Signal (msg :: Signal :: Reof (msg :: Reof :: Frq_W (map_ty_error ! (v)))) , msg :: Msg :: Signal (msg :: Signal :: Reof (msg :: Reof :: Frq_R))) } Some (36usize) => { impl_arm ! (RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , msg :: Msg :: State (msg :: State :: Current_R)) } Some (37usize) => { impl_arm ! (RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , msg :: Msg :: State (msg :: State :: StopReason_R)) } Some (38usize) => { impl_arm ! (RO , code , sys_lvl , PrivLvl :: NORMAL_LVL , msg :: Msg :: State (msg :: State :: Voltage_R)) } Some (39usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Survey (msg :: Survey :: Birth_W (map_range_error ! (map_ty_error ! (v) , | x : & 'a str | x . len () <= 10usize))) , msg :: Msg :: Survey (msg :: Survey :: Birth_R)) } Some (40usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Survey (msg :: Survey :: Id_W (map_ty_error ! (v))) , msg :: Msg :: Survey (msg :: Survey :: Id_R)) } Some (41usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Survey (msg :: Survey :: Name_W (map_range_error ! (map_ty_error ! (v) , | x : & 'a str | x . len () <= 64usize))) , msg :: Msg :: Survey (msg :: Survey :: Name_R)) } Some (42usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Survey (msg :: Survey :: PatientId_W (map_range_error ! (map_ty_error ! (v) , | x : & 'a str | x . len () <= 32usize))) , msg :: Msg :: Survey (msg :: Survey :: PatientId_R)) } Some (43usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Survey (msg :: Survey :: Patronymic_W (map_range_error ! (map_ty_error ! (v) , | x : & 'a str | x . len () <= 64usize))) , msg :: Msg :: Survey (msg :: Survey :: Patronymic_R)) } Some (44usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Survey (msg :: Survey :: Sex_W (map_range_error ! (map_ty_error ! (v) , | x : & 'a str | matches ! (x , "M" | "F")))) , msg :: Msg :: Survey (msg :: Survey :: Sex_R)) } Some (45usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Survey (msg :: Survey :: StartTime_W (map_ty_error ! (v))) , msg :: Msg :: Survey (msg :: Survey :: StartTime_R)) } Some (46usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Survey (msg :: Survey :: Surname_W (map_range_error ! (map_ty_error ! (v) , | x : & 'a str | x . len () <= 64usize))) , msg :: Msg :: Survey (msg :: Survey :: Surname_R)) } Some (47usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Echo_W (map_ty_error ! (v))) , msg :: Msg :: Test (msg :: Test :: Echo_R)) } Some (48usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Error_W (map_ty_error ! (v))) , msg :: Msg :: Test (msg :: Test :: Error_R)) } Some (49usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (0 , msg :: Slot :: Data_W (map_ty_error ! (v)))) , msg :: Msg :: Test (msg :: Test :: Slot (0 , msg :: Slot :: Data_R))) } Some (50usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (0 , msg :: Slot :: Pin (0 , msg :: Pin :: Level_W (map_ty_error ! (v))))) , msg :: Msg :: Test (msg :: Test :: Slot (0 , msg :: Slot :: Pin (0 , msg :: Pin :: Level_R)))) } Some (51usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (0 , msg :: Slot :: Pin (1 , msg :: Pin :: Level_W (map_ty_error ! (v))))) , msg :: Msg :: Test (msg :: Test :: Slot (0 , msg :: Slot :: Pin (1 , msg :: Pin :: Level_R)))) } Some (52usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (0 , msg :: Slot :: Used_W (map_ty_error ! (v)))) , msg :: Msg :: Test (msg :: Test :: Slot (0 , msg :: Slot :: Used_R))) } Some (53usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (1 , msg :: Slot :: Data_W (map_ty_error ! (v)))) , msg :: Msg :: Test (msg :: Test :: Slot (1 , msg :: Slot :: Data_R))) } Some (54usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (1 , msg :: Slot :: Pin (0 , msg :: Pin :: Level_W (map_ty_error ! (v))))) , msg :: Msg :: Test (msg :: Test :: Slot (1 , msg :: Slot :: Pin (0 , msg :: Pin :: Level_R)))) } Some (55usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (1 , msg :: Slot :: Pin (1 , msg :: Pin :: Level_W (map_ty_error ! (v))))) , msg :: Msg :: Test (msg :: Test :: Slot (1 , msg :: Slot :: Pin (1 , msg :: Pin :: Level_R)))) } Some (56usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (1 , msg :: Slot :: Used_W (map_ty_error ! (v)))) , msg :: Msg :: Test (msg :: Test :: Slot (1 , msg :: Slot :: Used_R))) } Some (57usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (2 , msg :: Slot :: Data_W (map_ty_error ! (v)))) , msg :: Msg :: Test (msg :: Test :: Slot (2 , msg :: Slot :: Data_R))) } Some (58usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (2 , msg :: Slot :: Pin (0 , msg :: Pin :: Level_W (map_ty_error ! (v))))) , msg :: Msg :: Test (msg :: Test :: Slot (2 , msg :: Slot :: Pin (0 , msg :: Pin :: Level_R)))) } Some (59usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (2 , msg :: Slot :: Pin (1 , msg :: Pin :: Level_W (map_ty_error ! (v))))) , msg :: Msg :: Test (msg :: Test :: Slot (2 , msg :: Slot :: Pin (1 , msg :: Pin :: Level_R)))) } Some (60usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (2 , msg :: Slot :: Used_W (map_ty_error ! (v)))) , msg :: Msg :: Test (msg :: Test :: Slot (2 , msg :: Slot :: Used_R))) } Some (61usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (3 , msg :: Slot :: Data_W (map_ty_error ! (v)))) , msg :: Msg :: Test (msg :: Test :: Slot (3 , msg :: Slot :: Data_R))) } Some (62usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (3 , msg :: Slot :: Pin (0 , msg :: Pin :: Level_W (map_ty_error ! (v))))) , msg :: Msg :: Test (msg :: Test :: Slot (3 , msg :: Slot :: Pin (0 , msg :: Pin :: Level_R)))) } Some (63usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (3 , msg :: Slot :: Pin (1 , msg :: Pin :: Level_W (map_ty_error ! (v))))) , msg :: Msg :: Test (msg :: Test :: Slot (3 , msg :: Slot :: Pin (1 , msg :: Pin :: Level_R)))) } Some (64usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Slot (3 , msg :: Slot :: Used_W (map_ty_error ! (v)))) , msg :: Msg :: Test (msg :: Test :: Slot (3 , msg :: Slot :: Used_R))) } Some (65usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Test1_W (map_ty_error ! (v))) , msg :: Msg :: Test (msg :: Test :: Test1_R)) } Some (66usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Test2_W (map_ty_error ! (v))) , msg :: Msg :: Test (msg :: Test :: Test2_R)) } Some (67usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Test3_W (map_ty_error ! (v))) , msg :: Msg :: Test (msg :: Test :: Test3_R)) } Some (68usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Test4_W (map_ty_error ! (v))) , msg :: Msg :: Test (msg :: Test :: Test4_R)) } Some (69usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Test (msg :: Test :: Test5_W (map_ty_error ! (v))) , msg :: Msg :: Test (msg :: Test :: Test5_R)) } Some (70usize) => { impl_arm ! (RW , code , sys_lvl , PrivLvl :: NORMAL_LVL , PrivLvl :: NORMAL_LVL , msg :: Msg :: Time_W (map_ty_error ! (v)) , msg :: Msg :: Time_R) } _ => { DispatchResult :: Err (AnswerCode :: ERR_PATH) } } } static DEFAULTS : [msg :: Msg < 'static > ; 12usize] = [msg :: Msg :: Calib (msg :: Calib :: Acc (msg :: Acc :: B_W (0))) , msg :: Msg :: Calib (msg :: Calib :: Acc (msg :: Acc :: K_W (1))) , msg :: Msg :: Calib (msg :: Calib :: Ecg (msg :: Ecg :: B_W (0))) , msg :: Msg :: Calib (msg :: Calib :: Ecg (msg :: Ecg :: K_W (1))) , msg :: Msg :: Calib (msg :: Calib :: Reo (msg :: Reo :: B_W (0))) , msg :: Msg :: Calib (msg :: Calib :: Reo (msg :: Reo :: K_W (1))) , msg :: Msg :: Conf (msg :: Conf :: Cyclic_W (false)) , msg :: Msg :: Conf (msg :: Conf :: Time_W (60)) , msg :: Msg :: Test (msg :: Test :: Slot (0 , msg :: Slot :: Used_W (false))) , msg :: Msg :: Test (msg :: Test :: Slot (1 , msg :: Slot :: Used_W (false))) , msg :: Msg :: Test (msg :: Test :: Slot (2 , msg :: Slot :: Used_W (false))) , msg :: Msg :: Test (msg :: Test :: Slot (3 , msg :: Slot :: Used_W (false))) ,] ; # [doc = r" Writes of `@default` values for register or whole section at `path`"] # [doc = r""] # [doc = r" `ERR_ACCESS` if there are no defaults under `path`"] pub fn reset2msgs (path : & str) -> Result < & 'static [msg :: Msg < 'static >] , AnswerCode > { match path { "/calib/acc/b" => Ok (& DEFAULTS [0usize .. 1usize]) , "/calib/acc/k" => Ok (& DEFAULTS [1usize .. 2usize]) , "/calib/acc" => Ok (& DEFAULTS [0usize .. 2usize]) , "/calib/ecg/b" => Ok (& DEFAULTS [2usize .. 3usize]) , "/calib/ecg/k" => Ok (& DEFAULTS [3usize .. 4usize]) , "/calib/ecg" => Ok (& DEFAULTS [2usize .. 4usize]) , "/calib/reo/b" => Ok (& DEFAULTS [4usize .. 5usize]) , "/calib/reo/k" => Ok (& DEFAULTS [5usize .. 6usize]) , "/calib/reo" => Ok (& DEFAULTS [4usize .. 6usize]) , "/calib" => Ok (& DEFAULTS [0usize .. 6usize]) , "/conf/cyclic" => Ok (& DEFAULTS [6usize .. 7usize]) , "/conf/time" => Ok (& DEFAULTS [7usize .. 8usize]) , "/conf" => Ok (& DEFAULTS [6usize .. 8usize]) , "/test/slot/0/used" => Ok (& DEFAULTS [8usize .. 9usize]) , "/test/slot/0" => Ok (& DEFAULTS [8usize .. 9usize]) , "/test/slot/1/used" => Ok (& DEFAULTS [9usize .. 10usize]) , "/test/slot/1" => Ok (& DEFAULTS [9usize .. 10usize]) , "/test/slot/2/used" => Ok (& DEFAULTS [10usize .. 11usize]) , "/test/slot/2" => Ok (& DEFAULTS [10usize .. 11usize]) , "/test/slot/3/used" => Ok (& DEFAULTS [11usize .. 12usize]) , "/test/slot/3" => Ok (& DEFAULTS [11usize .. 12usize]) , "/test" => Ok (& DEFAULTS [8usize .. 12usize]) , "/" => Ok (& DEFAULTS [0usize .. 12usize]) , "/build/compiler" => Err (AnswerCode :: ERR_ACCESS) , "/build/git" => Err (AnswerCode :: ERR_ACCESS) , "/build/host" => Err (AnswerCode :: ERR_ACCESS) , "/build/profile" => Err (AnswerCode :: ERR_ACCESS) , "/build/target" => Err (AnswerCode :: ERR_ACCESS) , "/build/time" => Err (AnswerCode :: ERR_ACCESS) , "/build/version" => Err (AnswerCode :: ERR_ACCESS) , "/build" => Err (AnswerCode :: ERR_ACCESS) , "/ctrl/erase" => Err (AnswerCode :: ERR_ACCESS) , "/ctrl/event" => Err (AnswerCode :: ERR_ACCESS) , "/ctrl/goto_loader" => Err (AnswerCode :: ERR_ACCESS) , "/ctrl/record" => Err (AnswerCode :: ERR_ACCESS) , "/ctrl/vis" => Err (AnswerCode :: ERR_ACCESS) , "/ctrl" => Err (AnswerCode :: ERR_ACCESS) , "/dbg/flags" => Err (AnswerCode :: ERR_ACCESS) , "/dbg/last" => Err (AnswerCode :: ERR_ACCESS) , "/dbg/last_code" => Err (AnswerCode :: ERR_ACCESS) , "/dbg" => Err (AnswerCode :: ERR_ACCESS) , "/desc/serial" => Err (AnswerCode :: ERR_ACCESS) , "/desc/type" => Err (AnswerCode :: ERR_ACCESS) , "/desc/version" => Err (AnswerCode :: ERR_ACCESS) , "/desc" => Err (AnswerCode :: ERR_ACCESS) , "/io/file/len" => Err (AnswerCode :: ERR_ACCESS) , "/io/file/max" => Err (AnswerCode :: ERR_ACCESS) , "/io/file/pos" => Err (AnswerCode :: ERR_ACCESS) , "/io/file/start" => Err (AnswerCode :: ERR_ACCESS) , "/io/file" => Err (AnswerCode :: ERR_ACCESS) , "/io/status" => Err (AnswerCode :: ERR_ACCESS) , "/io/store/idx" => Err (AnswerCode :: ERR_ACCESS) , "/io/store/line" => Err (AnswerCode :: ERR_ACCESS) , "/io/store/max" => Err (AnswerCode :: ERR_ACCESS) , "/io/store" => Err (AnswerCode :: ERR_ACCESS) , "/io" => Err (AnswerCode :: ERR_ACCESS) , "/signal/ecgf/frq" => Err (AnswerCode :: ERR_ACCESS) , "/signal/ecgf" => Err (AnswerCode :: ERR_ACCESS) , "/signal/reof/frq" => Err (AnswerCode :: ERR_ACCESS) , "/signal/reof" => Err (AnswerCode :: ERR_ACCESS) , "/signal" => Err (AnswerCode :: ERR_ACCESS) , "/state/current" => Err (AnswerCode :: ERR_ACCESS) , "/state/stop_reason" => Err (AnswerCode :: ERR_ACCESS) , "/state/voltage" => Err (AnswerCode :: ERR_ACCESS) , "/state" => Err (AnswerCode :: ERR_ACCESS) , "/survey/birth" => Err (AnswerCode :: ERR_ACCESS) , "/survey/id" => Err (AnswerCode :: ERR_ACCESS) , "/survey/name" => Err (AnswerCode :: ERR_ACCESS) , "/survey/patient_id" => Err (AnswerCode :: ERR_ACCESS) , "/survey/patronymic" => Err (AnswerCode :: ERR_ACCESS) , "/survey/sex" => Err (AnswerCode :: ERR_ACCESS) , "/survey/start_time" => Err (AnswerCode :: ERR_ACCESS) , "/survey/surname" => Err (AnswerCode :: ERR_ACCESS) , "/survey" => Err (AnswerCode :: ERR_ACCESS) , "/test/echo" => Err (AnswerCode :: ERR_ACCESS) , "/test/error" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/0/data" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/0/pin/0/level" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/0/pin/0" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/0/pin/1/level" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/0/pin/1" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/1/data" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/1/pin/0/level" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/1/pin/0" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/1/pin/1/level" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/1/pin/1" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/2/data" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/2/pin/0/level" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/2/pin/0" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/2/pin/1/level" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/2/pin/1" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/3/data" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/3/pin/0/level" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/3/pin/0" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/3/pin/1/level" => Err (AnswerCode :: ERR_ACCESS) , "/test/slot/3/pin/1" => Err (AnswerCode :: ERR_ACCESS) , "/test/test1" => Err (AnswerCode :: ERR_ACCESS) , "/test/test2" => Err (AnswerCode :: ERR_ACCESS) , "/test/test3" => Err (AnswerCode :: ERR_ACCESS) , "/test/test4" => Err (AnswerCode :: ERR_ACCESS) , "/test/test5" => Err (AnswerCode :: ERR_ACCESS) , "/time" => Err (AnswerCode :: ERR_ACCESS) , _ => Err (AnswerCode :: ERR_PATH) , } } # [doc = r" Level required to RESET `path`: the highest write level among registers reset"] pub fn reset_priv (path : & str) -> PrivLvl { match path { "/calib/acc/b" => PrivLvl :: SECUR_LVL , "/calib/acc/k" => PrivLvl :: SECUR_LVL , "/calib/acc" => PrivLvl :: SECUR_LVL , "/calib/ecg/b" => PrivLvl :: SECUR_LVL , "/calib/ecg/k" => PrivLvl :: SECUR_LVL , "/calib/ecg" => PrivLvl :: SECUR_LVL , "/calib/reo/b" => PrivLvl :: SECUR_LVL , "/calib/reo/k" => PrivLvl :: SECUR_LVL , "/calib/reo" => PrivLvl :: SECUR_LVL , "/calib" => PrivLvl :: SECUR_LVL , "/" => PrivLvl :: SECUR_LVL , _ => PrivLvl :: NORMAL_LVL , } } impl < 'a > From < & 'static msg :: Msg < 'static > > for msg :: Msg < 'a > { fn from (m : & 'static msg :: Msg < 'static >) -> Self { * m } } # [doc = r" Staged writes of `@txn` registers"] # [derive (Debug , Clone , Copy , Default , PartialEq , Eq)] pub struct Staging { pub conf_cyclic : Option < bool > , pub conf_time : Option < u32 > , pub signal_ecgf_frq : Option < u8 > , pub signal_reof_frq : Option < u8 > , } impl < 'a > Stage < msg :: Msg < 'a > > for Staging { const LEN : usize = 4usize ; fn stage (& mut self , m : msg :: Msg < 'a >) -> Result < () , msg :: Msg < 'a > > { match m { msg :: Msg :: Conf (msg :: Conf :: Cyclic_W (v)) => self . conf_cyclic = Some (v) , msg :: Msg :: Conf (msg :: Conf :: Time_W (v)) => self . conf_time = Some (v) , msg :: Msg :: Signal (msg :: Signal :: Ecgf (msg :: Ecgf :: Frq_W (v))) => self . signal_ecgf_frq = Some (v) , msg :: Msg :: Signal (msg :: Signal :: Reof (msg :: Reof :: Frq_W (v))) => self . signal_reof_frq = Some (v) , m => return Err (m) , } Ok (()) } fn write (& self , i : usize) -> Option < msg :: Msg < 'a > > { match i { 0usize => self . conf_cyclic . map (| v | msg :: Msg :: Conf (msg :: Conf :: Cyclic_W (v))) , 1usize => self . conf_time . map (| v | msg :: Msg :: Conf (msg :: Conf :: Time_W (v))) , 2usize => self . signal_ecgf_frq . map (| v | msg :: Msg :: Signal (msg :: Signal :: Ecgf (msg :: Ecgf :: Frq_W (v)))) , 3usize => self . signal_reof_frq . map (| v | msg :: Msg :: Signal (msg :: Signal :: Reof (msg :: Reof :: Frq_W (v)))) , _ => None , } } fn read (i : usize) -> msg :: Msg < 'a > { match i { 0usize => msg :: Msg :: Conf (msg :: Conf :: Cyclic_R) , 1usize => msg :: Msg :: Conf (msg :: Conf :: Time_R) , 2usize => msg :: Msg :: Signal (msg :: Signal :: Ecgf (msg :: Ecgf :: Frq_R)) , 3usize => msg :: Msg :: Signal (msg :: Signal :: Reof (msg :: Reof :: Frq_R)) , _ => panic ! ("No @txn register #{}" , i) , } } fn set (& mut self , i : usize , v : Value < '_ >) -> Result < () , AnswerCode > { match i { 0usize => self . conf_cyclic = Some (core :: convert :: TryInto :: try_into (v) ?) , 1usize => self . conf_time = Some (core :: convert :: TryInto :: try_into (v) ?) , 2usize => self . signal_ecgf_frq = Some (core :: convert :: TryInto :: try_into (v) ?) , 3usize => self . signal_reof_frq = Some (core :: convert :: TryInto :: try_into (v) ?) , _ => return Err (AnswerCode :: ERR_PATH) , } Ok (()) } } # [doc = r" Paths of `@persist` registers, image order"] pub const PERSIST_PATHS : [& str ; 16usize] = ["/calib/acc/b" , "/calib/acc/k" , "/calib/ecg/b" , "/calib/ecg/k" , "/calib/reo/b" , "/calib/reo/k" , "/conf/cyclic" , "/conf/time" , "/survey/birth" , "/survey/id" , "/survey/name" , "/survey/patient_id" , "/survey/patronymic" , "/survey/sex" , "/survey/start_time" , "/survey/surname"] ; # [doc = r" Size of the largest image `persist_save` can produce"] pub const PERSIST_IMAGE_MAX_SZ : usize = 831usize ; # [doc = r" Image of `@persist` registers in `buf`, `read` answers their read messages"] # [doc = r""] # [doc = r" Returns image size, ready for `Storage::store`."] pub fn persist_save < 'v , F > (buf : & mut [u8] , mut read : F) -> Result < usize , PersistError > where F : FnMut (msg :: Msg < 'static >) -> Result < Value < 'v > , AnswerErr < 'v >> , { const READS : [msg :: Msg < 'static > ; 16usize] = [msg :: Msg :: Calib (msg :: Calib :: Acc (msg :: Acc :: B_R)) , msg :: Msg :: Calib (msg :: Calib :: Acc (msg :: Acc :: K_R)) , msg :: Msg :: Calib (msg :: Calib :: Ecg (msg :: Ecg :: B_R)) , msg :: Msg :: Calib (msg :: Calib :: Ecg (msg :: Ecg :: K_R)) , msg :: Msg :: Calib (msg :: Calib :: Reo (msg :: Reo :: B_R)) , msg :: Msg :: Calib (msg :: Calib :: Reo (msg :: Reo :: K_R)) , msg :: Msg :: Conf (msg :: Conf :: Cyclic_R) , msg :: Msg :: Conf (msg :: Conf :: Time_R) , msg :: Msg :: Survey (msg :: Survey :: Birth_R) , msg :: Msg :: Survey (msg :: Survey :: Id_R) , msg :: Msg :: Survey (msg :: Survey :: Name_R) , msg :: Msg :: Survey (msg :: Survey :: PatientId_R) , msg :: Msg :: Survey (msg :: Survey :: Patronymic_R) , msg :: Msg :: Survey (msg :: Survey :: Sex_R) , msg :: Msg :: Survey (msg :: Survey :: StartTime_R) , msg :: Msg :: Survey (msg :: Survey :: Surname_R)] ; let mut image = persist :: ImageWriter :: new (buf , (1u16 , 0u16)) ? ; for (path , m) in PERSIST_PATHS . iter () . zip (READS . iter ()) { let v = read (* m) . map_err (| e | PersistError :: Read (e . 0)) ? ; image . push (path , v) ? ; } Ok (image . finish ()) } # [doc = r" Hands stored values of `image` to `write` as write messages"] # [doc = r""] # [doc = r" Entries of registers gone, not persisted anymore, retyped or out of range"] # [doc = r" are skipped, registers missing from image keep their values."] # [doc = r" Returns number of values `write` accepted."] pub fn persist_load < 'a , 'r , F > (image : & 'a [u8] , mut write : F) -> Result < usize , PersistError > where F : FnMut (msg :: Msg < 'a >) -> Result < Value < 'r > , AnswerErr < 'r >> , { let mut written = 0 ; for entry in persist :: ImageReader :: new (image) ? { let (path , v) = entry ? ; if ! PERSIST_PATHS . contains (& path) { continue ; } if let DispatchResult :: Msg (m) = req2msg (& mut WriteOnly , RequestCode :: WRITE , path , v , PrivLvl :: DEVEL_LVL) { if write (m) . is_ok () { written += 1 ; } } } Ok (written) } # [doc = r" Typed register access over `Transport`"] pub struct RegClient < T > (pub client :: Client < T >) ; # [allow (non_snake_case)] impl < T : Transport > RegClient < T > { pub fn new (transport : T) -> Self { Self (client :: Client :: new (transport)) } # [doc = " Read `/build/compiler`"] pub fn build_compiler_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/build/compiler") } # [doc = " Read `/build/git`"] pub fn build_git_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/build/git") } # [doc = " Read `/build/host`"] pub fn build_host_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/build/host") } # [doc = " Read `/build/profile`"] pub fn build_profile_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/build/profile") } # [doc = " Read `/build/target`"] pub fn build_target_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/build/target") } # [doc = " Read `/build/time`"] pub fn build_time_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/build/time") } # [doc = " Read `/build/version`"] pub fn build_version_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/build/version") } # [doc = " Read `/calib/acc/b`"] pub fn calib_acc_b_read (& mut self) -> Result < i32 , ClientError < T :: Error >> { self . 0 . read_as ("/calib/acc/b") } # [doc = " Write `/calib/acc/b`"] pub fn calib_acc_b_write (& mut self , v : i32) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/calib/acc/b" , Value :: I32 (v)) } # [doc = " Read `/calib/acc/k`"] pub fn calib_acc_k_read (& mut self) -> Result < i32 , ClientError < T :: Error >> { self . 0 . read_as ("/calib/acc/k") } # [doc = " Write `/calib/acc/k`"] pub fn calib_acc_k_write (& mut self , v : i32) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/calib/acc/k" , Value :: I32 (v)) } # [doc = " Read `/calib/ecg/b`"] pub fn calib_ecg_b_read (& mut self) -> Result < i32 , ClientError < T :: Error >> { self . 0 . read_as ("/calib/ecg/b") } # [doc = " Write `/calib/ecg/b`"] pub fn calib_ecg_b_write (& mut self , v : i32) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/calib/ecg/b" , Value :: I32 (v)) } # [doc = " Read `/calib/ecg/k`"] pub fn calib_ecg_k_read (& mut self) -> Result < i32 , ClientError < T :: Error >> { self . 0 . read_as ("/calib/ecg/k") } # [doc = " Write `/calib/ecg/k`"] pub fn calib_ecg_k_write (& mut self , v : i32) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/calib/ecg/k" , Value :: I32 (v)) } # [doc = " Read `/calib/reo/b`"] pub fn calib_reo_b_read (& mut self) -> Result < i32 , ClientError < T :: Error >> { self . 0 . read_as ("/calib/reo/b") } # [doc = " Write `/calib/reo/b`"] pub fn calib_reo_b_write (& mut self , v : i32) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/calib/reo/b" , Value :: I32 (v)) } # [doc = " Read `/calib/reo/k`"] pub fn calib_reo_k_read (& mut self) -> Result < i32 , ClientError < T :: Error >> { self . 0 . read_as ("/calib/reo/k") } # [doc = " Write `/calib/reo/k`"] pub fn calib_reo_k_write (& mut self , v : i32) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/calib/reo/k" , Value :: I32 (v)) } # [doc = " Read `/conf/cyclic`"] pub fn conf_cyclic_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/conf/cyclic") } # [doc = " Write `/conf/cyclic`"] pub fn conf_cyclic_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/conf/cyclic" , Value :: BOOL (v)) } # [doc = " Read `/conf/time`"] pub fn conf_time_read (& mut self) -> Result < u32 , ClientError < T :: Error >> { self . 0 . read_as ("/conf/time") } # [doc = " Write `/conf/time`"] pub fn conf_time_write (& mut self , v : u32) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/conf/time" , Value :: U32 (v)) } # [doc = " Write `/ctrl/erase`"] pub fn ctrl_erase_write (& mut self) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/ctrl/erase" , Value :: UNIT (())) } # [doc = " Write `/ctrl/event`"] pub fn ctrl_event_write < 'a > (& mut self , v : & 'a [u8]) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/ctrl/event" , Value :: BYTES (v)) } # [doc = " Write `/ctrl/goto_loader`"] pub fn ctrl_goto_loader_write (& mut self) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/ctrl/goto_loader" , Value :: UNIT (())) } # [doc = " Read `/ctrl/record`"] pub fn ctrl_record_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/ctrl/record") } # [doc = " Write `/ctrl/record`"] pub fn ctrl_record_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/ctrl/record" , Value :: BOOL (v)) } # [doc = " Read `/ctrl/vis`"] pub fn ctrl_vis_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/ctrl/vis") } # [doc = " Write `/ctrl/vis`"] pub fn ctrl_vis_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/ctrl/vis" , Value :: BOOL (v)) } # [doc = " Read `/dbg/flags`"] pub fn dbg_flags_read (& mut self) -> Result < u32 , ClientError < T :: Error >> { self . 0 . read_as ("/dbg/flags") } # [doc = " Read `/dbg/last`"] pub fn dbg_last_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/dbg/last") } # [doc = " Read `/dbg/last_code`"] pub fn dbg_last_code_read (& mut self) -> Result < u32 , ClientError < T :: Error >> { self . 0 . read_as ("/dbg/last_code") } # [doc = " Read `/desc/serial`"] pub fn desc_serial_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/desc/serial") } # [doc = " Read `/desc/type`"] pub fn desc_type_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/desc/type") } # [doc = " Read `/desc/version`"] # [doc = ""] # [doc = " Версия ПО"] pub fn desc_version_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/desc/version") } # [doc = " Read `/io/file/len`"] pub fn io_file_len_read (& mut self) -> Result < u32 , ClientError < T :: Error >> { self . 0 . read_as ("/io/file/len") } # [doc = " Write `/io/file/len`"] pub fn io_file_len_write (& mut self , v : u32) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/io/file/len" , Value :: U32 (v)) } # [doc = " Read `/io/file/max`"] pub fn io_file_max_read (& mut self) -> Result < u32 , ClientError < T :: Error >> { self . 0 . read_as ("/io/file/max") } # [doc = " Read `/io/file/pos`"] pub fn io_file_pos_read (& mut self) -> Result < u32 , ClientError < T :: Error >> { self . 0 . read_as ("/io/file/pos") } # [doc = " Write `/io/file/pos`"] pub fn io_file_pos_write (& mut self , v : u32) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/io/file/pos" , Value :: U32 (v)) } # [doc = " Write `/io/file/start`"] pub fn io_file_start_write (& mut self) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/io/file/start" , Value :: UNIT (())) } # [doc = " Read `/io/status`"] pub fn io_status_read (& mut self) -> Result < u32 , ClientError < T :: Error >> { self . 0 . read_as ("/io/status") } # [doc = " Write `/io/status`"] pub fn io_status_write (& mut self , v : u32) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/io/status" , Value :: U32 (v)) } # [doc = " Read `/io/store/idx`"] pub fn io_store_idx_read (& mut self) -> Result < u8 , ClientError < T :: Error >> { self . 0 . read_as ("/io/store/idx") } # [doc = " Write `/io/store/idx`"] pub fn io_store_idx_write (& mut self , v : u8) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/io/store/idx" , Value :: U8 (v)) } # [doc = " Read `/io/store/line`"] pub fn io_store_line_read < 'a > (& 'a mut self) -> Result < & 'a [u8] , ClientError < T :: Error >> { self . 0 . read_as ("/io/store/line") } # [doc = " Write `/io/store/line`"] pub fn io_store_line_write < 'a > (& mut self , v : & 'a [u8]) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/io/store/line" , Value :: BYTES (v)) } # [doc = " Read `/io/store/max`"] pub fn io_store_max_read (& mut self) -> Result < u32 , ClientError < T :: Error >> { self . 0 . read_as ("/io/store/max") } # [doc = " Read `/signal/ecgf/frq`"] # [doc = ""] # [doc = " Частота дискретизации ЭКГ, Гц"] pub fn signal_ecgf_frq_read (& mut self) -> Result < u8 , ClientError < T :: Error >> { self . 0 . read_as ("/signal/ecgf/frq") } # [doc = " Write `/signal/ecgf/frq`"] # [doc = ""] # [doc = " Частота дискретизации ЭКГ, Гц"] pub fn signal_ecgf_frq_write (& mut self , v : u8) -> Result < () , ClientError < T :: Error >> { if ! (1 ..= 200) . contains (& v) { return Err (ClientError :: Range) ; } self . 0 . write ("/signal/ecgf/frq" , Value :: U8 (v)) } # [doc = " Read `/signal/reof/frq`"] pub fn signal_reof_frq_read (& mut self) -> Result < u8 , ClientError < T :: Error >> { self . 0 . read_as ("/signal/reof/frq") } # [doc = " Write `/signal/reof/frq`"] pub fn signal_reof_frq_write (& mut self , v : u8) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/signal/reof/frq" , Value :: U8 (v)) } # [doc = " Read `/state/current`"] pub fn state_current_read (& mut self) -> Result < i32 , ClientError < T :: Error >> { self . 0 . read_as ("/state/current") } # [doc = " Read `/state/stop_reason`"] pub fn state_stop_reason_read (& mut self) -> Result < u32 , ClientError < T :: Error >> { self . 0 . read_as ("/state/stop_reason") } # [doc = " Read `/state/voltage`"] pub fn state_voltage_read (& mut self) -> Result < i32 , ClientError < T :: Error >> { self . 0 . read_as ("/state/voltage") } # [doc = " Read `/survey/birth`"] pub fn survey_birth_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/survey/birth") } # [doc = " Write `/survey/birth`"] pub fn survey_birth_write < 'a > (& mut self , v : & 'a str) -> Result < () , ClientError < T :: Error >> { if v . len () > 10usize { return Err (ClientError :: Range) ; } self . 0 . write ("/survey/birth" , Value :: STR (v)) } # [doc = " Read `/survey/id`"] pub fn survey_id_read (& mut self) -> Result < u32 , ClientError < T :: Error >> { self . 0 . read_as ("/survey/id") } # [doc = " Write `/survey/id`"] pub fn survey_id_write (& mut self , v : u32) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/survey/id" , Value :: U32 (v)) } # [doc = " Read `/survey/name`"] pub fn survey_name_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/survey/name") } # [doc = " Write `/survey/name`"] pub fn survey_name_write < 'a > (& mut self , v : & 'a str) -> Result < () , ClientError < T :: Error >> { if v . len () > 64usize { return Err (ClientError :: Range) ; } self . 0 . write ("/survey/name" , Value :: STR (v)) } # [doc = " Read `/survey/patient_id`"] pub fn survey_patient_id_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/survey/patient_id") } # [doc = " Write `/survey/patient_id`"] pub fn survey_patient_id_write < 'a > (& mut self , v : & 'a str) -> Result < () , ClientError < T :: Error >> { if v . len () > 32usize { return Err (ClientError :: Range) ; } self . 0 . write ("/survey/patient_id" , Value :: STR (v)) } # [doc = " Read `/survey/patronymic`"] pub fn survey_patronymic_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/survey/patronymic") } # [doc = " Write `/survey/patronymic`"] pub fn survey_patronymic_write < 'a > (& mut self , v : & 'a str) -> Result < () , ClientError < T :: Error >> { if v . len () > 64usize { return Err (ClientError :: Range) ; } self . 0 . write ("/survey/patronymic" , Value :: STR (v)) } # [doc = " Read `/survey/sex`"] pub fn survey_sex_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/survey/sex") } # [doc = " Write `/survey/sex`"] pub fn survey_sex_write < 'a > (& mut self , v : & 'a str) -> Result < () , ClientError < T :: Error >> { if ! matches ! (v , "M" | "F") { return Err (ClientError :: Range) ; } self . 0 . write ("/survey/sex" , Value :: STR (v)) } # [doc = " Read `/survey/start_time`"] pub fn survey_start_time_read (& mut self) -> Result < u32 , ClientError < T :: Error >> { self . 0 . read_as ("/survey/start_time") } # [doc = " Write `/survey/start_time`"] pub fn survey_start_time_write (& mut self , v : u32) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/survey/start_time" , Value :: U32 (v)) } # [doc = " Read `/survey/surname`"] pub fn survey_surname_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/survey/surname") } # [doc = " Write `/survey/surname`"] pub fn survey_surname_write < 'a > (& mut self , v : & 'a str) -> Result < () , ClientError < T :: Error >> { if v . len () > 64usize { return Err (ClientError :: Range) ; } self . 0 . write ("/survey/surname" , Value :: STR (v)) } # [doc = " Read `/test/echo`"] # [doc = ""] # [doc = " Эхо тест"] pub fn test_echo_read < 'a > (& 'a mut self) -> Result < & 'a [u8] , ClientError < T :: Error >> { self . 0 . read_as ("/test/echo") } # [doc = " Write `/test/echo`"] # [doc = ""] # [doc = " Эхо тест"] pub fn test_echo_write < 'a > (& mut self , v : & 'a [u8]) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/echo" , Value :: BYTES (v)) } # [doc = " Read `/test/error`"] pub fn test_error_read < 'a > (& 'a mut self) -> Result < & 'a str , ClientError < T :: Error >> { self . 0 . read_as ("/test/error") } # [doc = " Write `/test/error`"] pub fn test_error_write < 'a > (& mut self , v : & 'a str) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/error" , Value :: STR (v)) } # [doc = " Read `/test/slot/0/data`"] pub fn test_slot_0_data_read < 'a > (& 'a mut self) -> Result < & 'a [u8] , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/0/data") } # [doc = " Write `/test/slot/0/data`"] pub fn test_slot_0_data_write < 'a > (& mut self , v : & 'a [u8]) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/0/data" , Value :: BYTES (v)) } # [doc = " Read `/test/slot/0/pin/0/level`"] pub fn test_slot_0_pin_0_level_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/0/pin/0/level") } # [doc = " Write `/test/slot/0/pin/0/level`"] pub fn test_slot_0_pin_0_level_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/0/pin/0/level" , Value :: BOOL (v)) } # [doc = " Read `/test/slot/0/pin/1/level`"] pub fn test_slot_0_pin_1_level_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/0/pin/1/level") } # [doc = " Write `/test/slot/0/pin/1/level`"] pub fn test_slot_0_pin_1_level_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/0/pin/1/level" , Value :: BOOL (v)) } # [doc = " Read `/test/slot/0/used`"] pub fn test_slot_0_used_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/0/used") } # [doc = " Write `/test/slot/0/used`"] pub fn test_slot_0_used_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/0/used" , Value :: BOOL (v)) } # [doc = " Read `/test/slot/1/data`"] pub fn test_slot_1_data_read < 'a > (& 'a mut self) -> Result < & 'a [u8] , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/1/data") } # [doc = " Write `/test/slot/1/data`"] pub fn test_slot_1_data_write < 'a > (& mut self , v : & 'a [u8]) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/1/data" , Value :: BYTES (v)) } # [doc = " Read `/test/slot/1/pin/0/level`"] pub fn test_slot_1_pin_0_level_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/1/pin/0/level") } # [doc = " Write `/test/slot/1/pin/0/level`"] pub fn test_slot_1_pin_0_level_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/1/pin/0/level" , Value :: BOOL (v)) } # [doc = " Read `/test/slot/1/pin/1/level`"] pub fn test_slot_1_pin_1_level_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/1/pin/1/level") } # [doc = " Write `/test/slot/1/pin/1/level`"] pub fn test_slot_1_pin_1_level_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/1/pin/1/level" , Value :: BOOL (v)) } # [doc = " Read `/test/slot/1/used`"] pub fn test_slot_1_used_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/1/used") } # [doc = " Write `/test/slot/1/used`"] pub fn test_slot_1_used_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/1/used" , Value :: BOOL (v)) } # [doc = " Read `/test/slot/2/data`"] pub fn test_slot_2_data_read < 'a > (& 'a mut self) -> Result < & 'a [u8] , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/2/data") } # [doc = " Write `/test/slot/2/data`"] pub fn test_slot_2_data_write < 'a > (& mut self , v : & 'a [u8]) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/2/data" , Value :: BYTES (v)) } # [doc = " Read `/test/slot/2/pin/0/level`"] pub fn test_slot_2_pin_0_level_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/2/pin/0/level") } # [doc = " Write `/test/slot/2/pin/0/level`"] pub fn test_slot_2_pin_0_level_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/2/pin/0/level" , Value :: BOOL (v)) } # [doc = " Read `/test/slot/2/pin/1/level`"] pub fn test_slot_2_pin_1_level_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/2/pin/1/level") } # [doc = " Write `/test/slot/2/pin/1/level`"] pub fn test_slot_2_pin_1_level_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/2/pin/1/level" , Value :: BOOL (v)) } # [doc = " Read `/test/slot/2/used`"] pub fn test_slot_2_used_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/2/used") } # [doc = " Write `/test/slot/2/used`"] pub fn test_slot_2_used_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/2/used" , Value :: BOOL (v)) } # [doc = " Read `/test/slot/3/data`"] pub fn test_slot_3_data_read < 'a > (& 'a mut self) -> Result < & 'a [u8] , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/3/data") } # [doc = " Write `/test/slot/3/data`"] pub fn test_slot_3_data_write < 'a > (& mut self , v : & 'a [u8]) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/3/data" , Value :: BYTES (v)) } # [doc = " Read `/test/slot/3/pin/0/level`"] pub fn test_slot_3_pin_0_level_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/3/pin/0/level") } # [doc = " Write `/test/slot/3/pin/0/level`"] pub fn test_slot_3_pin_0_level_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/3/pin/0/level" , Value :: BOOL (v)) } # [doc = " Read `/test/slot/3/pin/1/level`"] pub fn test_slot_3_pin_1_level_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/3/pin/1/level") } # [doc = " Write `/test/slot/3/pin/1/level`"] pub fn test_slot_3_pin_1_level_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/3/pin/1/level" , Value :: BOOL (v)) } # [doc = " Read `/test/slot/3/used`"] pub fn test_slot_3_used_read (& mut self) -> Result < bool , ClientError < T :: Error >> { self . 0 . read_as ("/test/slot/3/used") } # [doc = " Write `/test/slot/3/used`"] pub fn test_slot_3_used_write (& mut self , v : bool) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/slot/3/used" , Value :: BOOL (v)) } # [doc = " Read `/test/test1`"] pub fn test_test1_read < 'a > (& 'a mut self) -> Result < & 'a [u8] , ClientError < T :: Error >> { self . 0 . read_as ("/test/test1") } # [doc = " Write `/test/test1`"] pub fn test_test1_write < 'a > (& mut self , v : & 'a [u8]) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/test1" , Value :: BYTES (v)) } # [doc = " Read `/test/test2`"] pub fn test_test2_read < 'a > (& 'a mut self) -> Result < & 'a [u8] , ClientError < T :: Error >> { self . 0 . read_as ("/test/test2") } # [doc = " Write `/test/test2`"] pub fn test_test2_write < 'a > (& mut self , v : & 'a [u8]) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/test2" , Value :: BYTES (v)) } # [doc = " Read `/test/test3`"] pub fn test_test3_read < 'a > (& 'a mut self) -> Result < & 'a [u8] , ClientError < T :: Error >> { self . 0 . read_as ("/test/test3") } # [doc = " Write `/test/test3`"] pub fn test_test3_write < 'a > (& mut self , v : & 'a [u8]) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/test3" , Value :: BYTES (v)) } # [doc = " Read `/test/test4`"] pub fn test_test4_read < 'a > (& 'a mut self) -> Result < & 'a [u8] , ClientError < T :: Error >> { self . 0 . read_as ("/test/test4") } # [doc = " Write `/test/test4`"] pub fn test_test4_write < 'a > (& mut self , v : & 'a [u8]) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/test4" , Value :: BYTES (v)) } # [doc = " Read `/test/test5`"] pub fn test_test5_read < 'a > (& 'a mut self) -> Result < & 'a [u8] , ClientError < T :: Error >> { self . 0 . read_as ("/test/test5") } # [doc = " Write `/test/test5`"] pub fn test_test5_write < 'a > (& mut self , v : & 'a [u8]) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/test/test5" , Value :: BYTES (v)) } # [doc = " Read `/time`"] pub fn time_read (& mut self) -> Result < u32 , ClientError < T :: Error >> { self . 0 . read_as ("/time") } # [doc = " Write `/time`"] pub fn time_write (& mut self , v : u32) -> Result < () , ClientError < T :: Error >> { self . 0 . write ("/time" , Value :: U32 (v)) } } # [doc = r" Custom error codes, sent with `ERR_CUSTOM` answer"] # [repr (u16)] # [derive (Debug , Clone , Copy , PartialEq , Eq)] pub enum CustomErr { BadState = 3u16 , Busy = 1u16 , IoFail = 4u16 , NoMedia = 2u16 , } impl CustomErr { pub fn name (& self) -> & 'static str { match self { CustomErr :: BadState => "bad_state" , CustomErr :: Busy => "busy" , CustomErr :: IoFail => "io_fail" , CustomErr :: NoMedia => "no_media" , } } # [doc = r" Error answer with code and message"] pub fn msg (self , m : & str) -> AnswerErr < '_ > { AnswerErr (AnswerCode :: ERR_CUSTOM , ErrDetail :: CodeMsg (self as u16 , m)) } } impl core :: convert :: TryFrom < u16 > for CustomErr { type Error = () ; fn try_from (c : u16) -> Result < Self , Self :: Error > { match c { 3u16 => Ok (CustomErr :: BadState) , 1u16 => Ok (CustomErr :: Busy) , 4u16 => Ok (CustomErr :: IoFail) , 2u16 => Ok (CustomErr :: NoMedia) , _ => Err (()) , } } } impl core :: fmt :: Display for CustomErr { fn fmt (& self , f : & mut core :: fmt :: Formatter < '_ >) -> core :: fmt :: Result { f . write_str (self . name ()) } } impl From < CustomErr > for AnswerErr < 'static > { fn from (e : CustomErr) -> Self { AnswerErr (AnswerCode :: ERR_CUSTOM , ErrDetail :: Code (e as u16)) } }
\ No newline at end of file
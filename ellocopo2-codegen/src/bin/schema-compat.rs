@@ -0,0 +1,34 @@
+//! `schema-compat OLD NEW`: wire compatibility of two scheme revisions
+//!
+//! Prints every change between register trees, exits with 1 if some of them
+//! break hosts built against `OLD`, with 2 if schemes can't be read.
+//! Format of each scheme follows its extension, JSON by default.
+
+use std::process::exit;
+
+use ellocopo2_codegen::compat::{diff, Change};
+use ellocopo2_codegen::parser::{parse_file, Schema};
+
+fn load(file: &str) -> Schema {
+    parse_file(file.as_ref()).unwrap_or_else(|e| {
+        eprintln!("{}: {}", file, e);
+        exit(2)
+    })
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() != 2 {
+        eprintln!("usage: schema-compat OLD NEW");
+        exit(2);
+    }
+    let (old, new) = (load(&args[0]), load(&args[1]));
+
+    let changes = diff(&old.tree, &new.tree);
+    for c in &changes {
+        println!("{}", c);
+    }
+    if changes.iter().any(Change::breaking) {
+        exit(1);
+    }
+}
@@ -0,0 +1,453 @@
+//! C backend: header with register table and request dispatch skeleton
+//!
+//! Application links generated source and implements `ellocopo_read` and
+//! `ellocopo_write`, framing and checks follow `protocol.rs` and `req2msg`.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+use ellocopo2::{AnswerCode, PrivLvl, RequestCode, TypeTag, HEADER_SZ, MAX_MSG_SZ, MAX_PATH_SZ, MAX_PAYLOAD_SZ, SIGN, SIGN_AUTH};
+
+use crate::diag::{Diagnostic, pointer};
+use crate::gen::{perfect_hash, PerfectHash};
+use crate::parser::*;
+
+pub const PREFIX: &str = "ELLOCOPO_";
+
+/// `ELLOCOPO_REG_<PATH>` id of every register, scheme order
+pub fn reg_ids(tree: &DslTree) -> Result<Vec<String>, Vec<Diagnostic>> {
+    let mut ids = Vec::new();
+    let mut errs = Vec::new();
+    let mut seen: HashMap<String, String> = HashMap::new();
+    tree.visit_regs(&mut |reg| {
+        let id: String = reg.path.join("_")
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+            .collect();
+        let id = format!("{}REG_{}", PREFIX, id);
+        let at = pointer(&reg.path, None);
+        match seen.get(&id) {
+            Some(other) => errs.push(Diagnostic::error(at, format!("C register id {} is already used by {}", id, other))),
+            None => { seen.insert(id.clone(), at); }
+        }
+        ids.push(id);
+    });
+    if errs.is_empty() { Ok(ids) } else { Err(errs) }
+}
+
+pub fn gen(schema: &Schema, header_name: &str) -> Result<(String, String), Vec<Diagnostic>> {
+    let ids = reg_ids(&schema.tree)?;
+    let mut regs = Vec::new();
+    schema.tree.visit_regs(&mut |reg| regs.push(reg.clone()));
+    Ok((header(schema, header_name, &ids), source(&regs, header_name, &ids)))
+}
+
+fn guard(header_name: &str) -> String {
+    header_name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect()
+}
+
+/// Enum body `ELLOCOPO_<name> = <value>,` of every value `f` knows
+fn variants<T: std::fmt::Debug>(prefix: &str, f: impl Fn(u8) -> Option<T>) -> String {
+    let mut s = String::new();
+    for (v, name) in (0..=u8::MAX).filter_map(|v| f(v).map(|n| (v, n))) {
+        writeln!(s, "    {}{}{:?} = {},", PREFIX, prefix, name, v).unwrap();
+    }
+    s
+}
+
+fn header(schema: &Schema, header_name: &str, ids: &[String]) -> String {
+    let guard = guard(header_name);
+    let mut s = String::new();
+    writeln!(s, "/* Generated by ellocopo2-codegen, do not edit */").unwrap();
+    writeln!(s, "#ifndef {}", guard).unwrap();
+    writeln!(s, "#define {}\n", guard).unwrap();
+    s.push_str("#include <stdbool.h>\n#include <stddef.h>\n#include <stdint.h>\n\n");
+    if let Some((major, minor)) = schema.header.version {
+        writeln!(s, "#define {}PROTOCOL_VERSION_MAJOR {}", PREFIX, major).unwrap();
+        writeln!(s, "#define {}PROTOCOL_VERSION_MINOR {}\n", PREFIX, minor).unwrap();
+    }
+
+    s.push_str("/* Frame: header, path, payload. Numbers are in native byte order */\n");
+    for (name, v) in [
+        ("SIGN", format!("0x{:02X}", SIGN)),
+        ("SIGN_AUTH", format!("0x{:02X}", SIGN_AUTH)),
+        ("HEADER_SZ", HEADER_SZ.to_string()),
+        ("MAX_MSG_SZ", MAX_MSG_SZ.to_string()),
+        ("MAX_PATH_SZ", MAX_PATH_SZ.to_string()),
+        ("MAX_PAYLOAD_SZ", MAX_PAYLOAD_SZ.to_string()),
+    ] {
+        writeln!(s, "#define {}{} {}", PREFIX, name, v).unwrap();
+    }
+
+    let req = |v| AnswerCode::try_from(v).ok().and_then(|c| RequestCode::try_from(c).ok());
+    let ans = |v| AnswerCode::try_from(v).ok();
+    let ty = |v| TypeTag::try_from(v).ok();
+    let lvl = |v| PrivLvl::try_from(v).ok();
+    writeln!(s, "\nenum ellocopo_req_code {{\n{}}};", variants("REQ_", req)).unwrap();
+    writeln!(s, "\nenum ellocopo_ans_code {{\n{}}};", variants("", ans)).unwrap();
+    writeln!(s, "\nenum ellocopo_type {{\n{}}};", variants("TY_", ty)).unwrap();
+    writeln!(s, "\nenum ellocopo_priv_lvl {{\n{}}};", variants("", lvl)).unwrap();
+
+    s.push_str("\n/* Register access flags */\n");
+    for (name, bit) in [("ACC_R", 0), ("ACC_W", 1), ("ACC_FAST", 2), ("ACC_TXN", 3)] {
+        writeln!(s, "#define {}{} 0x{:02X}", PREFIX, name, 1 << bit).unwrap();
+    }
+
+    s.push_str("\n/* Registers in scheme order */\nenum ellocopo_reg_id {\n");
+    for id in ids {
+        writeln!(s, "    {},", id).unwrap();
+    }
+    writeln!(s, "    {}REG_COUNT\n}};\n", PREFIX).unwrap();
+
+    s.push_str(r#"struct ellocopo_reg {
+    const char *path;
+    uint8_t path_sz;
+    /* enum ellocopo_type */
+    uint8_t type;
+    /* ELLOCOPO_ACC_* flags */
+    uint8_t access;
+    /* Levels required to write and read */
+    uint8_t w_lvl;
+    uint8_t r_lvl;
+};
+
+extern const struct ellocopo_reg ellocopo_regs[ELLOCOPO_REG_COUNT];
+
+/* Register id of path, -1 if there is no such register */
+int ellocopo_path2id(const char *path, size_t path_sz);
+
+/* Implemented by application.
+ * Read register `id` into `buf` of `cap` bytes, returns payload size or -answer code. */
+int ellocopo_read(int id, uint8_t *buf, size_t cap);
+/* Write `sz` payload bytes to register `id`, returns 0 or -answer code. */
+int ellocopo_write(int id, const uint8_t *payload, size_t sz);
+
+/* Answer READ or WRITE request `req` of `req_sz` bytes into `ans` of
+ * ELLOCOPO_MAX_MSG_SZ bytes, `ans` may be `req`. Returns answer size. */
+size_t ellocopo_dispatch(const uint8_t *req, size_t req_sz, uint8_t *ans, uint8_t sys_lvl);
+"#);
+    writeln!(s, "\n#endif /* {} */", guard).unwrap();
+    s
+}
+
+fn source(regs: &[Register], header_name: &str, ids: &[String]) -> String {
+    let paths: Vec<String> = regs.iter().map(|r| "/".to_string() + &r.path.join("/")).collect();
+    let PerfectHash{seed, tail, slots} = perfect_hash(&paths);
+
+    let mut s = String::new();
+    writeln!(s, "/* Generated by ellocopo2-codegen, do not edit */").unwrap();
+    writeln!(s, "#include <string.h>\n\n#include \"{}\"\n", header_name).unwrap();
+
+    s.push_str("const struct ellocopo_reg ellocopo_regs[ELLOCOPO_REG_COUNT] = {\n");
+    for ((reg, path), id) in regs.iter().zip(&paths).zip(ids) {
+        let MetaDesc{w, r, fast, txn, w_plvl, r_plvl, ..} = reg.meta;
+        let access: Vec<String> = [(r, "ACC_R"), (w, "ACC_W"), (fast, "ACC_FAST"), (txn, "ACC_TXN")]
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, name)| format!("{}{}", PREFIX, name))
+            .collect();
+        let access = if access.is_empty() { "0".to_string() } else { access.join(" | ") };
+        writeln!(s, "    [{}] = {{ {:?}, {}, {}TY_{:?}, {}, {}{:?}, {}{:?} }},",
+            id, path, path.len(), PREFIX, reg.ty, access, PREFIX, w_plvl, PREFIX, r_plvl).unwrap();
+    }
+    s.push_str("};\n\n");
+
+    let slots: Vec<String> = slots.iter().map(u16::to_string).collect();
+    writeln!(s, "/* Register id + 1 by hash of path size and last {} bytes, 0 for empty slot */", tail).unwrap();
+    writeln!(s, "static const uint16_t slots[{}] = {{ {} }};\n", slots.len(), slots.join(", ")).unwrap();
+    writeln!(s, r#"static uint32_t fnv1a(uint32_t h, const uint8_t *p, size_t n)
+{{
+    h ^= 0x811C9DC5u;
+    while (n--) {{
+        h ^= *p++;
+        h *= 0x01000193u;
+    }}
+    return h;
+}}
+
+int ellocopo_path2id(const char *path, size_t path_sz)
+{{
+    size_t tail = path_sz < {tail} ? path_sz : {tail};
+    const uint8_t *key = (const uint8_t *)path + path_sz - tail;
+    uint16_t i = slots[fnv1a({seed}u ^ (uint32_t)path_sz, key, tail) & {mask}u];
+
+    if (i == 0 || (size_t)ellocopo_regs[i - 1].path_sz != path_sz || memcmp(ellocopo_regs[i - 1].path, path, path_sz) != 0)
+        return -1;
+    return i - 1;
+}}
+"#, tail = tail, seed = seed, mask = slots.len() - 1).unwrap();
+
+    s.push_str("\nstatic bool known_code(uint8_t code)\n{\n    switch (code) {\n");
+    let codes = (0..=u8::MAX).filter_map(|v| AnswerCode::try_from(v).ok().and_then(|c| RequestCode::try_from(c).ok()));
+    for code in codes {
+        writeln!(s, "    case {}REQ_{:?}:", PREFIX, code).unwrap();
+    }
+    s.push_str("        return true;\n    default:\n        return false;\n    }\n}\n");
+
+    s.push_str("\n/* Written payload keeps register @min/@max/@maxlen/@oneof */\n");
+    s.push_str("static bool in_range(int id, const uint8_t *payload, size_t sz)\n{\n");
+    s.push_str("    (void)payload;\n    (void)sz;\n    switch (id) {\n");
+    for (reg, id) in regs.iter().zip(ids) {
+        if let Some(check) = range_check(reg) {
+            writeln!(s, "    case {}: {{", id).unwrap();
+            if let Some(ty) = c_int(reg.ty) {
+                writeln!(s, "        {} v;\n        memcpy(&v, payload, sizeof v);", ty).unwrap();
+            }
+            writeln!(s, "        return {};\n    }}", check).unwrap();
+        }
+    }
+    s.push_str("    default:\n        return true;\n    }\n}\n");
+    s.push_str(DISPATCH);
+    s
+}
+
+/// Fixed size value type, BOOL is read as its byte
+fn c_int(ty: TypeTag) -> Option<&'static str> {
+    match ty {
+        TypeTag::BOOL | TypeTag::U8 => Some("uint8_t"),
+        TypeTag::I8 => Some("int8_t"),
+        TypeTag::I16 => Some("int16_t"),
+        TypeTag::U16 => Some("uint16_t"),
+        TypeTag::I32 => Some("int32_t"),
+        TypeTag::U32 => Some("uint32_t"),
+        TypeTag::UNIT | TypeTag::STR | TypeTag::BYTES => None,
+    }
+}
+
+/// Condition over value `v` or payload of `sz` bytes, bounds of the type itself are left out
+fn range_check(reg: &Register) -> Option<String> {
+    let Constraints{min, max, maxlen, oneof} = &reg.constraints;
+    let (ty_min, ty_max) = int_range(reg.ty).unwrap_or((i64::MIN, i64::MAX));
+    let mut checks = Vec::new();
+    if let Some(min) = min.filter(|m| *m > ty_min) {
+        checks.push(format!("v >= {}", min));
+    }
+    if let Some(max) = max.filter(|m| *m < ty_max) {
+        checks.push(format!("v <= {}", max));
+    }
+    if let Some(n) = maxlen {
+        checks.push(format!("sz <= {}", n));
+    }
+    if !oneof.is_empty() {
+        let alts: Vec<String> = oneof.iter().map(|lit| match lit {
+            Literal::Bool(b) => format!("v == {}", *b as u8),
+            Literal::Int(i) => format!("v == {}", i),
+            Literal::Str(s) => c_bytes_eq(s.as_bytes()),
+            Literal::Bytes(b) => c_bytes_eq(b),
+        }).collect();
+        checks.push(if alts.len() == 1 { alts[0].clone() } else { format!("({})", alts.join(" || ")) });
+    }
+    if checks.is_empty() { None } else { Some(checks.join(" && ")) }
+}
+
+/// Payload equals `b`, bytes are octal escaped
+fn c_bytes_eq(b: &[u8]) -> String {
+    if b.is_empty() {
+        return "sz == 0".to_string();
+    }
+    let lit: String = b.iter().map(|b| format!("\\{:03o}", b)).collect();
+    format!("(sz == {} && memcmp(payload, \"{}\", {}) == 0)", b.len(), lit, b.len())
+}
+
+/// Same checks and order as generated `req2msg`, errors are answered with UNIT payload
+const DISPATCH: &str = r#"
+/* Payload size of fixed size types, indexed by enum ellocopo_type */
+static const uint8_t ty_sz[ELLOCOPO_TY_STR] = { 0, 1, 4, 2, 1, 4, 2, 1 };
+
+static size_t answer(uint8_t *ans, uint8_t path_sz, uint8_t code, uint8_t ty, size_t payload_sz)
+{
+    ans[0] = ELLOCOPO_SIGN;
+    ans[1] = path_sz;
+    ans[2] = (uint8_t)payload_sz;
+    ans[3] = code;
+    ans[4] = ty;
+    return ELLOCOPO_HEADER_SZ + path_sz + payload_sz;
+}
+
+size_t ellocopo_dispatch(const uint8_t *req, size_t req_sz, uint8_t *ans, uint8_t sys_lvl)
+{
+    const struct ellocopo_reg *reg;
+    const uint8_t *payload;
+    uint8_t path_sz, payload_sz, code, ty, lvl;
+    int id, rc;
+
+    if (req_sz < ELLOCOPO_HEADER_SZ || req[0] != ELLOCOPO_SIGN)
+        return answer(ans, 0, ELLOCOPO_ERR_BAD_FORMAT, ELLOCOPO_TY_UNIT, 0);
+    path_sz = req[1];
+    payload_sz = req[2];
+    code = req[3];
+    ty = req[4];
+    if (path_sz > ELLOCOPO_MAX_PATH_SZ || ELLOCOPO_HEADER_SZ + (size_t)path_sz + payload_sz > req_sz
+        || ty > ELLOCOPO_TY_BYTES || (ty < ELLOCOPO_TY_STR && payload_sz != ty_sz[ty]))
+        return answer(ans, 0, ELLOCOPO_ERR_BAD_FORMAT, ELLOCOPO_TY_UNIT, 0);
+
+    /* Answer keeps request path */
+    memmove(ans + ELLOCOPO_HEADER_SZ, req + ELLOCOPO_HEADER_SZ, path_sz);
+    payload = req + ELLOCOPO_HEADER_SZ + path_sz;
+
+    if (!known_code(code))
+        return answer(ans, path_sz, ELLOCOPO_ERR_BAD_PROTO, ELLOCOPO_TY_UNIT, 0);
+    if (code != ELLOCOPO_REQ_READ && code != ELLOCOPO_REQ_WRITE)
+        return answer(ans, path_sz, ELLOCOPO_ERR_UNIMPL, ELLOCOPO_TY_UNIT, 0);
+    if (code == ELLOCOPO_REQ_READ && ty != ELLOCOPO_TY_UNIT)
+        return answer(ans, path_sz, ELLOCOPO_ERR_TYPE, ELLOCOPO_TY_UNIT, 0);
+
+    id = ellocopo_path2id((const char *)ans + ELLOCOPO_HEADER_SZ, path_sz);
+    if (id < 0)
+        return answer(ans, path_sz, ELLOCOPO_ERR_PATH, ELLOCOPO_TY_UNIT, 0);
+    reg = &ellocopo_regs[id];
+
+    /* Write level guards writes and every request to write only register */
+    if ((code == ELLOCOPO_REQ_WRITE && (reg->access & ELLOCOPO_ACC_W)) || !(reg->access & ELLOCOPO_ACC_R))
+        lvl = reg->w_lvl;
+    else
+        lvl = reg->r_lvl;
+    if (sys_lvl < lvl)
+        return answer(ans, path_sz, ELLOCOPO_ERR_PRIV, ELLOCOPO_TY_UNIT, 0);
+    if (!(reg->access & (code == ELLOCOPO_REQ_READ ? ELLOCOPO_ACC_R : ELLOCOPO_ACC_W)))
+        return answer(ans, path_sz, ELLOCOPO_ERR_ACCESS, ELLOCOPO_TY_UNIT, 0);
+
+    if (code == ELLOCOPO_REQ_READ) {
+        rc = ellocopo_read(id, ans + ELLOCOPO_HEADER_SZ + path_sz, ELLOCOPO_MAX_PAYLOAD_SZ);
+        if (rc < 0)
+            return answer(ans, path_sz, (uint8_t)-rc, ELLOCOPO_TY_UNIT, 0);
+        return answer(ans, path_sz, ELLOCOPO_OK_READ, reg->type, (size_t)rc);
+    }
+
+    if (ty != reg->type)
+        return answer(ans, path_sz, ELLOCOPO_ERR_TYPE, ELLOCOPO_TY_UNIT, 0);
+    if (!in_range(id, payload, payload_sz))
+        return answer(ans, path_sz, ELLOCOPO_ERR_RANGE, ELLOCOPO_TY_UNIT, 0);
+    rc = ellocopo_write(id, payload, payload_sz);
+    if (rc < 0)
+        return answer(ans, path_sz, (uint8_t)-rc, ELLOCOPO_TY_UNIT, 0);
+    return answer(ans, path_sz, ELLOCOPO_OK_WRITE, ELLOCOPO_TY_UNIT, 0);
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::TmpDir;
+
+    /// Registers served by test program, everything else answers ERR_UNIMPL
+    const C_APP: &str = r#"
+#include <stdio.h>
+#include <string.h>
+
+#include "regs.h"
+
+static bool record;
+
+int ellocopo_read(int id, uint8_t *buf, size_t cap)
+{
+    (void)cap;
+    switch (id) {
+    case ELLOCOPO_REG_CTRL_RECORD:
+        buf[0] = record;
+        return 1;
+    case ELLOCOPO_REG_BUILD_COMPILER:
+        memcpy(buf, "cc", 2);
+        return 2;
+    default:
+        return -ELLOCOPO_ERR_UNIMPL;
+    }
+}
+
+int ellocopo_write(int id, const uint8_t *payload, size_t sz)
+{
+    (void)sz;
+    switch (id) {
+    case ELLOCOPO_REG_CTRL_RECORD:
+        record = payload[0];
+        return 0;
+    default:
+        return -ELLOCOPO_ERR_UNIMPL;
+    }
+}
+
+/* Answers request frames from stdin to stdout, in place */
+int main(void)
+{
+    static uint8_t in[1 << 16];
+    size_t sz = fread(in, 1, sizeof(in), stdin), pos = 0;
+
+    while (pos + ELLOCOPO_HEADER_SZ <= sz) {
+        uint8_t frame[ELLOCOPO_MAX_MSG_SZ];
+        size_t req_sz = ELLOCOPO_HEADER_SZ + in[pos + 1] + in[pos + 2];
+        memcpy(frame, in + pos, req_sz);
+        fwrite(frame, 1, ellocopo_dispatch(frame, req_sz, frame, ELLOCOPO_NORMAL_LVL), stdout);
+        pos += req_sz;
+    }
+    return ellocopo_path2id("/build/compiler", 15) == ELLOCOPO_REG_BUILD_COMPILER ? 0 : 1;
+}
+"#;
+
+    #[test]
+    fn c_backend() {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+        use ellocopo2::{AnswerCode, ParseMsg, RequestBuilder, RequestCode, Value};
+
+        let txt = std::fs::read_to_string("../scheme.json").unwrap();
+        let c = crate::generate_c(&txt, "regs.h").unwrap();
+        assert!(c.header.contains("ELLOCOPO_REG_CALIB_ECG_K,"));
+
+        let dir = TmpDir::new("c");
+        std::fs::write(dir.join("regs.h"), &c.header).unwrap();
+        std::fs::write(dir.join("regs.c"), &c.source).unwrap();
+        std::fs::write(dir.join("app.c"), C_APP).unwrap();
+
+        let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+        let built = Command::new(&cc)
+            .current_dir(&*dir)
+            .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-pedantic", "-o", "app", "app.c", "regs.c"])
+            .status()
+            .unwrap_or_else(|e| panic!("C compiler {:?} is needed, set CC: {}", cc, e));
+        assert!(built.success(), "generated C doesn't compile");
+
+        let cases = [
+            (RequestCode::WRITE, "/ctrl/record", Value::BOOL(true), AnswerCode::OK_WRITE, Value::UNIT(())),
+            (RequestCode::READ, "/ctrl/record", Value::UNIT(()), AnswerCode::OK_READ, Value::BOOL(true)),
+            (RequestCode::READ, "/build/compiler", Value::UNIT(()), AnswerCode::OK_READ, Value::STR("cc")),
+            (RequestCode::READ, "/ctrl/record", Value::U8(1), AnswerCode::ERR_TYPE, Value::UNIT(())),
+            (RequestCode::WRITE, "/ctrl/record", Value::U8(1), AnswerCode::ERR_TYPE, Value::UNIT(())),
+            (RequestCode::READ, "/ctrl/nope", Value::UNIT(()), AnswerCode::ERR_PATH, Value::UNIT(())),
+            (RequestCode::WRITE, "/io/file/max", Value::U32(1), AnswerCode::ERR_ACCESS, Value::UNIT(())),
+            (RequestCode::READ, "/ctrl/event", Value::UNIT(()), AnswerCode::ERR_ACCESS, Value::UNIT(())),
+            (RequestCode::WRITE, "/calib/ecg/k", Value::I32(2), AnswerCode::ERR_PRIV, Value::UNIT(())),
+            (RequestCode::READ, "/calib/ecg/k", Value::UNIT(()), AnswerCode::ERR_UNIMPL, Value::UNIT(())),
+            (RequestCode::RESET, "/ctrl", Value::UNIT(()), AnswerCode::ERR_UNIMPL, Value::UNIT(())),
+            (RequestCode::WRITE, "/signal/ecgf/frq", Value::U8(201), AnswerCode::ERR_RANGE, Value::UNIT(())),
+            (RequestCode::WRITE, "/signal/ecgf/frq", Value::U8(200), AnswerCode::ERR_UNIMPL, Value::UNIT(())),
+            (RequestCode::WRITE, "/survey/sex", Value::STR("X"), AnswerCode::ERR_RANGE, Value::UNIT(())),
+            (RequestCode::WRITE, "/survey/sex", Value::STR("F"), AnswerCode::ERR_UNIMPL, Value::UNIT(())),
+        ];
+        let mut input = Vec::new();
+        for (code, path, v, _, _) in cases.iter() {
+            let mut buf = [0u8; ellocopo2::MAX_MSG_SZ];
+            let sz = RequestBuilder::new(&mut buf).code(*code).path(path).payload(*v).build().unwrap();
+            input.extend_from_slice(&buf[..sz]);
+        }
+
+        let mut app = Command::new(dir.join("app"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        app.stdin.take().unwrap().write_all(&input).unwrap();
+        let out = app.wait_with_output().unwrap();
+        assert!(out.status.success(), "path2id of /build/compiler");
+
+        let mut ans = &out.stdout[..];
+        for (_, path, _, code, v) in cases.iter() {
+            let sz = ellocopo2::HEADER_SZ + ans[1] as usize + ans[2] as usize;
+            let msg = ParseMsg::new().try_parse(&ans[..sz]).unwrap();
+            assert_eq!((msg.0, msg.1, msg.2), (*code, *path, *v), "{}", path);
+            ans = &ans[sz..];
+        }
+        assert!(ans.is_empty());
+    }
+}
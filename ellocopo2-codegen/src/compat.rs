@@ -0,0 +1,186 @@
+//! Wire compatibility of two scheme revisions
+//!
+//! Host built against `old` keeps working with device built from `new`
+//! unless some change is breaking: register gone, renamed, retyped, its
+//! access narrowed or its constraints refusing value allowed before.
+
+use std::fmt;
+
+use ellocopo2::{PrivLvl, TypeTag};
+
+use crate::docs::{constraints, lvl, ty_name};
+use crate::parser::*;
+
+/// Read and write rights of register with levels they need
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Access {
+    pub r: Option<PrivLvl>,
+    pub w: Option<PrivLvl>,
+}
+
+impl Access {
+    fn of(reg: &Register) -> Self {
+        let MetaDesc{r, w, r_plvl, w_plvl, ..} = reg.meta;
+        Self {
+            r: if r { Some(r_plvl) } else { None },
+            w: if w { Some(w_plvl) } else { None },
+        }
+    }
+
+    /// Some request allowed by `self` is refused with `new`
+    fn narrowed(self, new: Access) -> bool {
+        let lost = |old: Option<PrivLvl>, new: Option<PrivLvl>| match (old, new) {
+            (Some(_), None) => true,
+            (Some(o), Some(n)) => n > o,
+            _ => false,
+        };
+        lost(self.r, new.r) || lost(self.w, new.w)
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.r, self.w) {
+            (Some(r), Some(w)) => write!(f, "R {}, W {}", lvl(r), lvl(w)),
+            (Some(r), None) => write!(f, "R {}", lvl(r)),
+            (None, Some(w)) => write!(f, "W {}", lvl(w)),
+            (None, None) => write!(f, "none"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    Added { path: String },
+    Removed { path: String },
+    TypeChanged { path: String, old: TypeTag, new: TypeTag },
+    AccessNarrowed { path: String, old: Access, new: Access },
+    AccessWidened { path: String, old: Access, new: Access },
+    /// Some value written before is now out of `@min`, `@max`, `@maxlen` or `@oneof`
+    ConstraintsNarrowed { path: String, old: Constraints, new: Constraints },
+    ConstraintsWidened { path: String, old: Constraints, new: Constraints },
+    /// Register moved to other path, type and access kept
+    Renamed { old: String, new: String },
+}
+
+impl Change {
+    /// Requests valid for `old` scheme may fail with `new` one
+    pub fn breaking(&self) -> bool {
+        !matches!(self, Change::Added{..} | Change::AccessWidened{..} | Change::ConstraintsWidened{..})
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", if self.breaking() { "breaking" } else { "compatible" })?;
+        match self {
+            Change::Added{path} => write!(f, "{}: added", path),
+            Change::Removed{path} => write!(f, "{}: removed", path),
+            Change::TypeChanged{path, old, new} => write!(f, "{}: type {} -> {}", path, ty_name(*old), ty_name(*new)),
+            Change::AccessNarrowed{path, old, new} => write!(f, "{}: access narrowed {} -> {}", path, old, new),
+            Change::AccessWidened{path, old, new} => write!(f, "{}: access widened {} -> {}", path, old, new),
+            Change::ConstraintsNarrowed{path, old, new} => write!(f, "{}: constraints narrowed {} -> {}", path, Shown(old), Shown(new)),
+            Change::ConstraintsWidened{path, old, new} => write!(f, "{}: constraints widened {} -> {}", path, Shown(old), Shown(new)),
+            Change::Renamed{old, new} => write!(f, "{}: renamed to {}", old, new),
+        }
+    }
+}
+
+/// Constraints as in register map, `none` if empty
+struct Shown<'a>(&'a Constraints);
+
+impl fmt::Display for Shown<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() { f.write_str("none") } else { f.write_str(&constraints(self.0)) }
+    }
+}
+
+/// Some value allowed by `old` is refused by `new`
+fn narrowed(old: &Constraints, new: &Constraints) -> bool {
+    let lost = |old: Option<i64>, new: Option<i64>, below: bool| match (old, new) {
+        (_, None) => false,
+        (None, Some(_)) => true,
+        (Some(o), Some(n)) => if below { n > o } else { n < o },
+    };
+    lost(old.min, new.min, true)
+        || lost(old.max, new.max, false)
+        || lost(old.maxlen.map(|n| n as i64), new.maxlen.map(|n| n as i64), false)
+        || (!new.oneof.is_empty() && (old.oneof.is_empty() || old.oneof.iter().any(|l| !new.oneof.contains(l))))
+}
+
+fn registers<'a>(node: &'a DslTree, regs: &mut Vec<(String, &'a Register)>) {
+    match node {
+        DslTree::SectionV(section) => section.children.iter().for_each(|c| registers(c, regs)),
+        DslTree::RegisterV(reg) => regs.push(("/".to_string() + &reg.path.join("/"), reg)),
+    }
+}
+
+/// Changes from `old` to `new`, in `old` scheme order followed by added registers
+pub fn diff(old: &DslTree, new: &DslTree) -> Vec<Change> {
+    let (mut old_regs, mut new_regs) = (Vec::new(), Vec::new());
+    registers(old, &mut old_regs);
+    registers(new, &mut new_regs);
+    let find = |regs: &[(String, &Register)], path: &str| regs.iter().any(|(p, _)| p == path);
+
+    let removed: Vec<&(String, &Register)> = old_regs.iter().filter(|(p, _)| !find(&new_regs, p)).collect();
+    let added: Vec<&(String, &Register)> = new_regs.iter().filter(|(p, _)| !find(&old_regs, p)).collect();
+    let renames = renames(&removed, &added);
+
+    let mut changes = Vec::new();
+    for (path, reg) in &old_regs {
+        if let Some((_, to)) = renames.iter().find(|(from, _)| from == path) {
+            changes.push(Change::Renamed { old: path.clone(), new: to.clone() });
+            continue;
+        }
+        let now = match new_regs.iter().find(|(p, _)| p == path) {
+            Some((_, now)) => now,
+            None => {
+                changes.push(Change::Removed { path: path.clone() });
+                continue;
+            }
+        };
+        if reg.ty != now.ty {
+            changes.push(Change::TypeChanged { path: path.clone(), old: reg.ty, new: now.ty });
+        }
+        let (old, new) = (Access::of(reg), Access::of(now));
+        if old.narrowed(new) {
+            changes.push(Change::AccessNarrowed { path: path.clone(), old, new });
+        } else if old != new {
+            changes.push(Change::AccessWidened { path: path.clone(), old, new });
+        }
+        // Constraints only apply to writes allowed by both
+        let (old, new) = (&reg.constraints, &now.constraints);
+        let written = reg.meta.w && now.meta.w;
+        if written && narrowed(old, new) {
+            changes.push(Change::ConstraintsNarrowed { path: path.clone(), old: old.clone(), new: new.clone() });
+        } else if written && old != new {
+            changes.push(Change::ConstraintsWidened { path: path.clone(), old: old.clone(), new: new.clone() });
+        }
+    }
+    changes.extend(added.iter()
+        .filter(|(path, _)| !renames.iter().any(|(_, to)| to == path))
+        .map(|(path, _)| Change::Added { path: path.clone() }));
+    changes
+}
+
+/// Removed and added registers paired into renames
+///
+/// Pair must keep type and access, and either leaf name or parent section.
+/// Ambiguous candidates are left as removed and added.
+fn renames(removed: &[&(String, &Register)], added: &[&(String, &Register)]) -> Vec<(String, String)> {
+    let parent = |r: &Register| r.path[..r.path.len() - 1].to_vec();
+    let same = |a: &Register, b: &Register| {
+        a.ty == b.ty && Access::of(a) == Access::of(b) && (a.name == b.name || parent(a) == parent(b))
+    };
+
+    let mut renames = Vec::new();
+    for (from, old) in removed {
+        let candidates: Vec<&&(String, &Register)> = added.iter().filter(|(_, new)| same(old, new)).collect();
+        if let [(to, new)] = candidates[..] {
+            if removed.iter().filter(|(_, r)| same(r, new)).count() == 1 {
+                renames.push((from.clone(), to.clone()));
+            }
+        }
+    }
+    renames
+}
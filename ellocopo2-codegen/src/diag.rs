@@ -0,0 +1,168 @@
+//! Schema diagnostics: what is wrong and where
+//!
+//! Location is JSON pointer to the offending key, e.g. `/io/file/max/@access`,
+//! line and column are looked up in the scheme text once the problem is found.
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// JSON pointer, empty for the whole scheme
+    pub path: String,
+    pub msg: String,
+    /// 1-based line and column
+    pub pos: Option<(usize, usize)>,
+}
+
+impl Diagnostic {
+    pub fn error(path: impl Into<String>, msg: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            path: path.into(),
+            msg: msg.into(),
+            pos: None,
+        }
+    }
+
+    pub fn warning(path: impl Into<String>, msg: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(path, msg)
+        }
+    }
+
+    /// Fill line and column from scheme `src`
+    pub fn locate(mut self, src: &str) -> Self {
+        if self.pos.is_none() {
+            self.pos = locate(src, &self.path);
+        }
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "error")?,
+            Severity::Warning => write!(f, "warning")?,
+        }
+        if !self.path.is_empty() {
+            write!(f, ": {}", self.path)?;
+        }
+        if let Some((line, col)) = self.pos {
+            write!(f, " ({}:{})", line, col)?;
+        }
+        write!(f, ": {}", self.msg)
+    }
+}
+
+/// JSON pointer of `key` under register or section `path`
+pub fn pointer(path: &[String], key: Option<&str>) -> String {
+    let mut p = String::new();
+    for s in path.iter().map(String::as_str).chain(key) {
+        p.push('/');
+        p.push_str(s);
+    }
+    p
+}
+
+/// Line and column of the key `path` points to
+fn locate(src: &str, path: &str) -> Option<(usize, usize)> {
+    if path.is_empty() {
+        return None;
+    }
+    let keys: Vec<&str> = path[1..].split('/').collect();
+    let mut s = Scanner { src: src.as_bytes(), pos: 0 };
+    s.ws();
+    let offset = s.find(&keys)?;
+
+    let before = &src[..offset];
+    let line = before.matches('\n').count() + 1;
+    let col = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    Some((line, col))
+}
+
+/// Just enough of JSON to walk keys of already valid document
+struct Scanner<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn ws(&mut self) {
+        while self.pos < self.src.len() && self.src[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    /// Offset of the key `keys` lead to inside value at current position
+    fn find(&mut self, keys: &[&str]) -> Option<usize> {
+        if self.peek()? != b'{' {
+            return None;
+        }
+        self.pos += 1;
+        loop {
+            self.ws();
+            match self.peek()? {
+                b'}' => return None,
+                b',' => { self.pos += 1; continue; }
+                _ => (),
+            }
+            let start = self.pos;
+            let key = self.string()?;
+            self.ws();
+            self.pos += 1; // :
+            self.ws();
+            if key == keys[0] {
+                return if keys.len() == 1 { Some(start) } else { self.find(&keys[1..]) };
+            }
+            self.skip()?;
+        }
+    }
+
+    /// String at current position, escapes are kept as is
+    fn string(&mut self) -> Option<String> {
+        let start = self.pos + 1;
+        self.pos = start;
+        while self.peek()? != b'"' {
+            self.pos += if self.peek()? == b'\\' { 2 } else { 1 };
+        }
+        self.pos += 1;
+        std::str::from_utf8(&self.src[start..self.pos - 1]).ok().map(String::from)
+    }
+
+    fn skip(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'"' => { self.string()?; }
+            open @ (b'{' | b'[') => {
+                let close = if open == b'{' { b'}' } else { b']' };
+                self.pos += 1;
+                loop {
+                    self.ws();
+                    match self.peek()? {
+                        c if c == close => { self.pos += 1; break; }
+                        b',' | b':' => self.pos += 1,
+                        _ => self.skip()?,
+                    }
+                }
+            }
+            _ => {
+                while !matches!(self.peek()?, b',' | b'}' | b']') && !self.peek()?.is_ascii_whitespace() {
+                    self.pos += 1;
+                }
+            }
+        }
+        Some(())
+    }
+}
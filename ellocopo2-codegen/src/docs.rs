@@ -0,0 +1,265 @@
+//! Register map: path, type, access, privilege, default, constraints and description
+//!
+//! Every section holding registers gets a table under its path heading,
+//! `@doc`/`@com` text goes to the description column.
+
+use std::fmt::Write;
+
+use ellocopo2::{PrivLvl, TypeTag};
+
+use crate::parser::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+const COLUMNS: [&str; 7] = ["Path", "Type", "Access", "Privilege", "Default", "Constraints", "Description"];
+
+pub fn gen(schema: &Schema, format: DocFormat) -> String {
+    let mut w = Writer { format, s: String::new() };
+    w.begin();
+    w.heading(1, "Register map");
+    if let Some((major, minor)) = schema.header.version {
+        w.para(&format!("Protocol version {}.{}", major, minor));
+    }
+    if let DslTree::SectionV(root) = &schema.tree {
+        if let Some(doc) = &root.doc {
+            w.para(doc);
+        }
+    }
+    section(&mut w, &schema.tree);
+
+    if !schema.errors.is_empty() {
+        w.heading(2, "Errors");
+        w.para("Detail codes of `ERR_CUSTOM` answers");
+        let rows: Vec<Vec<String>> = schema.errors.iter()
+            .map(|e| vec![code(&e.name), e.code.to_string()])
+            .collect();
+        w.table(&["Name", "Code"], &rows);
+    }
+    w.end();
+    w.s
+}
+
+fn section(w: &mut Writer, node: &DslTree) {
+    let Section{path, doc, children, ..} = match node {
+        DslTree::SectionV(section) => section,
+        DslTree::RegisterV(_) => return,
+    };
+    let regs: Vec<Vec<String>> = children.iter()
+        .filter_map(|c| match c {
+            DslTree::RegisterV(reg) => Some(row(reg)),
+            DslTree::SectionV(_) => None,
+        })
+        .collect();
+    if !path.is_empty() {
+        w.heading(2, &code(&("/".to_string() + &path.join("/"))));
+        if let Some(doc) = doc {
+            w.para(doc);
+        }
+    }
+    if !regs.is_empty() {
+        w.table(&COLUMNS, &regs);
+    }
+    for c in children {
+        section(w, c);
+    }
+}
+
+fn row(reg: &Register) -> Vec<String> {
+    let MetaDesc{w, r, fast, txn, persist, w_plvl, r_plvl} = reg.meta;
+    let mut access = match (r, w) {
+        (true, true) => "RW",
+        (true, false) => "RO",
+        _ => "WO",
+    }.to_string();
+    if fast {
+        access += ", fast";
+    }
+    if txn {
+        access += ", txn";
+    }
+    if persist {
+        access += ", persist";
+    }
+    let privilege = match (r, w) {
+        (true, true) if r_plvl != w_plvl => format!("R {}, W {}", lvl(r_plvl), lvl(w_plvl)),
+        (true, true) | (true, false) => lvl(r_plvl).to_string(),
+        _ => lvl(w_plvl).to_string(),
+    };
+    let default = reg.default.as_ref().map(literal).unwrap_or_default();
+    vec![
+        code(&("/".to_string() + &reg.path.join("/"))),
+        code(ty_name(reg.ty)),
+        access,
+        privilege,
+        if default.is_empty() { default } else { code(&default) },
+        constraints(&reg.constraints),
+        reg.doc.clone().unwrap_or_default(),
+    ]
+}
+
+fn literal(lit: &Literal) -> String {
+    match lit {
+        Literal::Bool(b) => b.to_string(),
+        Literal::Int(i) => i.to_string(),
+        Literal::Str(s) => format!("{:?}", s),
+        Literal::Bytes(b) => format!("{:?}", b),
+    }
+}
+
+pub(crate) fn constraints(c: &Constraints) -> String {
+    let mut parts = Vec::new();
+    match (c.min, c.max) {
+        (Some(min), Some(max)) => parts.push(code(&format!("{}..={}", min, max))),
+        (Some(min), None) => parts.push(code(&format!(">= {}", min))),
+        (None, Some(max)) => parts.push(code(&format!("<= {}", max))),
+        (None, None) => (),
+    }
+    if let Some(n) = c.maxlen {
+        parts.push(format!("len <= {}", n));
+    }
+    if !c.oneof.is_empty() {
+        let alts: Vec<String> = c.oneof.iter().map(|l| code(&literal(l))).collect();
+        parts.push(format!("one of {}", alts.join(", ")));
+    }
+    parts.join(", ")
+}
+
+/// Type as written in the scheme
+pub(crate) fn ty_name(ty: TypeTag) -> &'static str {
+    match ty {
+        TypeTag::UNIT => "()",
+        TypeTag::BOOL => "bool",
+        TypeTag::I32 => "i32",
+        TypeTag::I16 => "i16",
+        TypeTag::I8 => "i8",
+        TypeTag::U32 => "u32",
+        TypeTag::U16 => "u16",
+        TypeTag::U8 => "u8",
+        TypeTag::STR => "str",
+        TypeTag::BYTES => "[u8]",
+    }
+}
+
+pub(crate) fn lvl(lvl: PrivLvl) -> &'static str {
+    match lvl {
+        PrivLvl::NORMAL_LVL => "NORMAL",
+        PrivLvl::MODE1_LVL => "MODE1",
+        PrivLvl::MODE2_LVL => "MODE2",
+        PrivLvl::MODE3_LVL => "MODE3",
+        PrivLvl::SECUR_LVL => "SECUR",
+        PrivLvl::DEVEL_LVL => "DEVEL",
+        PrivLvl::UNDEF_LVL => "UNDEF",
+    }
+}
+
+/// Inline code marker, turned into `<code>` for HTML
+fn code(s: &str) -> String {
+    format!("`{}`", s)
+}
+
+struct Writer {
+    format: DocFormat,
+    s: String,
+}
+
+impl Writer {
+    fn begin(&mut self) {
+        if self.format == DocFormat::Html {
+            self.s.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Register map</title>\n</head>\n<body>\n");
+        }
+    }
+
+    fn end(&mut self) {
+        if self.format == DocFormat::Html {
+            self.s.push_str("</body>\n</html>\n");
+        }
+    }
+
+    fn heading(&mut self, level: usize, text: &str) {
+        let text = self.inline(text);
+        match self.format {
+            DocFormat::Markdown => writeln!(self.s, "{} {}\n", "#".repeat(level), text),
+            DocFormat::Html => writeln!(self.s, "<h{0}>{1}</h{0}>", level, text),
+        }.unwrap();
+    }
+
+    fn para(&mut self, text: &str) {
+        match self.format {
+            DocFormat::Markdown => {
+                // Hard line breaks keep `@com` lines apart
+                let lines: Vec<String> = text.lines().map(|l| self.inline(l)).collect();
+                writeln!(self.s, "{}\n", lines.join("  \n"))
+            }
+            DocFormat::Html => writeln!(self.s, "<p>{}</p>", self.cell(text)),
+        }.unwrap();
+    }
+
+    fn table(&mut self, columns: &[&str], rows: &[Vec<String>]) {
+        match self.format {
+            DocFormat::Markdown => {
+                writeln!(self.s, "| {} |", columns.join(" | ")).unwrap();
+                writeln!(self.s, "|{}", "---|".repeat(columns.len())).unwrap();
+                for r in rows {
+                    let cells: Vec<String> = r.iter().map(|c| self.cell(c)).collect();
+                    writeln!(self.s, "| {} |", cells.join(" | ")).unwrap();
+                }
+                self.s.push('\n');
+            }
+            DocFormat::Html => {
+                self.s.push_str("<table>\n<tr>");
+                for c in columns {
+                    write!(self.s, "<th>{}</th>", c).unwrap();
+                }
+                self.s.push_str("</tr>\n");
+                for r in rows {
+                    self.s.push_str("<tr>");
+                    for c in r {
+                        write!(self.s, "<td>{}</td>", self.cell(c)).unwrap();
+                    }
+                    self.s.push_str("</tr>\n");
+                }
+                self.s.push_str("</table>\n");
+            }
+        }
+    }
+
+    /// Multiline text of table cell or paragraph
+    fn cell(&self, text: &str) -> String {
+        let lines: Vec<String> = text.lines().map(|l| self.inline(l)).collect();
+        match self.format {
+            DocFormat::Markdown => lines.join("<br>").replace('|', "\\|"),
+            DocFormat::Html => lines.join("<br>"),
+        }
+    }
+
+    /// One line with `code` spans
+    fn inline(&self, text: &str) -> String {
+        match self.format {
+            // Text outside code spans would otherwise pass as HTML tags
+            DocFormat::Markdown => text.split('`').enumerate()
+                .map(|(i, part)| if i % 2 == 1 { part.to_string() } else { part.replace('<', "&lt;").replace('>', "&gt;") })
+                .collect::<Vec<_>>()
+                .join("`"),
+            DocFormat::Html => {
+                let escaped = text
+                    .replace('&', "&amp;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;")
+                    .replace('"', "&quot;");
+                let mut s = String::new();
+                for (i, part) in escaped.split('`').enumerate() {
+                    if i % 2 == 1 {
+                        write!(s, "<code>{}</code>", part).unwrap();
+                    } else {
+                        s.push_str(part);
+                    }
+                }
+                s
+            }
+        }
+    }
+}
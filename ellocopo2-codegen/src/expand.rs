@@ -0,0 +1,145 @@
+//! Scheme reuse, resolved before the tree is parsed
+//!
+//! `@include` merges other scheme files into the object it's written in,
+//! `@use` merges named bodies of the root `@template` table. Own keys of the
+//! object win and nested objects are merged key by key, so a use site can
+//! override single annotation of a template register:
+//!
+//! ```json
+//! "@template": { "cal": { "k": { "@type": "i32", "@default": 1 }, "b": "i32" } },
+//! "calib": {
+//!     "ecg": { "@use": "cal" },
+//!     "acc": { "@use": "cal", "k": { "@default": 16 } }
+//! }
+//! ```
+//!
+//! Template of a plain type string is a register template. Section arrays
+//! `name[N]` are lowered later by the parser.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Map, Value as JsonValue};
+
+use crate::diag::{pointer, Diagnostic};
+use crate::parser::Format;
+
+pub(crate) const ANNOTATION_INCLUDE_STR:  &str = "@include";
+pub(crate) const ANNOTATION_TEMPLATE_STR: &str = "@template";
+pub(crate) const ANNOTATION_USE_STR:      &str = "@use";
+
+/// Scheme `root` with includes and templates resolved, `base` is directory
+/// include paths are relative to
+pub(crate) fn expand(mut root: JsonValue, base: &Path) -> Result<JsonValue, Diagnostic> {
+    includes(&mut root, base, &mut Vec::new(), &mut Vec::new())?;
+    let templates = match &mut root {
+        JsonValue::Object(fields) => match fields.remove(ANNOTATION_TEMPLATE_STR) {
+            None => Map::new(),
+            Some(JsonValue::Object(templates)) => templates,
+            Some(v) => Err(Diagnostic::error(pointer(&[], Some(ANNOTATION_TEMPLATE_STR)), format!("Malformed table: {}", v)))?,
+        },
+        _ => return Ok(root),
+    };
+    uses(&mut root, &templates, &mut Vec::new(), &mut Vec::new())?;
+    Ok(root)
+}
+
+/// Merge `other` into `own`, keys of `own` win
+fn merge(own: &mut Map<String, JsonValue>, other: Map<String, JsonValue>) {
+    for (k, v) in other {
+        match (own.get_mut(&k), v) {
+            (Some(JsonValue::Object(own)), JsonValue::Object(other)) => merge(own, other),
+            (Some(_), _) => (),
+            (None, v) => {
+                own.insert(k, v);
+            }
+        }
+    }
+}
+
+/// Take name or list of names under `key` out of `fields`
+fn names(fields: &mut Map<String, JsonValue>, path: &[String], key: &str) -> Result<Vec<String>, Diagnostic> {
+    let at = |v: &JsonValue| Diagnostic::error(pointer(path, Some(key)), format!("Name or list of names expected: {}", v));
+    match fields.remove(key) {
+        None => Ok(Vec::new()),
+        Some(JsonValue::String(name)) => Ok(vec![name]),
+        Some(JsonValue::Array(list)) => list.iter()
+            .map(|v| v.as_str().map(String::from).ok_or_else(|| at(v)))
+            .collect(),
+        Some(v) => Err(at(&v)),
+    }
+}
+
+/// `stack` holds files being included, to catch include cycles
+fn includes(v: &mut JsonValue, base: &Path, path: &mut Vec<String>, stack: &mut Vec<PathBuf>) -> Result<(), Diagnostic> {
+    let fields = match v {
+        JsonValue::Object(fields) => fields,
+        _ => return Ok(()),
+    };
+
+    for file in names(fields, path, ANNOTATION_INCLUDE_STR)? {
+        let at = |msg: String| Diagnostic::error(pointer(path, Some(ANNOTATION_INCLUDE_STR)), msg);
+        let file = base.join(file);
+        let format = Format::from_path(&file)
+            .ok_or_else(|| at(format!("Unknown scheme format: {}", file.display())))?;
+        let src = std::fs::read_to_string(&file)
+            .map_err(|e| at(format!("{}: {}", file.display(), e)))?;
+        let canonical = file.canonicalize().unwrap_or_else(|_| file.clone());
+        if stack.contains(&canonical) {
+            Err(at(format!("{} includes itself", file.display())))?
+        }
+
+        // Problems inside included file are reported at the include
+        let inner = |d: Diagnostic| at(format!("{}: {}", file.display(), format.locate(d, &src)));
+        let mut included = format.read(&src).map_err(inner)?;
+        stack.push(canonical);
+        let res = includes(&mut included, file.parent().unwrap_or(base), &mut Vec::new(), stack);
+        stack.pop();
+        res.map_err(inner)?;
+
+        match included {
+            JsonValue::Object(other) => merge(fields, other),
+            v => Err(at(format!("{}: object expected: {}", file.display(), v)))?,
+        }
+    }
+
+    for (k, v) in fields.iter_mut() {
+        path.push(k.clone());
+        includes(v, base, path, stack)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+/// `stack` holds templates being expanded, to catch template cycles
+fn uses(v: &mut JsonValue, templates: &Map<String, JsonValue>, path: &mut Vec<String>, stack: &mut Vec<String>) -> Result<(), Diagnostic> {
+    let fields = match v {
+        JsonValue::Object(fields) => fields,
+        _ => return Ok(()),
+    };
+
+    for name in names(fields, path, ANNOTATION_USE_STR)? {
+        let at = |msg: String| Diagnostic::error(pointer(path, Some(ANNOTATION_USE_STR)), msg);
+        if stack.contains(&name) {
+            Err(at(format!("Template {} uses itself", name)))?
+        }
+        let mut body = match templates.get(&name) {
+            Some(body @ JsonValue::Object(_)) => body.clone(),
+            Some(JsonValue::String(ty)) => json!({ "@type": ty }),
+            Some(v) => Err(at(format!("Malformed template {}: {}", name, v)))?,
+            None => Err(at(format!("Unknown template: {}", name)))?,
+        };
+        stack.push(name);
+        uses(&mut body, templates, path, stack)?;
+        stack.pop();
+        if let JsonValue::Object(body) = body {
+            merge(fields, body);
+        }
+    }
+
+    for (k, v) in fields.iter_mut() {
+        path.push(k.clone());
+        uses(v, templates, path, stack)?;
+        path.pop();
+    }
+    Ok(())
+}
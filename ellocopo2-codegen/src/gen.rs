@@ -4,7 +4,7 @@ use syn::{Ident, Expr, parse_str};
 //use syn::parse::{Parse, ParseStream};
 use quote::quote;
 
-use ellocopo2::TypeTag;
+use ellocopo2::{TypeTag, PrivLvl};
 
 use crate::parser::REGISTER_PATH_DELIMETR;
 
@@ -13,6 +13,13 @@ use crate::parser::{
     Section,
     DslTree,
     MetaDesc,
+    Schema,
+    SchemaHeader,
+    Visibility,
+    ErrorDesc,
+    Constraints,
+    Literal,
+    int_range,
 };
 
 pub struct MsgStream {
@@ -34,28 +41,48 @@ impl Default for MsgStream {
 // cargo test && rustfmt --emit stdout codegen.rs && rm codegen.rs
 
 /// Entry point for codegen
-pub fn gen(mut dsl: DslTree) -> String {
-    dsl = preproc(dsl);
+pub fn gen(schema: Schema) -> String {
+    let Schema{header, tree: dsl, errors, ..} = schema;
+    let dsl = preproc(dsl);
 
     //let msg_enum = gen_msg_enum(list.clone());
     //let msg_infra = gen_req2msg(list.clone());
     //let _typecheck = gen_typecheck(list.clone());
     //let _path2num = gen_path2num(list.clone());
     
-    let MsgStream{def, ..} = msg_enum_def::gen(&dsl);
-    //let cb = gen_fstcb(&dsl);
+    let MsgStream{def, lf, ..} = msg_enum_def::gen(&dsl);
     
-    let dispatch = dispatch::gen(&dsl);
+    let handler = handler::gen(&dsl);
+    let dispatch = dispatch::gen(&dsl, lf);
+    let reset = reset::gen(&dsl, lf);
+    let txn = txn::gen(&dsl, lf);
+    let persist = persist::gen(&dsl, header.version, lf);
+    let client = client::gen(&dsl);
+    let errors = custom_err::gen(&errors);
+    let header = header::gen(&header);
 
     quote!(
+        #header
+
         pub mod msg {
             #def
         }
         pub use msg::*;
 
-    //    #cb
+        #handler
+
         #dispatch
 
+        #reset
+
+        #txn
+
+        #persist
+
+        #client
+
+        #errors
+
     ).to_string()
 }
 
@@ -92,7 +119,8 @@ mod msg_enum_def {
 
         match dsl {
             DslTree::SectionV(section) => {
-                if !section.meta.fast {
+                // Elements of section array share the enum of the first one
+                if !section.meta.fast && section.index.unwrap_or(0) == 0 {
                     visit_section(section)
                 } else { Default::default() }
             }
@@ -106,7 +134,15 @@ mod msg_enum_def {
 
     fn visit_section(sec: &Section) -> MsgStream {
 
-        let Section{name, children, ..} = sec;
+        let Section{name, children, path, doc, index, ..} = sec;
+        let head = if path.is_empty() {
+            "Requests of the scheme".to_string()
+        } else if index.is_some() {
+            format!("Requests to `/{}/<index>`", path[..path.len() - 1].join("/"))
+        } else {
+            format!("Requests to `/{}`", path.join("/"))
+        };
+        let docs = doc_attrs(&head, doc.as_deref());
         let mut definitions = TokenStream::new();
         let mut variants = TokenStream::new();
 
@@ -127,6 +163,7 @@ mod msg_enum_def {
 
             #definitions
 
+            #docs
             #[allow(non_camel_case_types)]
             #[derive(Debug, Clone, Copy, PartialEq, Eq)]
             pub enum #name_ident#lf_token {
@@ -134,9 +171,11 @@ mod msg_enum_def {
             }
         );
 
-        let out_var = quote!( 
-            #variant_ident(#name_ident#lf_token),
-        );
+        let out_var = if index.is_some() {
+            quote!( #variant_ident(u8, #name_ident#lf_token), )
+        } else {
+            quote!( #variant_ident(#name_ident#lf_token), )
+        };
 
         MsgStream {
             def: out_def, vars: out_var,
@@ -146,7 +185,8 @@ mod msg_enum_def {
 
     fn visit_register(reg: &Register) -> MsgStream {
         
-        let Register{name, ty, meta: MetaDesc{w, r, ..}, ..} = reg;
+        let Register{name, ty, meta: MetaDesc{w, r, ..}, path, doc, ..} = reg;
+        let path = "/".to_string() + &path.join("/");
         let mut stream = TokenStream::new();
 
         let lf = if (TypeTag::STR == *ty || TypeTag::BYTES == *ty) && *w {
@@ -159,14 +199,16 @@ mod msg_enum_def {
             let name_s = &(name.clone() + ENUM_WRITE_POSTFIX);
             let name_ident = Ident::new(&name_s, Span::call_site());
             let ty_ts = convert_ty(*ty);
-            stream.extend(quote!( #name_ident(#ty_ts), ));
+            let docs = doc_attrs(&format!("Write `{}`", path), doc.as_deref());
+            stream.extend(quote!( #docs #name_ident(#ty_ts), ));
         }
 
         if *r {
 
             let name_s = &(name.clone() + ENUM_READ_POSTFIX);
             let name_ident = Ident::new(&name_s, Span::call_site());
-            stream.extend(quote!( #name_ident, ));
+            let docs = doc_attrs(&format!("Read `{}`", path), doc.as_deref());
+            stream.extend(quote!( #docs #name_ident, ));
         }
         
         MsgStream {
@@ -178,31 +220,97 @@ mod msg_enum_def {
 
 }
 
-fn gen_fstcb(dsl: &DslTree) -> TokenStream {
-    let mut fn_names = Vec::new();
-    let mut pn_msgs = Vec::new();
-
-    dsl.visit_regs(&mut |reg| {
-        if reg.meta.fast {
-            if reg.meta.w { unimplemented!() }
-            let fn_name_s = "cb_".to_string() + &reg.path.join("_") + "_r";
-            pn_msgs.push(fn_name_s.clone());
-            let fn_name = Ident::new(&fn_name_s, Span::call_site());
-            fn_names.push(fn_name);
-        }
-    });
-    
-    quote!(
-        #(
-            #[no_mangle]
-            #[linkage = "weak"]
-            pub fn #fn_names() -> Value<'static> {
-                unimplemented!(#pn_msgs)
-            }
-        )*
-    )
+/// Generation of root annotation constants
+mod header {
+    use super::*;
+
+    pub fn gen(header: &SchemaHeader) -> TokenStream {
+        let version = match header.version {
+            Some((major, minor)) => {
+                let s = format!("{}.{}", major, minor);
+                quote!(
+                    /// `@protocol_version` of the scheme
+                    pub const PROTOCOL_VERSION: &str = #s;
+                    pub const PROTOCOL_VERSION_MAJOR: u16 = #major;
+                    pub const PROTOCOL_VERSION_MINOR: u16 = #minor;
+                )
+            }
+            None => TokenStream::new(),
+        };
+        let visibility = match header.visibility {
+            Visibility::Public => "public",
+            Visibility::Private => "private",
+        };
+
+        quote!(
+            #version
+
+            /// `@visibility` of the scheme
+            pub const VISIBILITY: &str = #visibility;
+        )
+    }
 }
 
+/// Generation of `@errors` enum
+mod custom_err {
+    use super::*;
+
+    pub fn gen(errors: &[ErrorDesc]) -> TokenStream {
+        if errors.is_empty() {
+            return TokenStream::new();
+        }
+
+        let names: Vec<&String> = errors.iter().map(|e| &e.name).collect();
+        let variants: Vec<Ident> = errors.iter()
+            .map(|e| Ident::new(&camel_case_names(&e.name), Span::call_site()))
+            .collect();
+        let codes: Vec<u16> = errors.iter().map(|e| e.code).collect();
+
+        quote!(
+            /// Custom error codes, sent with `ERR_CUSTOM` answer
+            #[repr(u16)]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            pub enum CustomErr {
+                #( #variants = #codes, )*
+            }
+
+            impl CustomErr {
+                pub fn name(&self) -> &'static str {
+                    match self {
+                        #( CustomErr::#variants => #names, )*
+                    }
+                }
+
+                /// Error answer with code and message
+                pub fn msg(self, m: &str) -> AnswerErr<'_> {
+                    AnswerErr(AnswerCode::ERR_CUSTOM, ErrDetail::CodeMsg(self as u16, m))
+                }
+            }
+
+            impl core::convert::TryFrom<u16> for CustomErr {
+                type Error = ();
+                fn try_from(c: u16) -> Result<Self, Self::Error> {
+                    match c {
+                        #( #codes => Ok(CustomErr::#variants), )*
+                        _ => Err(()),
+                    }
+                }
+            }
+
+            impl core::fmt::Display for CustomErr {
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                    f.write_str(self.name())
+                }
+            }
+
+            impl From<CustomErr> for AnswerErr<'static> {
+                fn from(e: CustomErr) -> Self {
+                    AnswerErr(AnswerCode::ERR_CUSTOM, ErrDetail::Code(e as u16))
+                }
+            }
+        )
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct EnumCtor {
@@ -219,6 +327,25 @@ impl EnumCtor {
             post: vec![],
         }
     }
+
+    /// Nest constructor into section enum
+    fn enter(&mut self, section: &Section) {
+        // First lvl skip
+        // Enums are referred through `msg` module to not clash with ellocopo2 types
+        if self.pre.is_empty() {
+            self.pre.push("msg::".to_string() + &section.name);
+        } else {
+            self.pre.push("::".to_string() + &section.name.clone());
+            self.pre.push("(".to_string());
+            // Array element variant is prefixed with its index
+            if let Some(i) = section.index {
+                self.pre.push(i.to_string() + ", ");
+            }
+            self.pre.push("msg::".to_string() + &section.name);
+
+            self.post.push(")".to_string());
+        }
+    }
 }
 
 impl Into<String> for EnumCtor {
@@ -230,62 +357,51 @@ impl Into<String> for EnumCtor {
 mod dispatch {
     use super::*;
 
-    pub fn gen(dsl: &DslTree) -> TokenStream {
+    pub fn gen(dsl: &DslTree, lf: bool) -> TokenStream {
         let mut right_arms = Vec::new();
         let ctor_pre: EnumCtor = EnumCtor::new();
         
         dsl.visit_accum(&mut |dsl, mut ctor_pre: EnumCtor| {
             match dsl {
                 DslTree::SectionV(section) => {
-                    // First lvl skip
-                    if ctor_pre.pre.is_empty() {
-                        ctor_pre.pre.push(section.name.clone());
-                    } else {
-                        ctor_pre.pre.push("::".to_string() + &section.name.clone());
-                        ctor_pre.pre.push("(".to_string());
-                        ctor_pre.pre.push(section.name.clone());
-
-                        ctor_pre.post.push(")".to_string());
-                    }
+                    ctor_pre.enter(section);
                 }
                 DslTree::RegisterV(register) => {
                     let (w_act, r_act, rw) = match register.meta {
-                        MetaDesc{w: true, r: true, fast: false} => {
-                            ctor_pre.val = "::".to_string() + &register.name + ENUM_WRITE_POSTFIX + "(map_ty_error!(v))";
+                        MetaDesc{w: true, r: true, fast: false, ..} => {
+                            ctor_pre.val = "::".to_string() + &register.name + ENUM_WRITE_POSTFIX + "(" + &write_value(register) + ")";
                             let w_act: String = ctor_pre.clone().into();
                             ctor_pre.val = "::".to_string() + &register.name + ENUM_READ_POSTFIX;
                             let r_act: String = ctor_pre.clone().into();
                             (w_act + ",", r_act, "RW")
                         }
-                        MetaDesc{w: true, r: false, fast: false} => {
-                            ctor_pre.val = "::".to_string() + &register.name + ENUM_WRITE_POSTFIX + "(map_ty_error!(v))";
+                        MetaDesc{w: true, r: false, fast: false, ..} => {
+                            ctor_pre.val = "::".to_string() + &register.name + ENUM_WRITE_POSTFIX + "(" + &write_value(register) + ")";
                             let w_act: String = ctor_pre.clone().into();
                             (w_act, "".to_string(), "WO")
                         }
-                        MetaDesc{w: false, r: true, fast: false} => {
+                        MetaDesc{w: false, r: true, fast: false, ..} => {
                             ctor_pre.val = "::".to_string() + &register.name + ENUM_READ_POSTFIX;
                             let r_act: String = ctor_pre.clone().into();
                             ("".to_string(), r_act, "RO")
                         }
-                        MetaDesc{w: true, r: true, fast: true} => {
-                            let w_act = "cb_".to_string() + &register.path.join("_") + "_w((map_ty_error!(v)))";
-                            let r_act = "cb_".to_string() + &register.path.join("_") + "_r()";
-                            (w_act + ",", r_act, "RW")
-                        }
-                        MetaDesc{w: true, r: false, fast: true} => {
-                            let w_act = "cb_".to_string() + &register.path.join("_") + "_w((map_ty_error!(v)))";
-                            (w_act, "".to_string(), "WO")
-                        }
-                        MetaDesc{w: false, r: true, fast: true} => {
-                            let r_act = "cb_".to_string() + &register.path.join("_") + "_r()";
+                        // Writable @fast registers are rejected by validation
+                        MetaDesc{w: false, r: true, fast: true, ..} => {
+                            let r_act = format!("match h.{}_read() {{ Ok(v) => Value::{:?}(v), Err(e) => return DispatchResult::Err(e) }}",
+                                register.path.join("_"), register.ty);
                             ("".to_string(), r_act, "RO")
                         }
                         _ => panic!("Imposible reg meta combintation: {:?}", &register.meta),
                     };
 
                     let fast = if register.meta.fast { "FAST," } else { "" };
+                    let lvl = match rw {
+                        "RW" => format!("PrivLvl::{:?}, PrivLvl::{:?}", register.meta.w_plvl, register.meta.r_plvl),
+                        "WO" => format!("PrivLvl::{:?}", register.meta.w_plvl),
+                        _ => format!("PrivLvl::{:?}", register.meta.r_plvl),
+                    };
 
-                    let right_arm = format!("impl_arm!({} {}, code, sys_lvl, {}, {} {})", fast, rw, "PrivLvl::NORMAL_LVL", w_act, r_act);
+                    let right_arm = format!("impl_arm!({} {}, code, sys_lvl, {}, {} {})", fast, rw, lvl, w_act, r_act);
                     right_arms.push(parse_str::<Expr>(&right_arm).unwrap());
                 }
             }
@@ -293,25 +409,57 @@ mod dispatch {
             ctor_pre
         }, ctor_pre);
 
-        let mut left_arms = Vec::new();
+        let mut paths = Vec::new();
         dsl.visit_regs(&mut |reg| {
-            left_arms.push("/".to_string() + &reg.path.join("/"));
+            paths.push("/".to_string() + &reg.path.join("/"));
         });
+        let idx: Vec<usize> = (0..paths.len()).collect();
+        let PerfectHash{seed, tail, slots} = perfect_hash(&paths);
+        let slots_len = slots.len();
+        let mask = slots_len - 1;
+        let paths_len = paths.len();
 
         //for (l,r) in left_arms.iter().zip(right_arms.iter()) {
         //    println!("\"{}\" => {}", l, r);
         //}
 
+        let lf_token = if lf { quote!(<'a>) } else { quote!() };
+        let lf_static = if lf { quote!(<'static>) } else { quote!() };
+
         quote!(
-            pub fn req2msg<'a>(code: RequestCode, path: &str, v: Value<'a>, sys_lvl: PrivLvl) -> DispatchResult<'a> {
+            /// Full path of every register, scheme order
+            pub const PATHS: [&str; #paths_len] = [#( #paths ),*];
+
+            /// Index of register at `path` in `PATHS`, by perfect hash of full paths
+            pub fn path2idx(path: &str) -> Option<usize> {
+                // Register index + 1, 0 for empty slot
+                const SLOTS: [u16; #slots_len] = [#( #slots ),*];
+
+                let b = path.as_bytes();
+                let key = &b[b.len().saturating_sub(#tail)..];
+                let i = SLOTS[fnv1a(#seed ^ b.len() as u32, key) as usize & #mask] as usize;
+                if i != 0 && PATHS[i - 1] == path { Some(i - 1) } else { None }
+            }
+
+            #[allow(unused_variables)]
+            pub fn req2msg<'a, H: Handler>(h: &mut H, code: RequestCode, path: &str, v: Value<'a>, sys_lvl: PrivLvl) -> DispatchResult<msg::Msg#lf_token, &'static [msg::Msg#lf_static]> {
                 // this is sad
                 if code == RequestCode::READ {
                     let _: () = map_ty_error!(v);
                 }
 
-                match path {
+                if code == RequestCode::RESET {
+                    let _: () = map_ty_error!(v);
+                    return match reset2msgs(path) {
+                        Ok(_) if sys_lvl < reset_priv(path) => DispatchResult::Err(AnswerCode::ERR_PRIV),
+                        Ok(defaults) => DispatchResult::Reset(defaults),
+                        Err(e) => DispatchResult::Err(e),
+                    };
+                }
+
+                match path2idx(path) {
                     #(
-                        #left_arms => { #right_arms }
+                        Some(#idx) => { #right_arms }
                     )*
                     _ => {
                         DispatchResult::Err(AnswerCode::ERR_PATH)
@@ -320,10 +468,511 @@ mod dispatch {
             }
         )
     }
+
+    /// Request value converted to register type, then checked against its constraints
+    fn write_value(reg: &Register) -> String {
+        let x = Ident::new("x", Span::call_site());
+        match range_check(reg, &x, false) {
+            Some(ok) => format!("map_range_error!(map_ty_error!(v), |x: {}| {})", convert_ty(reg.ty), ok),
+            None => "map_ty_error!(v)".to_string(),
+        }
+    }
+
+    /// Paths are told apart by length and last `tail` bytes, full compare confirms the hit
+    pub(crate) struct PerfectHash {
+        pub(crate) seed: u32,
+        pub(crate) tail: usize,
+        pub(crate) slots: Vec<u16>,
+    }
+
+    /// Shortest tail and seed placing every path into its own slot,
+    /// table grows until one is found
+    pub(crate) fn perfect_hash(paths: &[String]) -> PerfectHash {
+        assert!(paths.len() < u16::MAX as usize, "Too many registers for u16 slots");
+        let max_tail = paths.iter().map(String::len).max().unwrap_or(0);
+        let mut len = (paths.len() * 2).next_power_of_two().max(2);
+        loop {
+            for tail in 1..=max_tail.max(1) {
+                for seed in 0..1000u32 {
+                    let mut slots = vec![0u16; len];
+                    let placed = paths.iter().enumerate().all(|(i, p)| {
+                        let b = p.as_bytes();
+                        let key = &b[b.len().saturating_sub(tail)..];
+                        let slot = &mut slots[ellocopo2::fnv1a(seed ^ b.len() as u32, key) as usize & (len - 1)];
+                        let free = *slot == 0;
+                        *slot = i as u16 + 1;
+                        free
+                    });
+                    if placed {
+                        return PerfectHash { seed, tail, slots };
+                    }
+                }
+            }
+            len *= 2;
+        }
+    }
+}
+
+/// Generation of `Handler` trait for `@fast` registers
+mod handler {
+    use super::*;
+
+    pub fn gen(dsl: &DslTree) -> TokenStream {
+        let (mut methods, mut refused) = (Vec::new(), Vec::new());
+        dsl.visit_regs(&mut |reg| {
+            if reg.meta.fast {
+                let read = Ident::new(&(reg.path.join("_") + "_read"), Span::call_site());
+                // Fast answers don't borrow from request or handler
+                let ty = match reg.ty {
+                    TypeTag::STR => quote!(&'static str),
+                    TypeTag::BYTES => quote!(&'static [u8]),
+                    ty => convert_ty(ty),
+                };
+                let docs = doc_attrs(&format!("`@fast` read of `/{}`", reg.path.join("/")), reg.doc.as_deref());
+                methods.push(quote!(
+                    #docs
+                    fn #read(&mut self) -> Result<#ty, AnswerCode>;
+                ));
+                refused.push(quote!(
+                    fn #read(&mut self) -> Result<#ty, AnswerCode> {
+                        Err(AnswerCode::ERR_ACCESS)
+                    }
+                ));
+            }
+        });
+
+        quote!(
+            /// `@fast` registers, answered right in `req2msg` instead of becoming `Msg`
+            ///
+            /// Every one of them has to be implemented, a register the device
+            /// can't serve answers `Err(AnswerCode::ERR_UNIMPL)` explicitly.
+            #[allow(non_snake_case)]
+            pub trait Handler {
+                #( #methods )*
+            }
+
+            /// Handler of write-only dispatch, refuses reads it's never asked for
+            #[allow(dead_code)]
+            struct WriteOnly;
+
+            #[allow(non_snake_case)]
+            impl Handler for WriteOnly {
+                #( #refused )*
+            }
+        )
+    }
+}
+
+/// Generation of `@default` table for RESET request
+mod reset {
+    use super::*;
+    use crate::parser::Literal;
+
+    #[derive(Default)]
+    struct Defaults {
+        writes: Vec<Expr>,
+        // (path, first default, end of defaults)
+        ranges: Vec<(String, usize, usize)>,
+        // (path, highest write level of registers reset)
+        privs: Vec<(String, Expr)>,
+    }
+
+    pub fn gen(dsl: &DslTree, lf: bool) -> TokenStream {
+        let mut defaults = Defaults::default();
+        visit_tree(dsl, EnumCtor::new(), &mut defaults);
+
+        let Defaults{writes, ranges, privs} = defaults;
+        let (priv_paths, priv_lvls): (Vec<_>, Vec<_>) = privs.into_iter().unzip();
+        let n = writes.len();
+
+        let (resettable, fixed): (Vec<_>, Vec<_>) = ranges.into_iter()
+            .partition(|(_, start, end)| start != end);
+        let reset_paths = resettable.iter().map(|(p, ..)| p);
+        let reset_ranges = resettable.iter().map(|(_, start, end)| quote!(#start..#end));
+        let fixed_paths = fixed.iter().map(|(p, ..)| p);
+
+        let lf_token = if lf { quote!(<'a>) } else { quote!() };
+        let lf_static = if lf { quote!(<'static>) } else { quote!() };
+
+        quote!(
+            static DEFAULTS: [msg::Msg#lf_static; #n] = [
+                #( #writes, )*
+            ];
+
+            /// Writes of `@default` values for register or whole section at `path`
+            ///
+            /// `ERR_ACCESS` if there are no defaults under `path`
+            pub fn reset2msgs(path: &str) -> Result<&'static [msg::Msg#lf_static], AnswerCode> {
+                match path {
+                    #(
+                        #reset_paths => Ok(&DEFAULTS[#reset_ranges]),
+                    )*
+                    #(
+                        #fixed_paths => Err(AnswerCode::ERR_ACCESS),
+                    )*
+                    _ => Err(AnswerCode::ERR_PATH),
+                }
+            }
+
+            /// Level required to RESET `path`: the highest write level among registers reset
+            pub fn reset_priv(path: &str) -> PrivLvl {
+                match path {
+                    #(
+                        #priv_paths => #priv_lvls,
+                    )*
+                    _ => PrivLvl::NORMAL_LVL,
+                }
+            }
+
+            impl#lf_token From<&'static msg::Msg#lf_static> for msg::Msg#lf_token {
+                fn from(m: &'static msg::Msg#lf_static) -> Self {
+                    *m
+                }
+            }
+        )
+    }
+
+    /// Returns the highest write level of registers with defaults under `dsl`
+    fn visit_tree(dsl: &DslTree, mut ctor_pre: EnumCtor, defaults: &mut Defaults) -> PrivLvl {
+        let start = defaults.writes.len();
+        let mut lvl = PrivLvl::NORMAL_LVL;
+        let path = match dsl {
+            DslTree::SectionV(section) => {
+                ctor_pre.enter(section);
+                for c in &section.children {
+                    lvl = lvl.max(visit_tree(c, ctor_pre.clone(), defaults));
+                }
+                &section.path
+            }
+            DslTree::RegisterV(register) => {
+                if let Some(lit) = &register.default {
+                    ctor_pre.val = "::".to_string() + &register.name + ENUM_WRITE_POSTFIX + "(" + &literal(lit) + ")";
+                    let ctor: String = ctor_pre.into();
+                    defaults.writes.push(parse_str::<Expr>(&ctor).unwrap());
+                    lvl = register.meta.w_plvl;
+                }
+                &register.path
+            }
+        };
+        let path = "/".to_string() + &path.join("/");
+        defaults.ranges.push((path.clone(), start, defaults.writes.len()));
+        if lvl != PrivLvl::NORMAL_LVL {
+            defaults.privs.push((path, parse_str::<Expr>(&format!("PrivLvl::{:?}", lvl)).unwrap()));
+        }
+        lvl
+    }
+
+    fn literal(lit: &Literal) -> String {
+        match lit {
+            Literal::Bool(b) => b.to_string(),
+            Literal::Int(i) => i.to_string(),
+            Literal::Str(s) => format!("{:?}", s),
+            Literal::Bytes(b) => format!("&{:?}", b),
+        }
+    }
+}
+/// Generation of `Staging` storage for `@txn` registers
+mod txn {
+    use super::*;
+
+    pub fn gen(dsl: &DslTree, lf: bool) -> TokenStream {
+        let mut fields = Vec::new();
+        let mut tys = Vec::new();
+        let mut writes = Vec::new();
+        let mut reads = Vec::new();
+
+        dsl.visit_accum(&mut |dsl, mut ctor_pre: EnumCtor| {
+            match dsl {
+                DslTree::SectionV(section) => ctor_pre.enter(section),
+                DslTree::RegisterV(register) if register.meta.txn => {
+                    fields.push(Ident::new(&register.path.join("_"), Span::call_site()));
+                    tys.push(convert_ty(register.ty));
+
+                    ctor_pre.val = "::".to_string() + &register.name + ENUM_WRITE_POSTFIX + "(v)";
+                    let w: String = ctor_pre.clone().into();
+                    writes.push(w.parse::<TokenStream>().unwrap());
+
+                    ctor_pre.val = "::".to_string() + &register.name + ENUM_READ_POSTFIX;
+                    let r: String = ctor_pre.clone().into();
+                    reads.push(parse_str::<Expr>(&r).unwrap());
+                }
+                _ => (),
+            }
+            ctor_pre
+        }, EnumCtor::new());
+
+        if fields.is_empty() {
+            return TokenStream::new();
+        }
+
+        let len = fields.len();
+        let idx: Vec<usize> = (0..len).collect();
+        let lf_token = if lf { quote!(<'a>) } else { quote!() };
+
+        quote!(
+            /// Staged writes of `@txn` registers
+            #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+            pub struct Staging {
+                #( pub #fields: Option<#tys>, )*
+            }
+
+            impl#lf_token Stage<msg::Msg#lf_token> for Staging {
+                const LEN: usize = #len;
+
+                fn stage(&mut self, m: msg::Msg#lf_token) -> Result<(), msg::Msg#lf_token> {
+                    match m {
+                        #( #writes => self.#fields = Some(v), )*
+                        m => return Err(m),
+                    }
+                    Ok(())
+                }
+
+                fn write(&self, i: usize) -> Option<msg::Msg#lf_token> {
+                    match i {
+                        #( #idx => self.#fields.map(|v| #writes), )*
+                        _ => None,
+                    }
+                }
+
+                fn read(i: usize) -> msg::Msg#lf_token {
+                    match i {
+                        #( #idx => #reads, )*
+                        _ => panic!("No @txn register #{}", i),
+                    }
+                }
+
+                fn set(&mut self, i: usize, v: Value<'_>) -> Result<(), AnswerCode> {
+                    match i {
+                        #( #idx => self.#fields = Some(core::convert::TryInto::try_into(v)?), )*
+                        _ => return Err(AnswerCode::ERR_PATH),
+                    }
+                    Ok(())
+                }
+            }
+        )
+    }
 }
 
+/// Generation of storage image save and load for `@persist` registers
+mod persist {
+    use super::*;
 
-fn camel_case_names(name: &str) -> String {
+    pub fn gen(dsl: &DslTree, version: Option<(u16, u16)>, lf: bool) -> TokenStream {
+        let mut paths = Vec::new();
+        let mut reads = Vec::new();
+        let mut image_sz = ellocopo2::persist::IMAGE_HEADER_SZ;
+
+        dsl.visit_accum(&mut |dsl, mut ctor_pre: EnumCtor| {
+            match dsl {
+                DslTree::SectionV(section) => ctor_pre.enter(section),
+                DslTree::RegisterV(register) if register.meta.persist => {
+                    let path = "/".to_string() + &register.path.join("/");
+                    image_sz += ellocopo2::HEADER_SZ + path.len() + payload_sz(register);
+                    paths.push(path);
+
+                    ctor_pre.val = "::".to_string() + &register.name + ENUM_READ_POSTFIX;
+                    let r: String = ctor_pre.clone().into();
+                    reads.push(parse_str::<Expr>(&r).unwrap());
+                }
+                _ => (),
+            }
+            ctor_pre
+        }, EnumCtor::new());
+
+        if paths.is_empty() {
+            return TokenStream::new();
+        }
+
+        let len = paths.len();
+        let (major, minor) = version.unwrap_or((0, 0));
+        let lf_token = if lf { quote!(<'a>) } else { quote!() };
+        let lf_static = if lf { quote!(<'static>) } else { quote!() };
+
+        quote!(
+            /// Paths of `@persist` registers, image order
+            pub const PERSIST_PATHS: [&str; #len] = [#( #paths ),*];
+            /// Size of the largest image `persist_save` can produce
+            pub const PERSIST_IMAGE_MAX_SZ: usize = #image_sz;
+
+            /// Image of `@persist` registers in `buf`, `read` answers their read messages
+            ///
+            /// Returns image size, ready for `Storage::store`.
+            pub fn persist_save<'v, F>(buf: &mut [u8], mut read: F) -> Result<usize, PersistError>
+            where
+                F: FnMut(msg::Msg#lf_static) -> Result<Value<'v>, AnswerErr<'v>>,
+            {
+                const READS: [msg::Msg#lf_static; #len] = [#( #reads ),*];
+
+                let mut image = persist::ImageWriter::new(buf, (#major, #minor))?;
+                for (path, m) in PERSIST_PATHS.iter().zip(READS.iter()) {
+                    let v = read(*m).map_err(|e| PersistError::Read(e.0))?;
+                    image.push(path, v)?;
+                }
+                Ok(image.finish())
+            }
+
+            /// Hands stored values of `image` to `write` as write messages
+            ///
+            /// Entries of registers gone, not persisted anymore, retyped or out of range
+            /// are skipped, registers missing from image keep their values.
+            /// Returns number of values `write` accepted.
+            pub fn persist_load<'a, 'r, F>(image: &'a [u8], mut write: F) -> Result<usize, PersistError>
+            where
+                F: FnMut(msg::Msg#lf_token) -> Result<Value<'r>, AnswerErr<'r>>,
+            {
+                let mut written = 0;
+                for entry in persist::ImageReader::new(image)? {
+                    let (path, v) = entry?;
+                    if !PERSIST_PATHS.contains(&path) {
+                        continue;
+                    }
+                    if let DispatchResult::Msg(m) = req2msg(&mut WriteOnly, RequestCode::WRITE, path, v, PrivLvl::DEVEL_LVL) {
+                        if write(m).is_ok() {
+                            written += 1;
+                        }
+                    }
+                }
+                Ok(written)
+            }
+        )
+    }
+
+    /// Largest payload register value can take
+    fn payload_sz(reg: &Register) -> usize {
+        match reg.ty {
+            TypeTag::UNIT => 0,
+            TypeTag::BOOL | TypeTag::I8 | TypeTag::U8 => 1,
+            TypeTag::I16 | TypeTag::U16 => 2,
+            TypeTag::I32 | TypeTag::U32 => 4,
+            TypeTag::STR | TypeTag::BYTES => reg.constraints.maxlen.unwrap_or(ellocopo2::MAX_PAYLOAD_SZ),
+        }
+    }
+}
+
+/// Generation of typed host side `RegClient`
+mod client {
+    use super::*;
+
+    pub fn gen(dsl: &DslTree) -> TokenStream {
+        let mut methods = Vec::new();
+
+        dsl.visit_regs(&mut |reg| {
+            let path = "/".to_string() + &reg.path.join("/");
+            let name = reg.path.join("_");
+            let tag = convert_typetag(reg.ty);
+            let ty = convert_ty(reg.ty);
+            // Read str and bytes borrow from the answer buffer
+            let (lf, recv) = if matches!(reg.ty, TypeTag::STR | TypeTag::BYTES) {
+                (quote!(<'a>), quote!(&'a mut self))
+            } else {
+                (quote!(), quote!(&mut self))
+            };
+
+            if reg.meta.r {
+                let read = Ident::new(&(name.clone() + "_read"), Span::call_site());
+                let docs = doc_attrs(&format!("Read `{}`", path), reg.doc.as_deref());
+                methods.push(quote!(
+                    #docs
+                    pub fn #read#lf(#recv) -> Result<#ty, ClientError<T::Error>> {
+                        self.0.read_as(#path)
+                    }
+                ));
+            }
+            if reg.meta.w {
+                let write = Ident::new(&(name + "_write"), Span::call_site());
+                let docs = doc_attrs(&format!("Write `{}`", path), reg.doc.as_deref());
+                methods.push(if reg.ty == TypeTag::UNIT {
+                    quote!(
+                        #docs
+                        pub fn #write(&mut self) -> Result<(), ClientError<T::Error>> {
+                            self.0.write(#path, Value::UNIT(()))
+                        }
+                    )
+                } else {
+                    let check = range_check(reg, &Ident::new("v", Span::call_site()), true).map(|bad| quote!(
+                        if #bad {
+                            return Err(ClientError::Range);
+                        }
+                    ));
+                    quote!(
+                        #docs
+                        pub fn #write#lf(&mut self, v: #ty) -> Result<(), ClientError<T::Error>> {
+                            #check
+                            self.0.write(#path, Value::#tag(v))
+                        }
+                    )
+                });
+            }
+        });
+
+        quote!(
+            /// Typed register access over `Transport`
+            pub struct RegClient<T>(pub client::Client<T>);
+
+            #[allow(non_snake_case)]
+            impl<T: Transport> RegClient<T> {
+                pub fn new(transport: T) -> Self {
+                    Self(client::Client::new(transport))
+                }
+
+                #( #methods )*
+            }
+        )
+    }
+}
+
+pub(crate) use dispatch::{perfect_hash, PerfectHash};
+
+/// `///` lines: `head`, then scheme `@doc`/`@com` as its own paragraph
+fn doc_attrs(head: &str, doc: Option<&str>) -> TokenStream {
+    let mut lines = vec![format!(" {}", head)];
+    if let Some(doc) = doc {
+        lines.push(String::new());
+        lines.extend(doc.lines().map(|l| format!(" {}", l)));
+    }
+    quote!( #( #[doc = #lines] )* )
+}
+
+/// Check of written value `v` against register constraints, `None` if there are none
+///
+/// `reject` flips it to hold for values breaking the constraints.
+fn range_check(reg: &Register, v: &Ident, reject: bool) -> Option<TokenStream> {
+    let Constraints{min, max, maxlen, oneof} = &reg.constraints;
+    let (ty_min, ty_max) = int_range(reg.ty).unwrap_or((i64::MIN, i64::MAX));
+    let int = |i: i64| proc_macro2::Literal::i64_unsuffixed(i);
+    let not = if reject { quote!(!) } else { quote!() };
+    let mut checks = Vec::new();
+    // Bounds of the type itself are left out, they always hold
+    match (min.filter(|m| *m > ty_min).map(int), max.filter(|m| *m < ty_max).map(int)) {
+        (Some(min), Some(max)) => checks.push(quote!(#not(#min..=#max).contains(&#v))),
+        (Some(min), None) if reject => checks.push(quote!(#v < #min)),
+        (Some(min), None) => checks.push(quote!(#v >= #min)),
+        (None, Some(max)) if reject => checks.push(quote!(#v > #max)),
+        (None, Some(max)) => checks.push(quote!(#v <= #max)),
+        (None, None) => (),
+    }
+    match maxlen {
+        Some(n) if reject => checks.push(quote!(#v.len() > #n)),
+        Some(n) => checks.push(quote!(#v.len() <= #n)),
+        None => (),
+    }
+    if !oneof.is_empty() {
+        let pats = oneof.iter().map(|lit| match lit {
+            Literal::Bool(b) => quote!(#b),
+            Literal::Int(i) => { let i = int(*i); quote!(#i) }
+            Literal::Str(s) => { let s = proc_macro2::Literal::string(s); quote!(#s) }
+            Literal::Bytes(b) => { let b = proc_macro2::Literal::byte_string(b); quote!(#b) }
+        });
+        checks.push(quote!(#not matches!(#v, #( #pats )|*)));
+    }
+    match (checks.is_empty(), reject) {
+        (true, _) => None,
+        (false, true) => Some(quote!( #( #checks )||* )),
+        (false, false) => Some(quote!( #( #checks )&&* )),
+    }
+}
+
+pub(crate) fn camel_case_names(name: &str) -> String {
     let empty = String::with_capacity(name.len());
     name.split(|c| c == REGISTER_PATH_DELIMETR.chars().next().unwrap() || c == '_')
         .fold(empty, |acc, s| {
@@ -480,12 +1129,14 @@ fn convert_ty(ty: TypeTag) -> TokenStream {
     match ty {
          UNIT  => quote!(()),
          BOOL  => quote!(bool),
-         U8    => quote!(u8),
+         I8    => quote!(i8),
+         I16   => quote!(i16),
          I32   => quote!(i32),
+         U8    => quote!(u8),
+         U16   => quote!(u16),
          U32   => quote!(u32),
          STR   => quote!(&'a str),
          BYTES => quote!(&'a [u8]),
-        _      => panic!("Gen: unsupproted type: {:?}", ty),
     }
 }
 
@@ -494,11 +1145,13 @@ fn convert_typetag(ty: TypeTag) -> TokenStream {
     match ty {
          UNIT  => quote!(UNIT),
          BOOL  => quote!(BOOL),
-         U8    => quote!(U8),
+         I8    => quote!(I8),
+         I16   => quote!(I16),
          I32   => quote!(I32),
+         U8    => quote!(U8),
+         U16   => quote!(U16),
          U32   => quote!(U32),
          STR   => quote!(STR),
          BYTES => quote!(BYTES),
-        _      => panic!("Gen: unsupproted type: {:?}", ty),
     }
 }
@@ -1,11 +1,150 @@
 #![allow(dead_code)]
 
+use std::path::Path;
+
+pub mod diag;
 pub mod parser;
 mod gen;
+mod c;
+mod py;
+mod docs;
+pub mod compat;
+pub mod text;
+mod expand;
+mod validate;
+
+pub use diag::{Diagnostic, Severity};
+pub use docs::DocFormat;
+pub use parser::Format;
+
+/// Generated code along with warnings found in the scheme
+pub struct Generated {
+    pub code: String,
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// C header and source, source includes header as `header_name`
+pub struct CGenerated {
+    pub header: String,
+    pub source: String,
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// Parsed and validated scheme `src`, or every problem found in it
+fn schema(src: &str, format: Format) -> Result<parser::Schema, Vec<Diagnostic>> {
+    schema_in(src, format, Path::new(""))
+}
+
+/// Same as `schema` with `@include` paths relative to `base`
+fn schema_in(src: &str, format: Format, base: &Path) -> Result<parser::Schema, Vec<Diagnostic>> {
+    let schema = parser::parse_in(src, format, base).map_err(|e| vec![e])?;
+    let errors = validate::validate(&schema);
+    if !errors.is_empty() {
+        return Err(errors.into_iter().map(|e| format.locate(e, src)).collect());
+    }
+    Ok(schema)
+}
+
+/// Code for JSON scheme `dsl`, or every problem found in it
+pub fn generate(dsl: &str) -> Result<Generated, Vec<Diagnostic>> {
+    generate_format(dsl, Format::Json)
+}
 
-pub fn generate(dsl: &str) -> String {
-    let l = parser::parser(dsl);
-    gen::gen(l.unwrap())
+/// Code for scheme `src` written in `format`
+pub fn generate_format(src: &str, format: Format) -> Result<Generated, Vec<Diagnostic>> {
+    let mut schema = schema(src, format)?;
+    let warnings = std::mem::take(&mut schema.warnings);
+    Ok(Generated {
+        code: gen::gen(schema),
+        warnings,
+    })
+}
+
+/// Code for scheme file `path`, format by extension or JSON, `@include`
+/// paths are relative to it
+pub fn generate_file(path: &Path) -> Result<Generated, Vec<Diagnostic>> {
+    let (src, format) = read_file(path)?;
+    let mut schema = schema_in(&src, format, base(path))?;
+    let warnings = std::mem::take(&mut schema.warnings);
+    Ok(Generated {
+        code: gen::gen(schema),
+        warnings,
+    })
+}
+
+/// C register table and dispatch skeleton for JSON scheme `dsl`
+pub fn generate_c(dsl: &str, header_name: &str) -> Result<CGenerated, Vec<Diagnostic>> {
+    c_in(dsl, Format::Json, Path::new(""), header_name)
+}
+
+/// Same as `generate_c` for scheme file `path`, read like `generate_file`
+pub fn generate_c_file(path: &Path, header_name: &str) -> Result<CGenerated, Vec<Diagnostic>> {
+    let (src, format) = read_file(path)?;
+    c_in(&src, format, base(path), header_name)
+}
+
+fn c_in(src: &str, format: Format, base: &Path, header_name: &str) -> Result<CGenerated, Vec<Diagnostic>> {
+    let mut schema = schema_in(src, format, base)?;
+    let warnings = std::mem::take(&mut schema.warnings);
+    let (header, source) = c::gen(&schema, header_name)
+        .map_err(|errs| errs.into_iter().map(|e| format.locate(e, src)).collect::<Vec<_>>())?;
+    Ok(CGenerated {
+        header,
+        source,
+        warnings,
+    })
+}
+
+/// Python client module for JSON scheme `dsl`
+pub fn generate_py(dsl: &str) -> Result<Generated, Vec<Diagnostic>> {
+    py_in(dsl, Format::Json, Path::new(""))
+}
+
+/// Same as `generate_py` for scheme file `path`, read like `generate_file`
+pub fn generate_py_file(path: &Path) -> Result<Generated, Vec<Diagnostic>> {
+    let (src, format) = read_file(path)?;
+    py_in(&src, format, base(path))
+}
+
+fn py_in(src: &str, format: Format, base: &Path) -> Result<Generated, Vec<Diagnostic>> {
+    let mut schema = schema_in(src, format, base)?;
+    let warnings = std::mem::take(&mut schema.warnings);
+    Ok(Generated {
+        code: py::gen(&schema),
+        warnings,
+    })
+}
+
+/// Register map of JSON scheme `dsl` in Markdown or HTML
+pub fn generate_docs(dsl: &str, format: DocFormat) -> Result<Generated, Vec<Diagnostic>> {
+    docs_in(dsl, Format::Json, Path::new(""), format)
+}
+
+/// Same as `generate_docs` for scheme file `path`, read like `generate_file`
+pub fn generate_docs_file(path: &Path, format: DocFormat) -> Result<Generated, Vec<Diagnostic>> {
+    let (src, scheme_format) = read_file(path)?;
+    docs_in(&src, scheme_format, base(path), format)
+}
+
+fn docs_in(src: &str, scheme_format: Format, base: &Path, format: DocFormat) -> Result<Generated, Vec<Diagnostic>> {
+    let mut schema = schema_in(src, scheme_format, base)?;
+    let warnings = std::mem::take(&mut schema.warnings);
+    Ok(Generated {
+        code: docs::gen(&schema, format),
+        warnings,
+    })
+}
+
+/// Scheme file `path` and its format by extension, JSON when unknown
+fn read_file(path: &Path) -> Result<(String, Format), Vec<Diagnostic>> {
+    let src = std::fs::read_to_string(path)
+        .map_err(|e| vec![Diagnostic::error("", e.to_string())])?;
+    Ok((src, Format::from_path(path).unwrap_or(Format::Json)))
+}
+
+/// Directory `@include` paths of scheme file `path` are relative to
+fn base(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new(""))
 }
 
 #[cfg(test)]
@@ -16,13 +155,472 @@ mod tests {
     use std::fs::read_to_string;
     use std::fs::File;
 
+    /// Scratch directory removed even when the test fails
+    pub(crate) struct TmpDir(std::path::PathBuf);
+
+    impl TmpDir {
+        pub(crate) fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("ellocopo2-{}-{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            TmpDir(dir)
+        }
+    }
+
+    impl std::ops::Deref for TmpDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TmpDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
     #[test]
     fn it_works() {
 
         let txt = read_to_string("../scheme.json").unwrap();
-        let txt = generate(&txt);
-        
+        let gen = generate(&txt).unwrap();
+        assert_eq!(gen.warnings, vec![]);
+        let txt = gen.code;
+        // `@fast` reads have to be implemented, nothing answers for the device
+        assert!(txt.contains("fn build_git_read (& mut self) -> Result < & 'static str , AnswerCode > ;"), "{}", txt);
+        assert!(!txt.contains("impl Handler for ()"), "{}", txt);
+
         let mut file = File::create("codegen.rs").unwrap();
         file.write_all(txt.as_bytes()).unwrap();
     }
+
+    #[test]
+    fn root_meta() {
+        use parser::Visibility;
+
+        let schema = parser::parser(r#"{
+            "@protocol_version": "2.13",
+            "@visibility": "private",
+            "@control": ["a/x", "b"],
+            "a": { "x": "u8", "y": "u8" },
+            "b": { "z": { "@type": "u8", "@access": "RO" }, "w": "u8" }
+        }"#).unwrap();
+        assert_eq!(schema.header.version, Some((2, 13)));
+        assert_eq!(schema.header.visibility, Visibility::Private);
+
+        let mut rw = Vec::new();
+        schema.tree.visit_regs(&mut |r| if r.meta.w { rw.push(r.path.join("/")) });
+        assert_eq!(rw, vec!["a/x", "b/w"]);
+
+        assert!(parser::parser(r#"{ "@protocol_version": "2", "a": "u8" }"#).is_err());
+        assert!(parser::parser(r#"{ "@visibility": "secret", "a": "u8" }"#).is_err());
+        assert!(parser::parser(r#"{ "@control": ["a, c"], "a": "u8" }"#).is_err());
+    }
+
+    #[test]
+    fn diagnostics() {
+        let e = parser::parser("{\n  \"a\": \"u8\",\n  \"b\" \"u8\"\n}").unwrap_err();
+        assert_eq!((e.severity, e.path.as_str(), e.pos), (Severity::Error, "", Some((3, 7))));
+
+        let scheme = r#"{
+  "io": {
+    "file": {
+      "max": { "@type": "u32", "@access": "RX" }
+    }
+  }
+}"#;
+        let e = parser::parser(scheme).unwrap_err();
+        assert_eq!((e.path.as_str(), e.pos), ("/io/file/max/@access", Some((4, 32))));
+        assert_eq!(e.to_string(), "error: /io/file/max/@access (4:32): Unsupported access: RX");
+
+        for (scheme, path) in [
+            (r#"{ "a": { "b": "f32" } }"#, "/a/b"),
+            (r#"{ "a": { "@type": 8 } }"#, "/a/@type"),
+            (r#"{ "a": { "@type": "u8", "@priv": "ROOT" } }"#, "/a/@priv"),
+            (r#"{ "a": { "@type": "u8", "@fast": "yes" } }"#, "/a/@fast"),
+            (r#"{ "a": { "@type": "u8", "@access": "RW", "@default": 256 } }"#, "/a/@default"),
+            (r#"{ "@errors": { "x": 1, "y": 1 }, "a": "u8" }"#, "/@errors/y"),
+            (r#"{ "a": 1 }"#, "/a"),
+            (r#"{ "a": { "@type": "u8", "@persist": true } }"#, "/a"),
+            (r#"{ "a": { "@type": "u8", "@access": "RW", "@persist": 1 } }"#, "/a/@persist"),
+        ] {
+            assert_eq!(parser::parser(scheme).unwrap_err().path, path, "{}", scheme);
+        }
+
+        // Unknown and misplaced annotations only warn
+        let schema = parser::parser(r#"{
+  "@com1": "comment",
+  "@colour": "red",
+  "a": { "@type": "u8", "@unit": "mV", "@com": "volts" },
+  "b": { "@errors": {}, "c": "u8" }
+}"#).unwrap();
+        let warnings: Vec<_> = schema.warnings.iter().map(|w| (w.severity, w.path.as_str(), w.pos)).collect();
+        assert_eq!(warnings, vec![
+            (Severity::Warning, "/@colour", Some((3, 3))),
+            (Severity::Warning, "/a/@unit", Some((4, 25))),
+            (Severity::Warning, "/b/@errors", Some((5, 10))),
+        ]);
+    }
+
+    #[test]
+    fn validation() {
+        let long = "x".repeat(ellocopo2::MAX_PATH_SZ);
+        let scheme = format!(r#"{{
+  "@errors": {{ "bad_crc": 1, "badCrc": 2 }},
+  "io": {{ "last_code": "u8", "lastCode": "u8", "2nd": "u8" }},
+  "sys": {{ "io": {{ "x": "u8" }}, "{}": "u8" }},
+  "build": {{ "@fast": true, "@access": "RW", "a": "str", "b": "str" }},
+  "rt": {{ "@fast": true, "t": "u32", "reset": "()" }},
+  "t": {{ "@access": "RW", "a_b": {{ "@type": "u8", "@txn": true }} }},
+  "t_a": {{ "@access": "RW", "b": {{ "@type": "u8", "@txn": true }} }},
+  "x-y": {{ "z": "u8" }}
+}}"#, long);
+
+        let errs = generate(&scheme).err().unwrap();
+        let errs: Vec<_> = errs.iter().map(|e| (e.path.as_str(), e.pos.map(|p| p.0))).collect();
+        let long_path = format!("/sys/{}", long);
+        assert_eq!(errs, vec![
+            ("/build", Some(5)),
+            ("/io/last_code", Some(3)),
+            ("/io/2nd", Some(3)),
+            ("/rt/reset", Some(6)),
+            ("/sys/io", Some(4)),
+            (long_path.as_str(), Some(4)),
+            ("/x-y", Some(9)),
+            ("/t_a/b", Some(8)),
+            ("/x-y/z", Some(9)),
+            ("/@errors/bad_crc", Some(2)),
+        ]);
+    }
+
+    #[test]
+    fn register_map() {
+        let scheme = r#"{
+  "@protocol_version": "1.2",
+  "@doc": "Demo device",
+  "@errors": { "busy": 1 },
+  "calib": {
+    "@access": "RW",
+    "@wpriv": "SECUR",
+    "@doc": "Calibration <k, b>",
+    "k": { "@type": "i32", "@default": -1, "@doc": "Gain", "@com": "a | b", "@min": -100, "@max": 100 },
+    "mode": { "@type": "str", "@oneof": ["auto", "hand"] },
+    "lvl": { "@type": "i8", "@min": -5, "@doc": "Level <dB>, `<= 0` is quiet" }
+  },
+  "build": { "@fast": true, "name": "str" }
+}"#;
+        let md = generate_docs(scheme, DocFormat::Markdown).unwrap().code;
+        assert!(md.contains("Protocol version 1.2\n\nDemo device\n"), "{}", md);
+        assert!(md.contains("## `/calib`\n\nCalibration &lt;k, b&gt;\n"), "{}", md);
+        assert!(md.contains("| `/calib/lvl` | `i8` | RW | R NORMAL, W SECUR |  | `>= -5` | Level &lt;dB&gt;, `<= 0` is quiet |"), "{}", md);
+        assert!(md.contains("| `/calib/k` | `i32` | RW | R NORMAL, W SECUR | `-1` | `-100..=100` | Gain<br>a \\| b |"), "{}", md);
+        assert!(md.contains("| `/build/name` | `str` | RO, fast | NORMAL |  |  |  |"), "{}", md);
+        assert!(md.contains("| `/calib/mode` | `str` | RW | R NORMAL, W SECUR |  | one of `\"auto\"`, `\"hand\"` |  |"), "{}", md);
+        assert!(md.contains("| `busy` | 1 |"), "{}", md);
+
+        let html = generate_docs(scheme, DocFormat::Html).unwrap().code;
+        assert!(html.contains("<h2><code>/calib</code></h2>\n<p>Calibration &lt;k, b&gt;</p>"), "{}", html);
+        assert!(html.contains("<td><code>-1</code></td><td><code>-100..=100</code></td><td>Gain<br>a | b</td>"), "{}", html);
+
+        // Same text lands on generated items
+        let code = generate(scheme).unwrap().code;
+        assert!(code.contains("# [doc = \" Write `/calib/k`\"] # [doc = \"\"] # [doc = \" Gain\"] # [doc = \" a | b\"]"), "{}", code);
+
+        let e = parser::parser(r#"{ "a": { "@type": "u8", "@doc": 1 } }"#).unwrap_err();
+        assert_eq!(e.path, "/a/@doc");
+    }
+
+    #[test]
+    fn constraints() {
+        for (scheme, path) in [
+            (r#"{ "a": { "@type": "u8", "@min": 1 } }"#, "/a/@min"),
+            (r#"{ "a": { "@type": "str", "@access": "RW", "@min": 1 } }"#, "/a/@min"),
+            (r#"{ "a": { "@type": "u8", "@access": "RW", "@min": 5, "@max": 4 } }"#, "/a/@max"),
+            (r#"{ "a": { "@type": "u8", "@access": "RW", "@max": 256 } }"#, "/a/@max"),
+            (r#"{ "a": { "@type": "u8", "@access": "RW", "@maxlen": 4 } }"#, "/a/@maxlen"),
+            (r#"{ "a": { "@type": "str", "@access": "RW", "@maxlen": 1000 } }"#, "/a/@maxlen"),
+            (r#"{ "a": { "@type": "str", "@access": "RW", "@oneof": [] } }"#, "/a/@oneof"),
+            (r#"{ "a": { "@type": "str", "@access": "RW", "@oneof": ["x", 1] } }"#, "/a/@oneof"),
+            (r#"{ "a": { "@type": "u8", "@access": "RW", "@max": 9, "@default": 10 } }"#, "/a/@default"),
+        ] {
+            assert_eq!(parser::parser(scheme).unwrap_err().path, path, "{}", scheme);
+        }
+
+        let code = generate(r#"{
+  "a": {
+    "@access": "RW",
+    "lvl": { "@type": "i8", "@min": -128, "@max": 10 },
+    "mode": { "@type": "[u8]", "@oneof": [[1], [2, 3]] }
+  }
+}"#).unwrap().code;
+        // Type bounds are not checked
+        assert!(code.contains("| x : i8 | x <= 10"), "{}", code);
+        assert!(code.contains("if v > 10 { return Err (ClientError :: Range) ; }"), "{}", code);
+        assert!(code.contains("! matches ! (v , b\"\\x01\" | b\"\\x02\\x03\")"), "{}", code);
+    }
+
+    #[test]
+    fn formats() {
+        let json = r#"{
+  "@protocol_version": "1.2",
+  "@errors": { "busy": 1 },
+  "@doc": "Demo device",
+  "ctrl": {
+    "record": "bool",
+    "erase": "()",
+    "event": { "@type": "[u8]", "@access": "WO", "@doc": "Raw event\nframe" }
+  },
+  "calib": {
+    "@access": "RW",
+    "@wpriv": "SECUR",
+    "k": { "@type": "i32", "@default": -1, "@min": -100, "@max": 100 },
+    "mode": { "@type": "str", "@oneof": ["auto", "hand"] },
+    "key": { "@type": "[u8]", "@default": [1, 2, 3] }
+  }
+}"#;
+        let toml = r#"
+"@protocol_version" = "1.2"
+"@doc" = "Demo device"
+"@errors" = { busy = 1 }
+
+# Real comments
+[ctrl]
+record = "bool"
+erase = "()"
+event = { "@type" = "[u8]", "@access" = "WO", "@doc" = "Raw event\nframe" }
+
+[calib]
+"@access" = "RW"
+"@wpriv" = "SECUR"
+k = { "@type" = "i32", "@default" = -1, "@min" = -100, "@max" = 100 }
+mode = { "@type" = "str", "@oneof" = ["auto", "hand"] }
+key = { "@type" = "[u8]", "@default" = [1, 2, 3] }
+"#;
+        let yaml = r#"
+"@protocol_version": "1.2"
+"@doc": Demo device
+"@errors": { busy: 1 }
+ctrl:
+  record: bool
+  erase: ()
+  # Real comments
+  event: { "@type": "[u8]", "@access": WO, "@doc": "Raw event\nframe" }
+calib:
+  "@access": RW
+  "@wpriv": SECUR
+  k: { "@type": i32, "@default": -1, "@min": -100, "@max": 100 }
+  mode: { "@type": str, "@oneof": [auto, hand] }
+  key: { "@type": "[u8]", "@default": [1, 2, 3] }
+"#;
+        let text = r#"//! Demo device
+@protocol_version "1.2"
+@errors { busy: 1 }
+
+ctrl {
+    record: bool
+    erase: ()   // Real comments
+    /// Raw event
+    /// frame
+    event: [u8] @access WO
+}
+
+calib @access RW @wpriv SECUR {
+    k: i32 @default -1 @min -100 @max 100
+    mode: str @oneof ["auto", "hand"]
+    "key": [u8] @default [1, 2, 3]
+}
+"#;
+        let code = generate(json).unwrap().code;
+        let formats = [(toml, Format::Toml, cfg!(feature = "toml")), (yaml, Format::Yaml, cfg!(feature = "yaml")), (text, Format::Text, true)];
+        for &(src, format, _) in formats.iter().filter(|f| f.2) {
+            assert_eq!(generate_format(src, format).unwrap().code, code, "{:?}", format);
+        }
+        assert_eq!(Format::from_path(std::path::Path::new("regs.yml")), Some(Format::Yaml));
+
+        // Text problems point into the text
+        let e = generate_format("ctrl {\n    record: bool @access RX\n}", Format::Text).err().unwrap();
+        assert_eq!((e[0].path.as_str(), e[0].pos), ("/ctrl/record/@access", Some((2, 18))));
+        let e = generate_format("@errors { busy: 1, idle: 1 }\nctrl { record: bool }", Format::Text).err().unwrap();
+        assert_eq!((e[0].path.as_str(), e[0].pos), ("/@errors/idle", Some((1, 20))));
+        let e = parser::parse("ctrl {\n    record: bool\n    record: u8\n}", Format::Text).unwrap_err();
+        assert_eq!((e.pos, e.msg.as_str()), (Some((3, 5)), "Duplicate key /ctrl/record"));
+        let e = parser::parse("ctrl {\n    record bool\n}", Format::Text).unwrap_err();
+        assert_eq!(e.pos, Some((2, 12)));
+        let e = parser::parse("ctrl = 1", Format::Toml).unwrap_err();
+        if cfg!(feature = "toml") {
+            assert_eq!(e.path, "/ctrl");
+        } else {
+            assert_eq!(e.msg, "TOML schemes need `toml` feature");
+        }
+    }
+
+    #[test]
+    #[cfg_attr(not(feature = "toml"), ignore = "includes TOML file")]
+    fn reuse() {
+        let dir = TmpDir::new("reuse");
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        std::fs::write(dir.join("parts/common.toml"), r#"
+["@template".cal]
+k = { "@type" = "i32", "@default" = 1 }
+b = "i32"
+
+["@template"]
+gain = "u8"
+"#).unwrap();
+        std::fs::write(dir.join("parts/io.ello"), "io { status: u32 }\n").unwrap();
+        std::fs::write(dir.join("regs.json"), r#"{
+  "@include": ["parts/common.toml", "parts/io.ello"],
+  "calib": {
+    "@access": "RW",
+    "ecg": { "@use": "cal" },
+    "acc": { "@use": "cal", "k": { "@default": 16 } }
+  },
+  "adc": {
+    "@access": "RW",
+    "channel[3]": { "gain": { "@use": "gain", "@max": 8 }, "raw": { "@type": "i32", "@access": "RO" } }
+  }
+}"#).unwrap();
+
+        let schema = parser::parse_file(&dir.join("regs.json")).unwrap();
+        let mut regs = Vec::new();
+        schema.tree.visit_regs(&mut |r| regs.push(format!("/{} {:?} {:?}", r.path.join("/"), r.ty, r.default)));
+        assert_eq!(regs, vec![
+            "/adc/channel/0/gain U8 None", "/adc/channel/0/raw I32 None",
+            "/adc/channel/1/gain U8 None", "/adc/channel/1/raw I32 None",
+            "/adc/channel/2/gain U8 None", "/adc/channel/2/raw I32 None",
+            "/calib/acc/b I32 None", "/calib/acc/k I32 Some(Int(16))",
+            "/calib/ecg/b I32 None", "/calib/ecg/k I32 Some(Int(1))",
+            "/io/status U32 None",
+        ]);
+        assert!(schema.warnings.is_empty(), "{:?}", schema.warnings);
+
+        // Every backend reads files the same way
+        let c = generate_c_file(&dir.join("regs.json"), "regs.h").unwrap();
+        assert!(c.header.contains("ELLOCOPO_REG_IO_STATUS"), "{}", c.header);
+        let py = generate_py_file(&dir.join("regs.json")).unwrap().code;
+        assert!(py.contains("\"/calib/acc/k\""), "{}", py);
+        let md = generate_docs_file(&dir.join("regs.json"), DocFormat::Markdown).unwrap().code;
+        assert!(md.contains("| `/adc/channel/2/gain` |"), "{}", md);
+        std::fs::write(dir.join("bad.ello"), "ctrl {\n    record: bool @access RX\n}\n").unwrap();
+        let e = generate_docs_file(&dir.join("bad.ello"), DocFormat::Html).err().unwrap();
+        assert_eq!((e[0].path.as_str(), e[0].pos), ("/ctrl/record/@access", Some((2, 18))));
+
+        // One enum for all elements, index goes with the variant
+        let code = generate_file(&dir.join("regs.json")).unwrap().code;
+        assert!(code.contains("Channel (u8 , Channel) ,"), "{}", code);
+        assert_eq!(code.matches("pub enum Channel").count(), 1);
+        assert!(code.contains("msg :: Adc :: Channel (2 , msg :: Channel :: Gain_W"), "{}", code);
+
+        // Subsections and nested arrays of element repeat for every index
+        let code = generate(r#"{
+  "adc": {
+    "@access": "RW",
+    "channel[2]": {
+      "cfg": { "gain": "u8" },
+      "raw": "i32",
+      "pin[2]": { "level": "bool", "mode": { "pull": "u8" } }
+    }
+  }
+}"#).unwrap().code;
+        for e in ["Channel", "Cfg", "Pin", "Mode"] {
+            assert_eq!(code.matches(&format!("pub enum {} ", e)).count(), 1, "{}", e);
+        }
+        assert!(code.contains("msg :: Adc :: Channel (1 , msg :: Channel :: Pin (1 , msg :: Pin :: Mode (msg :: Mode :: Pull_W"), "{}", code);
+        assert!(code.contains("\"/adc/channel/1/cfg/gain\""), "{}", code);
+
+        // Same arrays in text syntax
+        let text = generate_format("adc @access RW {\n    channel[3] {\n        gain: u8 @max 8\n        raw: i32 @access RO\n    }\n}\n", Format::Text).unwrap().code;
+        let json = generate(r#"{ "adc": { "@access": "RW", "channel[3]": { "gain": { "@type": "u8", "@max": 8 }, "raw": { "@type": "i32", "@access": "RO" } } } }"#).unwrap().code;
+        assert_eq!(text, json);
+
+        std::fs::write(dir.join("loop.json"), r#"{ "@include": "regs.json" }"#).unwrap();
+        std::fs::write(dir.join("regs.json"), r#"{ "@include": "loop.json" }"#).unwrap();
+        let e = parser::parse_file(&dir.join("regs.json")).unwrap_err();
+        assert_eq!(e.path, "/@include");
+        assert!(e.msg.contains("includes itself"), "{}", e.msg);
+
+        for (dsl, path, msg) in [
+            (r#"{ "a": { "@use": "nope" } }"#, "/a/@use", "Unknown template: nope"),
+            (r#"{ "@template": { "t": { "x": { "@use": "t" } } }, "a": { "@use": "t" } }"#, "/a/x/@use", "Template t uses itself"),
+            (r#"{ "a": { "@include": "missing.json" } }"#, "/a/@include", "missing.json"),
+            (r#"{ "a": { "r[2]": "u8" } }"#, "/a/r[2]", "Only sections can be arrays"),
+            (r#"{ "a": { "s[0]": { "r": "u8" } } }"#, "/a/s[0]", "Array length 1..=256 expected: 0"),
+        ] {
+            let e = parser::parser(dsl).unwrap_err();
+            assert_eq!(e.path, path, "{}", dsl);
+            assert!(e.msg.contains(msg), "{}: {}", dsl, e.msg);
+        }
+        let w = parser::parser(r#"{ "a": { "@template": {}, "r": "u8" } }"#).unwrap().warnings;
+        assert_eq!(w[0].path, "/a/@template");
+    }
+
+    #[test]
+    fn compat() {
+        use compat::{diff, Change};
+
+        let old = parser::parser(r#"{
+  "ctrl": { "record": "bool", "vis": "bool", "erase": "()" },
+  "io": { "status": "u32", "pos": "u32", "len": "u32" },
+  "calib": { "@access": "RW", "k": "i32", "b": "i32" }
+}"#).unwrap();
+        let new = parser::parser(r#"{
+  "ctrl": { "record": "bool", "visible": "bool", "erase": "()", "mode": "u8" },
+  "io": { "status": "u16", "pos": { "@type": "u32", "@access": "RW" } },
+  "calib": { "@access": "RW", "@wpriv": "SECUR", "k": "i32", "b": { "@type": "i32", "@access": "RO" } }
+}"#).unwrap();
+
+        let changes: Vec<String> = diff(&old.tree, &new.tree).iter().map(Change::to_string).collect();
+        assert_eq!(changes, vec![
+            "breaking: /calib/b: access narrowed R NORMAL, W NORMAL -> R NORMAL",
+            "breaking: /calib/k: access narrowed R NORMAL, W NORMAL -> R NORMAL, W SECUR",
+            "breaking: /ctrl/vis: renamed to /ctrl/visible",
+            "breaking: /io/len: removed",
+            "compatible: /io/pos: access widened R NORMAL -> R NORMAL, W NORMAL",
+            "breaking: /io/status: type u32 -> u16",
+            "compatible: /ctrl/mode: added",
+        ]);
+        assert!(diff(&old.tree, &old.tree).is_empty());
+        assert!(diff(&old.tree, &parser::parser(r#"{
+  "ctrl": { "record": "bool", "vis": "bool", "erase": "()", "new": "u8" },
+  "io": { "status": "u32", "pos": "u32", "len": "u32" },
+  "calib": { "@access": "RW", "k": "i32", "b": "i32" }
+}"#).unwrap().tree).iter().all(|c| !c.breaking()));
+
+        let old = parser::parser(r#"{
+  "cfg": {
+    "@access": "RW",
+    "lvl": { "@type": "i8", "@min": -10, "@max": 10 },
+    "gain": { "@type": "u8", "@max": 8 },
+    "name": { "@type": "str", "@maxlen": 8 },
+    "mode": { "@type": "str", "@oneof": ["auto", "hand"] },
+    "rate": "u16",
+    "ch": { "@type": "u8", "@oneof": [1, 2] }
+  }
+}"#).unwrap();
+        let new = parser::parser(r#"{
+  "cfg": {
+    "@access": "RW",
+    "lvl": { "@type": "i8", "@min": -20, "@max": 10 },
+    "gain": { "@type": "u8", "@max": 4 },
+    "name": { "@type": "str", "@maxlen": 16 },
+    "mode": { "@type": "str", "@oneof": ["auto", "hand", "off"] },
+    "rate": { "@type": "u16", "@min": 1 },
+    "ch": { "@type": "u8", "@oneof": [2, 3] }
+  }
+}"#).unwrap();
+        let changes: Vec<String> = diff(&old.tree, &new.tree).iter().map(Change::to_string).collect();
+        assert_eq!(changes, vec![
+            "breaking: /cfg/ch: constraints narrowed one of `1`, `2` -> one of `2`, `3`",
+            "breaking: /cfg/gain: constraints narrowed `<= 8` -> `<= 4`",
+            "compatible: /cfg/lvl: constraints widened `-10..=10` -> `-20..=10`",
+            "compatible: /cfg/mode: constraints widened one of `\"auto\"`, `\"hand\"` -> one of `\"auto\"`, `\"hand\"`, `\"off\"`",
+            "compatible: /cfg/name: constraints widened len <= 8 -> len <= 16",
+            "breaking: /cfg/rate: constraints narrowed none -> `>= 1`",
+        ]);
+        // Dropped constraints allow everything
+        let changes = diff(&new.tree, &parser::parser(r#"{ "cfg": { "@access": "RW", "lvl": "i8", "gain": "u8", "name": "str", "mode": "str", "rate": "u16", "ch": "u8" } }"#).unwrap().tree);
+        assert_eq!(changes.len(), 6);
+        assert!(changes.iter().all(|c| matches!(c, Change::ConstraintsWidened{..})), "{:?}", changes);
+    }
 }
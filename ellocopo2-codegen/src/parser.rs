@@ -1,14 +1,69 @@
 use std::fmt::Debug;
+use std::path::Path;
 
 use serde_json::{Value as JsonValue, map::Map};
-use ellocopo2::TypeTag;
+use ellocopo2::{TypeTag, PrivLvl};
+
+use crate::diag::{Diagnostic, pointer};
+use crate::expand::{expand, ANNOTATION_TEMPLATE_STR};
 
 const ANNOTATION_TOKEN:           &'static str = "@";
 const ANNOTATION_ACCESS_STR:      &'static str = "@access";
 const ANNOTATION_CONTROL_STR:     &'static str = "@control";
+const ANNOTATION_VERSION_STR:     &'static str = "@protocol_version";
+const ANNOTATION_VISIBILITY_STR:  &'static str = "@visibility";
 const ANNOTATION_TYPE_STR:        &'static str = "@type";
+const ANNOTATION_ERRORS_STR:      &'static str = "@errors";
+const ANNOTATION_DEFAULT_STR:     &'static str = "@default";
+const ANNOTATION_TXN_STR:         &'static str = "@txn";
+const ANNOTATION_PERSIST_STR:     &'static str = "@persist";
+const ANNOTATION_PRIV_STR:        &'static str = "@priv";
+const ANNOTATION_RPRIV_STR:       &'static str = "@rpriv";
+const ANNOTATION_WPRIV_STR:       &'static str = "@wpriv";
+const ANNOTATION_FAST_STR:        &'static str = "@fast";
+const ANNOTATION_COMMENT_STR:     &'static str = "@com";
+const ANNOTATION_DOC_STR:         &'static str = "@doc";
+const ANNOTATION_MIN_STR:         &'static str = "@min";
+const ANNOTATION_MAX_STR:         &'static str = "@max";
+const ANNOTATION_MAXLEN_STR:      &'static str = "@maxlen";
+const ANNOTATION_ONEOF_STR:       &'static str = "@oneof";
 pub const REGISTER_PATH_DELIMETR: &'static str = "/";
 
+/// Parsed scheme: register tree plus root level declarations
+#[derive(Clone, Debug)]
+pub struct Schema {
+    pub header: SchemaHeader,
+    pub tree: DslTree,
+    pub errors: Vec<ErrorDesc>,
+    /// Problems that don't stop generation, e.g. unknown annotations
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// Root annotations of the scheme
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SchemaHeader {
+    /// `@protocol_version`: "major.minor"
+    pub version: Option<(u16, u16)>,
+    /// `@visibility`
+    pub visibility: Visibility,
+    /// `@control`: sections and registers RW unless their own `@access` says otherwise
+    pub control: Vec<Vec<String>>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Visibility {
+    #[default]
+    Public,
+    Private,
+}
+
+/// Custom error code from `@errors` table
+#[derive(Clone, Debug)]
+pub struct ErrorDesc {
+    pub name: String,
+    pub code: u16,
+}
+
 #[derive(Clone, Debug)]
 pub enum DslTree {
     SectionV(Section),
@@ -20,6 +75,10 @@ pub struct Section {
     pub path: Vec<String>,
     pub name: String,
     pub meta: MetaDesc,
+    /// `@doc` followed by `@com*` annotations, one per line
+    pub doc: Option<String>,
+    /// Element of section array `name[N]`, path ends with the index
+    pub index: Option<usize>,
     pub children: Vec<DslTree>,
 }
 
@@ -29,6 +88,55 @@ pub struct Register {
     pub name: String,
     pub ty: TypeTag,
     pub meta: MetaDesc,
+    /// `@default` value restored by RESET request
+    pub default: Option<Literal>,
+    /// `@doc` followed by `@com*` annotations, one per line
+    pub doc: Option<String>,
+    /// Written values are checked against these
+    pub constraints: Constraints,
+}
+
+/// `@min`, `@max`, `@maxlen` and `@oneof` of writable register
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Constraints {
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    /// Size of STR or BYTES value in bytes
+    pub maxlen: Option<usize>,
+    /// Allowed values, any if empty
+    pub oneof: Vec<Literal>,
+}
+
+impl Constraints {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `lit` satisfies every constraint
+    pub fn allows(&self, lit: &Literal) -> bool {
+        let len = match lit {
+            Literal::Str(s) => Some(s.len()),
+            Literal::Bytes(b) => Some(b.len()),
+            _ => None,
+        };
+        let int = match lit {
+            Literal::Int(i) => Some(*i),
+            _ => None,
+        };
+        self.min.zip(int).into_iter().all(|(min, i)| i >= min)
+            && self.max.zip(int).into_iter().all(|(max, i)| i <= max)
+            && self.maxlen.zip(len).into_iter().all(|(max, l)| l <= max)
+            && (self.oneof.is_empty() || self.oneof.contains(lit))
+    }
+}
+
+/// Register value literal, already checked against register type
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Literal {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Bytes(Vec<u8>),
 }
 
 #[derive(Clone, Copy)]
@@ -36,34 +144,28 @@ pub struct MetaDesc {
     pub w: bool, // Write rights
     pub r: bool, // Read rights
     pub fast: bool, // Fast impl
-    // TODO: Смотри заметку ниже
-    //pub w_plvl: --; 
-    //pub r_plvl: --;
+    pub txn: bool, // Writes are staged in transaction
+    pub persist: bool, // Value is kept in storage image
+    pub w_plvl: PrivLvl, // Level required to write
+    pub r_plvl: PrivLvl, // Level required to read
 }
 
 impl DslTree {
     pub fn visit(&mut self, f: &mut impl FnMut(&mut DslTree)) {
         f(self);
-        match self {
-            DslTree::SectionV(section) => {
-                for mut c in &mut section.children {
-                    Self::visit(&mut c, f);
-                }
+        if let DslTree::SectionV(section) = self {
+            for c in &mut section.children {
+                Self::visit(c, f);
             }
-            _ => (),
         }
     }
 
     pub fn visit_accum<A: Clone>(&self, f: &mut impl FnMut(&DslTree, A) -> A, a: A) {
         let a = f(self, a);
-        match self {
-            DslTree::SectionV(section) => {
-                for c in &section.children {
-                    let a = a.clone();
-                    Self::visit_accum(&c, f, a);
-                }
+        if let DslTree::SectionV(section) = self {
+            for c in &section.children {
+                Self::visit_accum(c, f, a.clone());
             }
-            _ => (),
         }
     }
 
@@ -95,6 +197,22 @@ impl Debug for MetaDesc {
             write!(f, " fast")?;
         }
 
+        if self.txn {
+            write!(f, " txn")?;
+        }
+
+        if self.persist {
+            write!(f, " persist")?;
+        }
+
+        if self.w_plvl != PrivLvl::NORMAL_LVL {
+            write!(f, " w:{:?}", self.w_plvl)?;
+        }
+
+        if self.r_plvl != PrivLvl::NORMAL_LVL {
+            write!(f, " r:{:?}", self.r_plvl)?;
+        }
+
         Ok(())
     }
 }
@@ -105,38 +223,192 @@ impl Default for MetaDesc {
             w: false,
             r: true,
             fast: false,
+            txn: false,
+            persist: false,
+            w_plvl: PrivLvl::NORMAL_LVL,
+            r_plvl: PrivLvl::NORMAL_LVL,
         }
     }
 }
 
-pub fn parser(dsl: &str) -> Result<DslTree, String> {
-    let v: JsonValue = serde_json::from_str(dsl).unwrap();
-    //println!("{:#?}", v);
-    //sections(v);
-    let l = parse_dsl(v);
-    println!("{:#?}", &l);
-        
-    l
+/// Text syntax of the scheme, all of them lower to the same tree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    /// Needs `toml` feature
+    Toml,
+    /// Needs `yaml` feature
+    Yaml,
+    /// Own syntax with comments, see `text`
+    Text,
 }
 
-fn parse_dsl(root: JsonValue) -> Result<DslTree, String>{
+impl Format {
+    /// Format by file extension: `json`, `toml`, `yaml`/`yml` or `ello`
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "ello" => Some(Format::Text),
+            _ => None,
+        }
+    }
+
+    /// Scheme `src` as JSON value
+    pub(crate) fn read(self, src: &str) -> Result<JsonValue, Diagnostic> {
+        let at = |pos, msg: String| Diagnostic { pos, ..Diagnostic::error("", msg) };
+        match self {
+            Format::Json => serde_json::from_str(src)
+                .map_err(|e| at(Some((e.line(), e.column())), e.to_string())),
+            #[cfg(feature = "toml")]
+            Format::Toml => toml::from_str(src)
+                .map_err(|e| at(e.span().map(|s| line_col(src, s.start)), e.message().to_string())),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => serde_yaml::from_str(src)
+                .map_err(|e| at(e.location().map(|l| (l.line(), l.column())), e.to_string())),
+            #[cfg(not(feature = "toml"))]
+            Format::Toml => Err(at(None, "TOML schemes need `toml` feature".to_string())),
+            #[cfg(not(feature = "yaml"))]
+            Format::Yaml => Err(at(None, "YAML schemes need `yaml` feature".to_string())),
+            Format::Text => crate::text::parse(src).map(|(v, _)| v),
+        }
+    }
+
+    /// Fill line and column of `d` found in scheme `src`
+    ///
+    /// TOML and YAML problems are only pointed to by path.
+    pub fn locate(self, d: Diagnostic, src: &str) -> Diagnostic {
+        match self {
+            Format::Json => d.locate(src),
+            Format::Text if d.pos.is_none() => Diagnostic { pos: crate::text::locate(src, &d.path), ..d },
+            _ => d,
+        }
+    }
+}
+
+fn line_col(src: &str, offset: usize) -> (usize, usize) {
+    let before = &src[..offset];
+    let col = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (before.matches('\n').count() + 1, col)
+}
+
+pub fn parser(dsl: &str) -> Result<Schema, Diagnostic> {
+    parse(dsl, Format::Json)
+}
+
+/// Scheme `src` written in `format`, `@include` paths are relative to
+/// working directory
+pub fn parse(src: &str, format: Format) -> Result<Schema, Diagnostic> {
+    parse_in(src, format, Path::new(""))
+}
+
+/// Scheme file, format by extension or JSON, `@include` paths are relative to it
+pub fn parse_file(path: &Path) -> Result<Schema, Diagnostic> {
+    let src = std::fs::read_to_string(path)
+        .map_err(|e| Diagnostic::error("", e.to_string()))?;
+    let format = Format::from_path(path).unwrap_or(Format::Json);
+    parse_in(&src, format, path.parent().unwrap_or(Path::new("")))
+}
+
+pub(crate) fn parse_in(src: &str, format: Format, base: &Path) -> Result<Schema, Diagnostic> {
+    let v = format.read(src)?;
+    let parse = || -> Result<Schema, Diagnostic> {
+        let v = expand(v, base)?;
+        let header = parse_root_meta(&v)?;
+        let errors = parse_errors(&v)?;
+        let mut warnings = Vec::new();
+        unknown_annotations(&v, &mut Vec::new(), &mut warnings);
+        Ok(Schema {
+            tree: parse_dsl(&v, &header)?,
+            header,
+            errors,
+            warnings,
+        })
+    };
+
+    match parse() {
+        Ok(mut schema) => {
+            schema.warnings = schema.warnings.into_iter().map(|w| format.locate(w, src)).collect();
+            Ok(schema)
+        }
+        Err(e) => Err(format.locate(e, src)),
+    }
+}
+
+fn err(path: &[String], key: Option<&str>, msg: impl Into<String>) -> Diagnostic {
+    Diagnostic::error(pointer(path, key), msg)
+}
+
+/// Annotations codegen doesn't know are reported, but don't stop generation
+fn unknown_annotations(v: &JsonValue, path: &mut Vec<String>, warnings: &mut Vec<Diagnostic>) {
+    const ROOT: &[&str] = &[
+        ANNOTATION_ERRORS_STR, ANNOTATION_CONTROL_STR, ANNOTATION_VERSION_STR, ANNOTATION_VISIBILITY_STR,
+        ANNOTATION_TEMPLATE_STR,
+    ];
+    const NODE: &[&str] = &[
+        ANNOTATION_ACCESS_STR, ANNOTATION_TYPE_STR, ANNOTATION_DEFAULT_STR, ANNOTATION_TXN_STR, ANNOTATION_PERSIST_STR,
+        ANNOTATION_PRIV_STR, ANNOTATION_RPRIV_STR, ANNOTATION_WPRIV_STR, ANNOTATION_FAST_STR,
+        ANNOTATION_DOC_STR, ANNOTATION_MIN_STR, ANNOTATION_MAX_STR, ANNOTATION_MAXLEN_STR, ANNOTATION_ONEOF_STR,
+    ];
+
+    let fields = match v {
+        JsonValue::Object(fields) => fields,
+        _ => return,
+    };
+    for (k, v) in fields {
+        if !filter_nodes(k) {
+            if k.starts_with(ANNOTATION_COMMENT_STR) || NODE.contains(&k.as_str()) {
+                continue;
+            }
+            if ROOT.contains(&k.as_str()) {
+                if !path.is_empty() {
+                    warnings.push(Diagnostic::warning(pointer(path, Some(k)), format!("{} is only meaningful at the root, ignored", k)));
+                }
+                continue;
+            }
+            warnings.push(Diagnostic::warning(pointer(path, Some(k)), format!("Unknown annotation {}, ignored", k)));
+        } else {
+            path.push(k.clone());
+            unknown_annotations(v, path, warnings);
+            path.pop();
+        }
+    }
+}
+
+/// Root `@errors` table: `{ "name": code, ... }`
+fn parse_errors(root: &JsonValue) -> Result<Vec<ErrorDesc>, Diagnostic> {
+    let mut errors = Vec::new();
+    match root.get(ANNOTATION_ERRORS_STR) {
+        Some(JsonValue::Object(table)) => {
+            for (name, code) in table {
+                let at = || pointer(&[ANNOTATION_ERRORS_STR.to_string()], Some(name));
+                let code = code.as_u64()
+                    .filter(|c| *c <= u16::MAX as u64)
+                    .ok_or_else(|| Diagnostic::error(at(), format!("Bad error code: {}", code)))?;
+                if let Some(e) = errors.iter().find(|e: &&ErrorDesc| e.code == code as u16) {
+                    Err(Diagnostic::error(at(), format!("Duplicate error code {}, already used by {}", code, e.name)))?
+                }
+                errors.push(ErrorDesc { name: name.clone(), code: code as u16 });
+            }
+        }
+        Some(v) => Err(err(&[], Some(ANNOTATION_ERRORS_STR), format!("Malformed table: {}", v)))?,
+        None => (),
+    }
+    Ok(errors)
+}
+
+fn parse_dsl(root: &JsonValue, header: &SchemaHeader) -> Result<DslTree, Diagnostic> {
     // Default meta RO
     let meta = MetaDesc::default();
     // Prefix path with root elem /
-    let mut children = Vec::new();
     let path = Vec::new();
 
     // root object
-    if let JsonValue::Object(root) = root {
-        for (name, fields) in root {
-            if filter_nodes(&name) {
-                let mut new_path = path.clone();
-                new_path.push(name.clone());
-                children.push(visit_tree(&new_path, &name, &fields, meta)?);
-            }
-        }
+    let children = if let JsonValue::Object(root) = root {
+        visit_children(&path, root, meta, header)?
     } else {
-        Err("Non root object".to_string())?
+        Err(err(&path, None, "Non root object"))?
     };
 
     Ok(DslTree::SectionV(
@@ -144,62 +416,109 @@ fn parse_dsl(root: JsonValue) -> Result<DslTree, String>{
             path,
             name: "Msg".to_string(),
             meta,
+            doc: match root {
+                JsonValue::Object(fields) => extract_doc(&[], fields)?,
+                _ => None,
+            },
+            index: None,
             children,
         }
     ))
 }
 
-// Здесь читаем аннтоцаии рута, из них строим особоые права доступа для секции и регистров
-// потом в корненвую структуру MetaDesc положим Rc<AccessPriv> у которой поля хэшмапы с именами
-// секций/регистров и в каждом вызове extract_meta будем их правильно устнавливать если значения в
-// хэше сошлись с именем(путем) фактической ноды
-fn parse_root_meta(_root: JsonValue) -> MetaDesc {
-    todo!()
+/// Root annotations: `@protocol_version`, `@visibility` and `@control`
+///
+/// `@control` paths are applied in `visit_tree` while walking the tree:
+/// matching node and everything under it become RW by default.
+fn parse_root_meta(root: &JsonValue) -> Result<SchemaHeader, Diagnostic> {
+    let mut header = SchemaHeader::default();
+
+    match root.get(ANNOTATION_VERSION_STR) {
+        Some(JsonValue::String(v)) => {
+            let version = v.split_once('.')
+                .and_then(|(major, minor)| Some((major.parse().ok()?, minor.parse().ok()?)))
+                .ok_or_else(|| err(&[], Some(ANNOTATION_VERSION_STR), format!("Malformed version, \"major.minor\" expected: {}", v)))?;
+            header.version = Some(version);
+        }
+        Some(v) => Err(err(&[], Some(ANNOTATION_VERSION_STR), format!("Malformed version: {}", v)))?,
+        None => (),
+    }
+
+    if let Some(v) = root.get(ANNOTATION_VISIBILITY_STR) {
+        header.visibility = match v.as_str() {
+            Some("public") => Visibility::Public,
+            Some("private") => Visibility::Private,
+            _ => Err(err(&[], Some(ANNOTATION_VISIBILITY_STR), format!("\"public\" or \"private\" expected: {}", v)))?,
+        };
+    }
+
+    match root.get(ANNOTATION_CONTROL_STR) {
+        Some(JsonValue::Array(paths)) => {
+            for p in paths {
+                let p = p.as_str()
+                    .ok_or_else(|| err(&[], Some(ANNOTATION_CONTROL_STR), format!("Malformed entry: {}", p)))?;
+                // Entries may list several paths: "ctrl, io"
+                for p in p.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                    let p = p.trim_start_matches(REGISTER_PATH_DELIMETR);
+                    if root.pointer(&(REGISTER_PATH_DELIMETR.to_string() + p)).is_none() {
+                        Err(err(&[], Some(ANNOTATION_CONTROL_STR), format!("Unknown node: /{}", p)))?
+                    }
+                    header.control.push(p.split(REGISTER_PATH_DELIMETR).map(String::from).collect());
+                }
+            }
+        }
+        Some(v) => Err(err(&[], Some(ANNOTATION_CONTROL_STR), format!("Malformed paths: {}", v)))?,
+        None => (),
+    }
+
+    Ok(header)
 }
 
-fn filter_nodes(name: &String) -> bool {
+fn filter_nodes(name: &str) -> bool {
     // filter @annotations
     !name.starts_with(ANNOTATION_TOKEN)
 }
 
-fn visit_tree(path: &Vec<String>, name: &String, value: &JsonValue, meta: MetaDesc) -> Result<DslTree, String> {
+fn visit_tree(path: &[String], name: &str, value: &JsonValue, meta: MetaDesc, header: &SchemaHeader) -> Result<DslTree, Diagnostic> {
+    // Root scoped access, own @access of the node still wins
+    let meta = if header.control.iter().any(|p| p == path) {
+        MetaDesc{w: true, r: true, .. meta}
+    } else { meta };
+
     Ok(match value {
-        JsonValue::Object(fields) => visit_node(path, name, fields, meta)?,
+        JsonValue::Object(fields) => visit_node(path, name, fields, meta, header)?,
         JsonValue::String(ty_s) => { 
-            let ty = ty_convert(ty_s)?;
-            visit_leaf(path, name, ty, meta)?
+            let ty = ty_convert(ty_s).map_err(|e| err(path, None, e))?;
+            visit_leaf(path, name, ty, meta, None, None)?
         }
-        err_str @ _ => Err(&format!("Unexpected entity in parse tree: {:?}", err_str))?,
+        err_str => Err(err(path, None, format!("Type or object expected: {}", err_str)))?,
     })
 }
 
-fn visit_node(path: &Vec<String>, name: &String, fields: &Map<String, JsonValue>, meta: MetaDesc) -> Result<DslTree, String> {
-    let meta = meta.extract_update(path, fields);
+fn visit_node(path: &[String], name: &str, fields: &Map<String, JsonValue>, meta: MetaDesc, header: &SchemaHeader) -> Result<DslTree, Diagnostic> {
+    let meta = meta.extract_update(path, fields)?;
     
     // Test for nested register definition
-    let res = match extract_ty(fields) {
+    let res = match extract_ty(path, fields)? {
         // It's nested register definition, proceed to creating a leaf
         Some(ty) => {
-            visit_leaf(path, name, ty, meta)?
+            let default = extract_default(path, ty, meta, fields)?;
+            let doc = extract_doc(path, fields)?;
+            let mut leaf = visit_leaf(path, name, ty, meta, default, doc)?;
+            if let DslTree::RegisterV(reg) = &mut leaf {
+                reg.constraints = extract_constraints(path, reg, fields)?;
+            }
+            leaf
         }
         // None => then it's nested section, so continue recursively
         None => {
-            let mut children = Vec::new();
-            for (name, keys) in fields {
-                if filter_nodes(&name) {
-                    let mut new_path = path.clone();
-                    new_path.push(name.clone());
-                    children.push(
-                        visit_tree(&new_path, name, keys, meta)?
-                    );
-                }
-            }
-
             DslTree::SectionV(Section {
-                name: name.clone(),
-                path: path.clone(),
+                name: name.to_string(),
+                path: path.to_vec(),
                 meta,
-                children,
+                doc: extract_doc(path, fields)?,
+                index: None,
+                children: visit_children(path, fields, meta, header)?,
             })
         }
     };
@@ -207,70 +526,267 @@ fn visit_node(path: &Vec<String>, name: &String, fields: &Map<String, JsonValue>
     Ok(res)
 }
 
-fn visit_leaf(path: &Vec<String>, name: &String, ty: TypeTag, meta: MetaDesc) -> Result<DslTree, String> {
+/// Nodes under section `path`, section arrays are unrolled here
+fn visit_children(path: &[String], fields: &Map<String, JsonValue>, meta: MetaDesc, header: &SchemaHeader) -> Result<Vec<DslTree>, Diagnostic> {
+    let mut children = Vec::new();
+    for (key, value) in fields {
+        if !filter_nodes(key) {
+            continue;
+        }
+        let mut new_path = path.to_vec();
+        new_path.push(key.clone());
+        let (name, len) = match array_key(key) {
+            Some(array) => array,
+            None => {
+                children.push(visit_tree(&new_path, key, value, meta, header)?);
+                continue;
+            }
+        };
+
+        // Element index is `u8` in generated enums
+        let len = match len.parse::<usize>() {
+            Ok(len @ 1..=256) => len,
+            _ => Err(err(&new_path, None, format!("Array length 1..=256 expected: {}", len)))?,
+        };
+        match value {
+            JsonValue::Object(fields) if !fields.contains_key(ANNOTATION_TYPE_STR) => (),
+            _ => Err(err(&new_path, None, "Only sections can be arrays"))?,
+        }
+        let name = name.to_string();
+        for i in 0..len {
+            let mut elem_path = path.to_vec();
+            elem_path.push(name.clone());
+            elem_path.push(i.to_string());
+            let mut elem = visit_tree(&elem_path, &name, value, meta, header)?;
+            if let DslTree::SectionV(section) = &mut elem {
+                section.index = Some(i);
+            }
+            children.push(elem);
+        }
+    }
+    Ok(children)
+}
+
+/// `name[N]` key of section array split into name and length
+fn array_key(key: &str) -> Option<(&str, &str)> {
+    key.strip_suffix(']')?.split_once('[')
+}
+
+fn visit_leaf(path: &[String], name: &str, ty: TypeTag, meta: MetaDesc, default: Option<Literal>, doc: Option<String>) -> Result<DslTree, Diagnostic> {
 
     // WO behaviour for UNIT ty
     let meta = if let TypeTag::UNIT = ty {
         MetaDesc{w: true, r: false, .. meta}
     } else { meta };
 
+    // Staged values are kept by value and backed up by reading before commit
+    let scalar = !matches!(ty, TypeTag::UNIT | TypeTag::STR | TypeTag::BYTES);
+    if meta.txn && !(meta.w && meta.r && !meta.fast && scalar) {
+        Err(err(path, None, format!("{} register must be plain RW scalar", ANNOTATION_TXN_STR)))?
+    }
+
+    // Saved by reading it and restored by writing it back
+    if meta.persist && !(meta.w && meta.r && !meta.fast) {
+        Err(err(path, None, format!("{} register must be RW and not {}", ANNOTATION_PERSIST_STR, ANNOTATION_FAST_STR)))?
+    }
+
     Ok(DslTree::RegisterV(Register {
-        name: name.clone(),
-        path: path.clone(),
+        name: name.to_string(),
+        path: path.to_vec(),
         meta,
         ty,
+        default,
+        doc,
+        constraints: Constraints::default(),
     }))
 }
 
-fn extract_ty(fields: &Map<String, JsonValue>) -> Option<TypeTag> {
-    let mut ty = None;
-    for (k,v) in fields {
-        if k.starts_with(ANNOTATION_TYPE_STR) {
-            if let JsonValue::String(tyy) = v {
-                ty = Some(ty_convert(tyy).unwrap());
-            } else  {
-                panic!("Wrong type in @type")
+/// `@doc` text, then string `@com*` annotations in key order, joined by lines
+fn extract_doc(path: &[String], fields: &Map<String, JsonValue>) -> Result<Option<String>, Diagnostic> {
+    let mut lines = Vec::new();
+    match fields.get(ANNOTATION_DOC_STR) {
+        Some(JsonValue::String(doc)) => lines.push(doc.trim()),
+        Some(v) => Err(err(path, Some(ANNOTATION_DOC_STR), format!("Text expected: {}", v)))?,
+        None => (),
+    }
+    lines.extend(fields.iter()
+        .filter(|(k, _)| k.starts_with(ANNOTATION_COMMENT_STR))
+        .filter_map(|(_, v)| v.as_str())
+        .map(str::trim));
+    Ok(if lines.is_empty() { None } else { Some(lines.join("\n")) })
+}
+
+fn extract_ty(path: &[String], fields: &Map<String, JsonValue>) -> Result<Option<TypeTag>, Diagnostic> {
+    match fields.get(ANNOTATION_TYPE_STR) {
+        Some(JsonValue::String(ty)) => ty_convert(ty)
+            .map(Some)
+            .map_err(|e| err(path, Some(ANNOTATION_TYPE_STR), e)),
+        Some(v) => Err(err(path, Some(ANNOTATION_TYPE_STR), format!("Type name expected: {}", v))),
+        None => Ok(None),
+    }
+}
+
+/// `@default` of nested register definition, only for plain writable registers
+fn extract_default(path: &[String], ty: TypeTag, meta: MetaDesc, fields: &Map<String, JsonValue>) -> Result<Option<Literal>, Diagnostic> {
+    let v = match fields.get(ANNOTATION_DEFAULT_STR) {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let at = |msg: String| err(path, Some(ANNOTATION_DEFAULT_STR), msg);
+    if !meta.w || meta.fast || ty == TypeTag::UNIT {
+        Err(at("Default on register without plain write access".to_string()))?
+    }
+
+    literal(ty, v, at).map(Some)
+}
+
+/// JSON value `v` as literal of register type `ty`
+fn literal(ty: TypeTag, v: &JsonValue, at: impl Fn(String) -> Diagnostic) -> Result<Literal, Diagnostic> {
+    let lit = match (ty, v) {
+        (TypeTag::BOOL, JsonValue::Bool(b)) => Literal::Bool(*b),
+        (TypeTag::STR, JsonValue::String(s)) => Literal::Str(s.clone()),
+        (TypeTag::BYTES, JsonValue::Array(a)) => Literal::Bytes(a.iter()
+            .map(|b| b.as_u64().filter(|b| *b <= u8::MAX as u64).map(|b| b as u8))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| at(format!("Bad byte: {}", v)))?),
+        (ty, JsonValue::Number(n)) => {
+            let (min, max) = int_range(ty)
+                .ok_or_else(|| at(format!("Numeric value for {:?} register", ty)))?;
+            let n = n.as_i64()
+                .filter(|n| (min..=max).contains(n))
+                .ok_or_else(|| at(format!("Out of {:?} range: {}", ty, n)))?;
+            Literal::Int(n)
+        }
+        (ty, v) => Err(at(format!("Doesn't match {:?}: {}", ty, v)))?,
+    };
+    Ok(lit)
+}
+
+/// Constraints of register `reg`, its `@default` must satisfy them
+fn extract_constraints(path: &[String], reg: &Register, fields: &Map<String, JsonValue>) -> Result<Constraints, Diagnostic> {
+    let mut c = Constraints::default();
+    let keys = [ANNOTATION_MIN_STR, ANNOTATION_MAX_STR, ANNOTATION_MAXLEN_STR, ANNOTATION_ONEOF_STR];
+    let first = match keys.iter().find(|k| fields.contains_key(**k)) {
+        Some(k) => *k,
+        None => return Ok(c),
+    };
+    if !reg.meta.w || reg.ty == TypeTag::UNIT {
+        Err(err(path, Some(first), "Constraint on register without written value"))?
+    }
+    let ty = reg.ty;
+
+    for key in &[ANNOTATION_MIN_STR, ANNOTATION_MAX_STR] {
+        if let Some(v) = fields.get(*key) {
+            let at = |msg: String| err(path, Some(key), msg);
+            if int_range(ty).is_none() {
+                Err(at(format!("{} of {:?} register", key, ty)))?
+            }
+            let n = match literal(ty, v, at)? {
+                Literal::Int(n) => n,
+                _ => unreachable!("Integer register literal"),
+            };
+            if *key == ANNOTATION_MIN_STR { c.min = Some(n) } else { c.max = Some(n) }
+        }
+    }
+    if let (Some(min), Some(max)) = (c.min, c.max) {
+        if min > max {
+            Err(err(path, Some(ANNOTATION_MAX_STR), format!("@max {} is below @min {}", max, min)))?
+        }
+    }
+
+    if let Some(v) = fields.get(ANNOTATION_MAXLEN_STR) {
+        let at = |msg: String| err(path, Some(ANNOTATION_MAXLEN_STR), msg);
+        if !matches!(ty, TypeTag::STR | TypeTag::BYTES) {
+            Err(at(format!("@maxlen of {:?} register", ty)))?
+        }
+        let n = v.as_u64()
+            .filter(|n| *n <= ellocopo2::MAX_PAYLOAD_SZ as u64)
+            .ok_or_else(|| at(format!("Length up to {} expected: {}", ellocopo2::MAX_PAYLOAD_SZ, v)))?;
+        c.maxlen = Some(n as usize);
+    }
+
+    if let Some(v) = fields.get(ANNOTATION_ONEOF_STR) {
+        let at = |msg: String| err(path, Some(ANNOTATION_ONEOF_STR), msg);
+        match v {
+            JsonValue::Array(values) if !values.is_empty() => {
+                for v in values {
+                    c.oneof.push(literal(ty, v, at)?);
+                }
             }
+            _ => Err(at(format!("Non empty list of values expected: {}", v)))?,
+        }
+    }
+
+    if let Some(default) = &reg.default {
+        if !c.allows(default) {
+            Err(err(path, Some(ANNOTATION_DEFAULT_STR), "Default breaks register constraints"))?
         }
     }
-    ty
+    Ok(c)
+}
+
+pub(crate) fn int_range(ty: TypeTag) -> Option<(i64, i64)> {
+    Some(match ty {
+        TypeTag::I8  => (i8::MIN as i64, i8::MAX as i64),
+        TypeTag::I16 => (i16::MIN as i64, i16::MAX as i64),
+        TypeTag::I32 => (i32::MIN as i64, i32::MAX as i64),
+        TypeTag::U8  => (0, u8::MAX as i64),
+        TypeTag::U16 => (0, u16::MAX as i64),
+        TypeTag::U32 => (0, u32::MAX as i64),
+        _ => return None,
+    })
 }
 
 impl MetaDesc {
-    fn extract_update(self, _path: &Vec<String>, fields: &Map<String, JsonValue>) -> MetaDesc {
+    fn extract_update(self, path: &[String], fields: &Map<String, JsonValue>) -> Result<MetaDesc, Diagnostic> {
         let mut meta = self;
         for (k,v) in fields {
+            let at = |msg: String| err(path, Some(k), msg);
+            let flag = || v.as_bool().ok_or_else(|| at(format!("true or false expected: {}", v)));
             if k == ANNOTATION_ACCESS_STR {
-                if let JsonValue::String(rights) = v {
-                    let Access{ w, r} = access_convert(rights)
-                        .expect("Malformed access rights format");
-                    meta.w = w;
-                    meta.r = r;
-                } else  {
-                    panic!("Malformed access rights inner type")
-                }
+                let rights = v.as_str().ok_or_else(|| at(format!("Access rights expected: {}", v)))?;
+                let Access{ w, r} = access_convert(rights).map_err(at)?;
+                meta.w = w;
+                meta.r = r;
+            }
+            if k == ANNOTATION_FAST_STR {
+                meta.fast = flag()?;
+            }
+            if k == ANNOTATION_TXN_STR {
+                meta.txn = flag()?;
             }
-            if k == ("@fast") {
-                if let JsonValue::Bool(true) = v {
-                    meta.fast = true;
+            if k == ANNOTATION_PERSIST_STR {
+                meta.persist = flag()?;
+            }
+            if k == ANNOTATION_PRIV_STR || k == ANNOTATION_RPRIV_STR || k == ANNOTATION_WPRIV_STR {
+                let lvl = v.as_str().ok_or_else(|| at(format!("Privilege level expected: {}", v)))?;
+                let lvl = priv_convert(lvl).map_err(at)?;
+                if k != ANNOTATION_RPRIV_STR {
+                    meta.w_plvl = lvl;
+                }
+                if k != ANNOTATION_WPRIV_STR {
+                    meta.r_plvl = lvl;
                 }
             }
         }
-        meta
+        Ok(meta)
     }
 }
 
 
-fn ty_convert(tytag: &String) -> Result<TypeTag, String> {
-    let ty = match tytag.as_str() {
+fn ty_convert(tytag: &str) -> Result<TypeTag, String> {
+    let ty = match tytag {
         "()"   => TypeTag::UNIT,
         "bool" => TypeTag::BOOL,
-        "u8"   => TypeTag::U8,
+        "i8"   => TypeTag::I8,
+        "i16"  => TypeTag::I16,
         "i32"  => TypeTag::I32,
+        "u8"   => TypeTag::U8,
+        "u16"  => TypeTag::U16,
         "u32"  => TypeTag::U32,
         "str"  => TypeTag::STR,
         "[u8]" => TypeTag::BYTES,
-        _      => return Err(format!("Unsupproted type: {}", &tytag)),
+        _      => return Err(format!("Unsupported type: {}", tytag)),
     };
     Ok(ty)
 }
@@ -280,14 +796,26 @@ struct Access {
     r: bool,
 }
 
-fn access_convert(access: &String) -> Result<Access, String> {
-    let access = match access.as_str() {
+fn access_convert(access: &str) -> Result<Access, String> {
+    let access = match access {
         "WO" => Access{ w: true, r: false },
         "RO" => Access{ w: false, r: true },
         "RW" => Access{ w: true, r: true },
-        _    => return Err(format!("Unsupproted access meta: {}", &access)),
+        _    => return Err(format!("Unsupported access: {}", access)),
     };
     Ok(access)
 }
 
-
+/// Privilege level by name, `SECUR` or `SECUR_LVL`
+fn priv_convert(lvl: &str) -> Result<PrivLvl, String> {
+    let lvl = match lvl.trim_end_matches("_LVL") {
+        "NORMAL" => PrivLvl::NORMAL_LVL,
+        "MODE1"  => PrivLvl::MODE1_LVL,
+        "MODE2"  => PrivLvl::MODE2_LVL,
+        "MODE3"  => PrivLvl::MODE3_LVL,
+        "SECUR"  => PrivLvl::SECUR_LVL,
+        "DEVEL"  => PrivLvl::DEVEL_LVL,
+        _        => return Err(format!("Unsupported privilege level: {}", lvl)),
+    };
+    Ok(lvl)
+}
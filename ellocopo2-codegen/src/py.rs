@@ -0,0 +1,511 @@
+//! Python backend: self-contained client module
+//!
+//! Frame encode/decode mirrors `RequestBuilder` and `ParseMsg`, `RegClient`
+//! gets `<path>_read`/`<path>_write` per register like the Rust client.
+
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+use ellocopo2::{AnswerCode, PrivLvl, RequestCode, TypeTag, FRAME_TRAILER_SZ, HEADER_SZ, MAX_MSG_SZ, MAX_PATH_SZ, MAX_PAYLOAD_SZ, SIGN, SIGN_AUTH};
+
+use crate::parser::*;
+
+pub fn gen(schema: &Schema) -> String {
+    let mut s = String::new();
+    s.push_str(r#""""Register client, generated by ellocopo2-codegen from scheme, do not edit
+
+Transport is any object with `exchange(frame: bytes) -> bytes`, sending one
+request frame and returning one answer frame. `StreamTransport` does that over
+pyserial `Serial` or `socket.makefile("rwb", buffering=0)`.
+"""
+
+import struct
+from enum import IntEnum
+
+"#);
+    if let Some((major, minor)) = schema.header.version {
+        writeln!(s, "PROTOCOL_VERSION = ({}, {})", major, minor).unwrap();
+    }
+    writeln!(s, "SIGN = 0x{:02X}", SIGN).unwrap();
+    writeln!(s, "SIGN_AUTH = 0x{:02X}", SIGN_AUTH).unwrap();
+    writeln!(s, "HEADER_SZ = {}", HEADER_SZ).unwrap();
+    writeln!(s, "FRAME_TRAILER_SZ = {}", FRAME_TRAILER_SZ).unwrap();
+    writeln!(s, "MAX_MSG_SZ = {}", MAX_MSG_SZ).unwrap();
+    writeln!(s, "MAX_PATH_SZ = {}", MAX_PATH_SZ).unwrap();
+    writeln!(s, "MAX_PAYLOAD_SZ = {}", MAX_PAYLOAD_SZ).unwrap();
+
+    let req = |v| AnswerCode::try_from(v).ok().and_then(|c| RequestCode::try_from(c).ok());
+    enum_class(&mut s, "RequestCode", req);
+    enum_class(&mut s, "AnswerCode", |v| AnswerCode::try_from(v).ok());
+    enum_class(&mut s, "TypeTag", |v| TypeTag::try_from(v).ok());
+    enum_class(&mut s, "PrivLvl", |v| PrivLvl::try_from(v).ok());
+
+    s.push_str("\n\nclass CustomErr(IntEnum):\n    \"\"\"Detail codes of ERR_CUSTOM answers\"\"\"\n");
+    for e in &schema.errors {
+        writeln!(s, "    {} = {}", e.name.to_uppercase(), e.code).unwrap();
+    }
+    if schema.errors.is_empty() {
+        s.push_str("    pass\n");
+    }
+    s.push_str(FRAMING);
+
+    s.push_str("\n\nclass RegClient(Client):\n    \"\"\"Typed accessor per register of the scheme\"\"\"\n");
+    walk(&mut s, &schema.tree, None);
+    s
+}
+
+/// Registers in scheme order along with doc of their section
+fn walk(s: &mut String, node: &DslTree, section_doc: Option<&str>) {
+    match node {
+        DslTree::SectionV(section) => {
+            for c in &section.children {
+                walk(s, c, section.doc.as_deref());
+            }
+        }
+        DslTree::RegisterV(reg) => accessors(s, reg, section_doc),
+    }
+}
+
+fn enum_class<T: std::fmt::Debug>(s: &mut String, name: &str, f: impl Fn(u8) -> Option<T>) {
+    writeln!(s, "\n\nclass {}(IntEnum):", name).unwrap();
+    for (v, variant) in (0..=u8::MAX).filter_map(|v| f(v).map(|n| (v, n))) {
+        writeln!(s, "    {:?} = {}", variant, v).unwrap();
+    }
+}
+
+/// Python string literal
+fn py_str(v: &str) -> String {
+    let mut s = String::from("\"");
+    for c in v.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            c if c.is_control() => write!(s, "\\u{:04x}", c as u32).unwrap(),
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+    s
+}
+
+/// Docstring body lines, safe inside `"""`
+fn doc_lines(text: &str) -> Vec<String> {
+    text.lines().map(|l| l.replace('\\', "\\\\").replace("\"\"\"", "\\\"\\\"\\\"")).collect()
+}
+
+fn py_hint(ty: TypeTag) -> &'static str {
+    match ty {
+        TypeTag::UNIT => "None",
+        TypeTag::BOOL => "bool",
+        TypeTag::STR => "str",
+        TypeTag::BYTES => "bytes",
+        _ => "int",
+    }
+}
+
+fn accessors(s: &mut String, reg: &Register, section_doc: Option<&str>) {
+    let path = "/".to_string() + &reg.path.join("/");
+    let name = reg.path.join("_");
+    let MetaDesc{w, r, w_plvl, r_plvl, ..} = reg.meta;
+    let hint = py_hint(reg.ty);
+    let access = match (r, w) {
+        (true, true) => "RW",
+        (true, false) => "RO",
+        _ => "WO",
+    };
+
+    let mut doc = doc_lines(&format!("`{}`, {:?}, {}", path, reg.ty, access));
+    for text in reg.doc.as_deref().into_iter().chain(section_doc) {
+        doc.push(String::new());
+        doc.extend(doc_lines(text));
+    }
+    let docstring = |s: &mut String, lvl: PrivLvl| {
+        let mut lines = doc.clone();
+        if lvl != PrivLvl::NORMAL_LVL {
+            lines.push(String::new());
+            lines.push(format!("Needs {:?}", lvl));
+        }
+        if lines.len() == 1 {
+            writeln!(s, "        \"\"\"{}\"\"\"", lines[0]).unwrap();
+            return;
+        }
+        writeln!(s, "        \"\"\"{}", lines[0]).unwrap();
+        for l in &lines[1..] {
+            if l.is_empty() {
+                s.push('\n');
+            } else {
+                writeln!(s, "        {}", l).unwrap();
+            }
+        }
+        s.push_str("        \"\"\"\n");
+    };
+
+    if r {
+        writeln!(s, "\n    def {}_read(self) -> {}:", name, hint).unwrap();
+        docstring(s, r_plvl);
+        writeln!(s, "        return self.read_as({}, TypeTag.{:?})", py_str(&path), reg.ty).unwrap();
+    }
+    if w {
+        if reg.ty == TypeTag::UNIT {
+            writeln!(s, "\n    def {}_write(self) -> None:", name).unwrap();
+            docstring(s, w_plvl);
+            writeln!(s, "        self.write({}, TypeTag.UNIT)", py_str(&path)).unwrap();
+        } else {
+            writeln!(s, "\n    def {}_write(self, v: {}) -> None:", name, hint).unwrap();
+            docstring(s, w_plvl);
+            if let Some(check) = range_check(reg) {
+                writeln!(s, "        if not ({}):\n            raise RangeError(v)", check).unwrap();
+            }
+            writeln!(s, "        self.write({}, TypeTag.{:?}, v)", py_str(&path), reg.ty).unwrap();
+        }
+    }
+}
+
+/// Condition over written value `v`, like the Rust client check
+fn range_check(reg: &Register) -> Option<String> {
+    let Constraints{min, max, maxlen, oneof} = &reg.constraints;
+    let mut checks = Vec::new();
+    match (min, max) {
+        (Some(min), Some(max)) => checks.push(format!("{} <= v <= {}", min, max)),
+        (Some(min), None) => checks.push(format!("{} <= v", min)),
+        (None, Some(max)) => checks.push(format!("v <= {}", max)),
+        (None, None) => (),
+    }
+    if let Some(n) = maxlen {
+        checks.push(format!("len(encode_value(TypeTag.{:?}, v)) <= {}", reg.ty, n));
+    }
+    if !oneof.is_empty() {
+        let alts: Vec<String> = oneof.iter().map(|lit| match lit {
+            Literal::Bool(true) => "True".to_string(),
+            Literal::Bool(false) => "False".to_string(),
+            Literal::Int(i) => i.to_string(),
+            Literal::Str(s) => py_str(s),
+            Literal::Bytes(b) => format!("bytes({:?})", b),
+        }).collect();
+        checks.push(format!("v in ({},)", alts.join(", ")));
+    }
+    if checks.is_empty() { None } else { Some(checks.join(" and ")) }
+}
+
+/// Scheme independent part: errors, frames and untyped client
+const FRAMING: &str = r#"
+
+class ProtocolError(Exception):
+    pass
+
+
+class ParseError(ProtocolError):
+    """Malformed frame, like `ParserError`"""
+
+
+class NeedMoreData(ParseError):
+    """Frame is not complete yet"""
+
+
+class AnswerError(ProtocolError):
+    """Device answered with `code` instead of success
+
+    `detail` is the answer value: U16 code, STR message, or BYTES of code
+    followed by message for ERR_CUSTOM.
+    """
+
+    def __init__(self, code, detail=None):
+        super().__init__(code, detail)
+        self.code = code
+        self.detail = detail
+
+    @property
+    def custom(self):
+        """`CustomErr` of ERR_CUSTOM answer, or its raw code if unknown"""
+        if self.code != AnswerCode.ERR_CUSTOM:
+            return None
+        if isinstance(self.detail, int):
+            code = self.detail
+        elif isinstance(self.detail, bytes) and len(self.detail) >= 2:
+            code = struct.unpack("=H", self.detail[:2])[0]
+        else:
+            return None
+        try:
+            return CustomErr(code)
+        except ValueError:
+            return code
+
+
+class TypeMismatch(ProtocolError):
+    """Answer payload is not of the register type"""
+
+
+class RangeError(ProtocolError, ValueError):
+    """Value breaks register constraints, request isn't sent"""
+
+
+# Fixed size types, numbers are in native byte order
+_FMT = {
+    TypeTag.BOOL: "=?",
+    TypeTag.I32: "=i",
+    TypeTag.I16: "=h",
+    TypeTag.I8: "=b",
+    TypeTag.U32: "=I",
+    TypeTag.U16: "=H",
+    TypeTag.U8: "=B",
+}
+
+
+def encode_value(ty, value=None):
+    """Payload bytes of `value` of type `ty`"""
+    ty = TypeTag(ty)
+    if ty == TypeTag.UNIT:
+        return b""
+    if ty == TypeTag.STR:
+        return value.encode("utf-8")
+    if ty == TypeTag.BYTES:
+        return bytes(value)
+    return struct.pack(_FMT[ty], value)
+
+
+def decode_value(ty, payload):
+    """Value of type `ty` from payload bytes"""
+    try:
+        ty = TypeTag(ty)
+    except ValueError:
+        raise ParseError("bad type id {}".format(ty))
+    try:
+        if ty == TypeTag.UNIT:
+            if payload:
+                raise ParseError("UNIT with payload")
+            return None
+        if ty == TypeTag.STR:
+            return bytes(payload).decode("utf-8")
+        if ty == TypeTag.BYTES:
+            return bytes(payload)
+        return struct.unpack(_FMT[ty], payload)[0]
+    except (struct.error, UnicodeDecodeError) as e:
+        raise ParseError("bad {} value: {}".format(ty.name, e))
+
+
+def encode_request(code, path, ty=TypeTag.UNIT, value=None):
+    """Request frame, like `RequestBuilder`"""
+    path_b = path.encode("utf-8")
+    payload = encode_value(ty, value)
+    if len(path_b) > MAX_PATH_SZ:
+        raise ValueError("path too big: {}".format(len(path_b)))
+    if len(payload) > MAX_PAYLOAD_SZ:
+        raise ValueError("payload too big: {}".format(len(payload)))
+    header = bytes([SIGN, len(path_b), len(payload), int(code), int(ty)])
+    return header + path_b + payload
+
+
+def frame_size(buf):
+    """Size of frame starting `buf`, once its header is there"""
+    if len(buf) < HEADER_SZ:
+        raise NeedMoreData()
+    return HEADER_SZ + buf[1] + buf[2]
+
+
+def decode_frame(buf):
+    """`(code, path, ty, value, size)` of answer frame starting `buf`, like `ParseMsg`
+
+    Signed frames can't be checked without frame key and are refused.
+    """
+    if len(buf) < HEADER_SZ:
+        raise NeedMoreData()
+    sign, path_sz, payload_sz, code, ty = buf[:HEADER_SZ]
+    if sign == SIGN_AUTH:
+        raise ParseError("signed frame, no frame key to check it")
+    if sign != SIGN:
+        raise ParseError("bad header")
+    if path_sz > MAX_PATH_SZ:
+        raise ParseError("bad path size")
+    size = HEADER_SZ + path_sz + payload_sz
+    if len(buf) < size:
+        raise NeedMoreData()
+    try:
+        code = AnswerCode(code)
+    except ValueError:
+        raise ParseError("bad code {}".format(code))
+    path = bytes(buf[HEADER_SZ:HEADER_SZ + path_sz]).decode("utf-8", "replace")
+    value = decode_value(ty, buf[HEADER_SZ + path_sz:size])
+    return code, path, TypeTag(ty), value, size
+
+
+class StreamTransport:
+    """Frames over byte stream with `write(bytes)` and `read(n)`"""
+
+    def __init__(self, stream):
+        self.stream = stream
+
+    def _read(self, n):
+        buf = b""
+        while len(buf) < n:
+            chunk = self.stream.read(n - len(buf))
+            if not chunk:
+                raise ProtocolError("stream closed or timed out")
+            buf += chunk
+        return buf
+
+    def exchange(self, frame):
+        self.stream.write(frame)
+        if hasattr(self.stream, "flush"):
+            self.stream.flush()
+        header = self._read(HEADER_SZ)
+        size = header[1] + header[2]
+        # Trailer of signed frame is taken too, so the next answer starts clean
+        if header[0] == SIGN_AUTH:
+            size += FRAME_TRAILER_SZ
+        return header + self._read(size)
+
+
+class Client:
+    """Requests over `transport`, failed answers raise `AnswerError`"""
+
+    def __init__(self, transport):
+        self.transport = transport
+
+    def request(self, code, path, ty=TypeTag.UNIT, value=None):
+        """`(ty, value)` of successful answer"""
+        ans = self.transport.exchange(encode_request(code, path, ty, value))
+        ans_code, _, ans_ty, ans_value, _ = decode_frame(ans)
+        if ans_code != int(code):
+            raise AnswerError(ans_code, ans_value)
+        return ans_ty, ans_value
+
+    def read(self, path):
+        return self.request(RequestCode.READ, path)
+
+    def read_as(self, path, ty):
+        """READ checked against register type"""
+        ans_ty, value = self.read(path)
+        if ans_ty != ty:
+            raise TypeMismatch(ans_ty)
+        return value
+
+    def write(self, path, ty, value=None):
+        self.request(RequestCode.WRITE, path, ty, value)
+"#;
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::TmpDir;
+
+    /// Talks to canned answers given as hex, prints sent requests as hex
+    const PY_APP: &str = r#"
+import io
+import sys
+
+import regs
+
+answers = [bytes.fromhex(a) for a in sys.argv[1].split(",")]
+sent = []
+
+class Canned:
+    def exchange(self, frame):
+        sent.append(frame)
+        return answers[len(sent) - 1]
+
+class Stream:
+    def __init__(self, ans):
+        self.ans = io.BytesIO(ans)
+    def write(self, frame):
+        sent.append(frame)
+    def read(self, n):
+        return self.ans.read(n)
+
+c = regs.RegClient(Canned())
+c.ctrl_record_write(True)
+assert c.ctrl_record_read() is True
+assert c.build_compiler_read() == "cc"
+assert c.calib_ecg_k_read() == -5
+try:
+    c.io_file_start_write()
+    raise SystemExit("no error")
+except regs.AnswerError as e:
+    assert (e.code, e.custom) == (regs.AnswerCode.ERR_CUSTOM, regs.CustomErr.BUSY), e
+try:
+    c.survey_name_read()
+    raise SystemExit("no error")
+except regs.TypeMismatch as e:
+    pass
+
+for write, v in ((c.signal_ecgf_frq_write, 0), (c.survey_sex_write, "X"), (c.survey_name_write, "я" * 33)):
+    try:
+        write(v)
+        raise SystemExit("no error")
+    except regs.RangeError:
+        pass
+
+c = regs.RegClient(regs.StreamTransport(Stream(answers[len(sent)])))
+assert c.io_status_read() == 7
+
+# Signed answer is refused whole, the one after it is read fine
+class Unsent(Stream):
+    def write(self, frame):
+        pass
+status = answers[len(sent) - 1]
+signed = bytes([regs.SIGN_AUTH]) + status[1:] + bytes(regs.FRAME_TRAILER_SZ)
+t = regs.StreamTransport(Unsent(signed + status))
+assert t.exchange(b"") == signed
+try:
+    regs.decode_frame(signed)
+    raise SystemExit("no error")
+except regs.ParseError:
+    pass
+assert regs.decode_frame(t.exchange(b""))[3] == 7
+
+assert "Max line size 64" in regs.RegClient.io_store_line_read.__doc__
+assert "Эхо тест" in regs.RegClient.test_echo_write.__doc__
+assert "DEVEL_LVL" in regs.RegClient.ctrl_goto_loader_write.__doc__
+assert regs.decode_frame(answers[1])[:4] == (regs.AnswerCode.OK_READ, "/ctrl/record", regs.TypeTag.BOOL, True)
+print(",".join(f.hex() for f in sent))
+"#;
+
+    #[test]
+    fn python_client() {
+        use std::process::Command;
+        use ellocopo2::*;
+
+        let txt = std::fs::read_to_string("../scheme.json").unwrap();
+        let py = crate::generate_py(&txt).unwrap();
+
+        let dir = TmpDir::new("py");
+        std::fs::write(dir.join("regs.py"), &py.code).unwrap();
+        std::fs::write(dir.join("app.py"), PY_APP).unwrap();
+
+        let cases = [
+            (RequestCode::WRITE, "/ctrl/record", Value::BOOL(true), AnswerErr(AnswerCode::OK_WRITE, ErrDetail::None), Value::UNIT(())),
+            (RequestCode::READ, "/ctrl/record", Value::UNIT(()), AnswerErr(AnswerCode::OK_READ, ErrDetail::None), Value::BOOL(true)),
+            (RequestCode::READ, "/build/compiler", Value::UNIT(()), AnswerErr(AnswerCode::OK_READ, ErrDetail::None), Value::STR("cc")),
+            (RequestCode::READ, "/calib/ecg/k", Value::UNIT(()), AnswerErr(AnswerCode::OK_READ, ErrDetail::None), Value::I32(-5)),
+            (RequestCode::WRITE, "/io/file/start", Value::UNIT(()), AnswerErr(AnswerCode::ERR_CUSTOM, ErrDetail::CodeMsg(1, "busy")), Value::UNIT(())),
+            (RequestCode::READ, "/survey/name", Value::UNIT(()), AnswerErr(AnswerCode::OK_READ, ErrDetail::None), Value::U8(1)),
+            (RequestCode::READ, "/io/status", Value::UNIT(()), AnswerErr(AnswerCode::OK_READ, ErrDetail::None), Value::U32(7)),
+        ];
+        let mut requests = Vec::new();
+        let mut answers = Vec::new();
+        for (code, path, v, AnswerErr(ans, detail), ans_v) in cases.iter() {
+            let mut buf = [0u8; MAX_MSG_SZ];
+            let sz = RequestBuilder::new(&mut buf).code(*code).path(path).payload(*v).build().unwrap();
+            requests.push(hex(&buf[..sz]));
+            let mut b = AnswerBuilder::new(&mut buf);
+            match detail {
+                ErrDetail::None => b.code(*ans).payload(*ans_v),
+                _ => b.error(AnswerErr(*ans, *detail)),
+            };
+            let sz = b.build();
+            answers.push(hex(&buf[..sz]));
+        }
+
+        let out = Command::new("python3")
+            .current_dir(&*dir)
+            .arg("app.py")
+            .arg(answers.join(","))
+            .output()
+            .unwrap_or_else(|e| panic!("python3 is needed: {}", e));
+        assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+        assert_eq!(String::from_utf8(out.stdout).unwrap().trim(), requests.join(","));
+    }
+
+    fn hex(b: &[u8]) -> String {
+        b.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
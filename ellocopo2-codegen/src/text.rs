@@ -0,0 +1,394 @@
+//! Text syntax of the scheme, same tree as JSON with real comments
+//!
+//! ```text
+//! //! Device registers
+//! @protocol_version "1.0"
+//! @errors { busy: 1, no_media: 2 }
+//!
+//! ctrl {
+//!     record: bool
+//!     /// Goes to `@doc` of the register below
+//!     event: [u8] @access WO
+//! }
+//!
+//! conf @access RW @txn true {
+//!     time: u32 @default 60 @min 1   // plain comment
+//! }
+//!
+//! // Section array
+//! channel[4] @use adc {}
+//! ```
+//!
+//! `//!` lines at the start are the root `@doc`. `name: type @key value ...`
+//! is a register, `name @key value ... { ... }` is a section. Annotation values are strings, numbers, `true`/`false`, bare
+//! words like `RW`, `[...]` arrays and `{ key: value }` objects. Names that
+//! aren't identifiers are quoted.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value as JsonValue};
+
+use crate::diag::{pointer, Diagnostic};
+
+/// Line and column of every key, by JSON pointer
+pub type Positions = HashMap<String, (usize, usize)>;
+
+/// Scheme as JSON value along with position of its keys
+pub fn parse(src: &str) -> Result<(JsonValue, Positions), Diagnostic> {
+    let mut p = Parser { tokens: lex(src)?, i: 0, positions: HashMap::new() };
+    let mut root = Map::new();
+    let mut doc = Vec::new();
+    while let Some(Token { kind: Kind::InnerDoc(line), .. }) = p.tokens.get(p.i) {
+        doc.push(line.clone());
+        p.i += 1;
+    }
+    if !doc.is_empty() {
+        root.insert(ANNOTATION_DOC.to_string(), JsonValue::String(doc.join("\n")));
+    }
+    p.body(&[], &mut root)?;
+    if let Some(t) = p.tokens.get(p.i) {
+        return Err(t.error("Unexpected `}`"));
+    }
+    Ok((JsonValue::Object(root), p.positions))
+}
+
+/// Line and column of the key `path` points to
+pub fn locate(src: &str, path: &str) -> Option<(usize, usize)> {
+    parse(src).ok()?.1.get(path).copied()
+}
+
+const ANNOTATION_DOC: &str = "@doc";
+const ANNOTATION_TYPE: &str = "@type";
+
+#[derive(Clone, Debug, PartialEq)]
+enum Kind {
+    Ident(String),
+    Str(String),
+    Int(i64),
+    Annotation(String),
+    /// `///` line
+    Doc(String),
+    /// `//!` line
+    InnerDoc(String),
+    Punct(char),
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: Kind,
+    pos: (usize, usize),
+}
+
+impl Token {
+    fn error(&self, msg: impl Into<String>) -> Diagnostic {
+        Diagnostic { pos: Some(self.pos), ..Diagnostic::error("", msg) }
+    }
+}
+
+fn lex(src: &str) -> Result<Vec<Token>, Diagnostic> {
+    let chars: Vec<char> = src.chars().collect();
+    let (mut i, mut line, mut col) = (0, 1, 1);
+    let mut tokens = Vec::new();
+    let err = |pos, msg: &str| Diagnostic { pos: Some(pos), ..Diagnostic::error("", msg) };
+    let word = |c: char| c.is_alphanumeric() || c == '_';
+
+    while i < chars.len() {
+        let pos = (line, col);
+        let start = i;
+        let c = chars[i];
+        let kind = match c {
+            '\n' => {
+                i += 1;
+                line += 1;
+                col = 1;
+                continue;
+            }
+            c if c.is_whitespace() => {
+                i += 1;
+                col += 1;
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let doc = |prefix: &str| {
+                    let rest = &text[prefix.len()..];
+                    rest.strip_prefix(' ').unwrap_or(rest).trim_end().to_string()
+                };
+                col += i - start;
+                if text.starts_with("///") && !text.starts_with("////") {
+                    Kind::Doc(doc("///"))
+                } else if text.starts_with("//!") {
+                    Kind::InnerDoc(doc("//!"))
+                } else {
+                    continue;
+                }
+            }
+            '"' => {
+                let mut s = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None | Some('\n') => return Err(err(pos, "Unterminated string")),
+                        Some('"') => break,
+                        Some('\\') => {
+                            s.push(match chars.get(i + 1) {
+                                Some('n') => '\n',
+                                Some('t') => '\t',
+                                Some('"') => '"',
+                                Some('\\') => '\\',
+                                _ => return Err(err((line, col + i - start), "Unknown escape")),
+                            });
+                            i += 2;
+                        }
+                        Some(c) => {
+                            s.push(*c);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+                col += i - start;
+                Kind::Str(s)
+            }
+            '@' => {
+                i += 1;
+                while i < chars.len() && word(chars[i]) {
+                    i += 1;
+                }
+                col += i - start;
+                if i == start + 1 {
+                    return Err(err(pos, "Annotation name expected after `@`"));
+                }
+                Kind::Annotation(chars[start..i].iter().collect())
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                i += 1;
+                while i < chars.len() && word(chars[i]) {
+                    i += 1;
+                }
+                col += i - start;
+                let text: String = chars[start..i].iter().collect();
+                let (neg, digits) = match text.strip_prefix('-') {
+                    Some(d) => (true, d),
+                    None => (false, text.as_str()),
+                };
+                let v = match digits.strip_prefix("0x") {
+                    Some(hex) => i64::from_str_radix(hex, 16),
+                    None => digits.parse(),
+                }.map_err(|_| err(pos, &format!("Bad number: {}", text)))?;
+                Kind::Int(if neg { -v } else { v })
+            }
+            c if word(c) => {
+                while i < chars.len() && word(chars[i]) {
+                    i += 1;
+                }
+                col += i - start;
+                Kind::Ident(chars[start..i].iter().collect())
+            }
+            '{' | '}' | '[' | ']' | '(' | ')' | ':' | ',' => {
+                i += 1;
+                col += 1;
+                Kind::Punct(c)
+            }
+            c => return Err(err(pos, &format!("Unexpected character {:?}", c))),
+        };
+        tokens.push(Token { kind, pos });
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    i: usize,
+    positions: Positions,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Kind> {
+        self.tokens.get(self.i).map(|t| &t.kind)
+    }
+
+    fn next(&mut self, what: &str) -> Result<Token, Diagnostic> {
+        match self.tokens.get(self.i) {
+            Some(t) => {
+                self.i += 1;
+                Ok(t.clone())
+            }
+            None => Err(self.eof(what)),
+        }
+    }
+
+    fn eof(&self, what: &str) -> Diagnostic {
+        let pos = self.tokens.last().map(|t| t.pos);
+        Diagnostic { pos, ..Diagnostic::error("", format!("{} expected, found end of scheme", what)) }
+    }
+
+    fn punct(&mut self, c: char) -> Result<(), Diagnostic> {
+        let t = self.next(&format!("`{}`", c))?;
+        match t.kind {
+            Kind::Punct(p) if p == c => Ok(()),
+            _ => Err(t.error(format!("`{}` expected", c))),
+        }
+    }
+
+    fn insert(&mut self, map: &mut Map<String, JsonValue>, path: &[String], key: String, v: JsonValue, t: &Token) -> Result<(), Diagnostic> {
+        let at = pointer(path, Some(&key));
+        if map.contains_key(&key) {
+            return Err(t.error(format!("Duplicate key {}", at)));
+        }
+        self.positions.insert(at, t.pos);
+        map.insert(key, v);
+        Ok(())
+    }
+
+    /// Items of section at `path` up to its `}` or end of scheme
+    fn body(&mut self, path: &[String], map: &mut Map<String, JsonValue>) -> Result<(), Diagnostic> {
+        let mut doc: Vec<String> = Vec::new();
+        loop {
+            let t = match self.tokens.get(self.i) {
+                None | Some(Token { kind: Kind::Punct('}'), .. }) => break,
+                Some(t) => t.clone(),
+            };
+            self.i += 1;
+            match t.kind.clone() {
+                Kind::Doc(line) => {
+                    doc.push(line);
+                    continue;
+                }
+                Kind::Annotation(key) if doc.is_empty() => {
+                    let v = self.value(&pointer(path, Some(&key)))?;
+                    self.insert(map, path, key, v, &t)?;
+                }
+                Kind::Ident(name) | Kind::Str(name) => {
+                    let name = self.array_len(name)?;
+                    let mut node_path = path.to_vec();
+                    node_path.push(name.clone());
+                    let node = self.node(&node_path, std::mem::take(&mut doc))?;
+                    self.insert(map, path, name, node, &t)?;
+                }
+                _ if !doc.is_empty() => return Err(t.error("Doc comment must precede register or section")),
+                Kind::InnerDoc(_) => return Err(t.error("`//!` doc comments belong to the start of scheme")),
+                _ => return Err(t.error("Register, section or annotation expected")),
+            }
+        }
+        if !doc.is_empty() {
+            return Err(self.eof("Register or section"));
+        }
+        Ok(())
+    }
+
+    /// `name[N]` of section array kept as JSON key
+    fn array_len(&mut self, name: String) -> Result<String, Diagnostic> {
+        if self.peek() != Some(&Kind::Punct('[')) {
+            return Ok(name);
+        }
+        self.i += 1;
+        let t = self.next("Array length")?;
+        let len = match t.kind {
+            Kind::Int(len) => len,
+            _ => return Err(t.error("Array length expected")),
+        };
+        self.punct(']')?;
+        Ok(format!("{}[{}]", name, len))
+    }
+
+    /// Register or section after its name
+    fn node(&mut self, path: &[String], doc: Vec<String>) -> Result<JsonValue, Diagnostic> {
+        let mut map = Map::new();
+        let register = self.peek() == Some(&Kind::Punct(':'));
+        if register {
+            self.i += 1;
+            let ty = self.ty()?;
+            map.insert(ANNOTATION_TYPE.to_string(), JsonValue::String(ty));
+        }
+        if !doc.is_empty() {
+            map.insert(ANNOTATION_DOC.to_string(), JsonValue::String(doc.join("\n")));
+        }
+        while let Some(Kind::Annotation(key)) = self.peek().cloned() {
+            let t = self.next("Annotation")?;
+            let v = self.value(&pointer(path, Some(&key)))?;
+            self.insert(&mut map, path, key, v, &t)?;
+        }
+        if register {
+            // Plain `name: type` stays a type string, like in JSON
+            if map.len() == 1 {
+                return Ok(map.remove(ANNOTATION_TYPE).unwrap());
+            }
+            return Ok(JsonValue::Object(map));
+        }
+        self.punct('{')?;
+        self.body(path, &mut map)?;
+        self.punct('}')?;
+        Ok(JsonValue::Object(map))
+    }
+
+    fn ty(&mut self) -> Result<String, Diagnostic> {
+        let t = self.next("Type")?;
+        match t.kind {
+            Kind::Ident(ty) => Ok(ty),
+            Kind::Punct('(') => {
+                self.punct(')')?;
+                Ok("()".to_string())
+            }
+            Kind::Punct('[') => {
+                let t = self.next("Type")?;
+                let ty = match t.kind {
+                    Kind::Ident(ty) => ty,
+                    _ => return Err(t.error("Type expected")),
+                };
+                self.punct(']')?;
+                Ok(format!("[{}]", ty))
+            }
+            _ => Err(t.error("Type expected")),
+        }
+    }
+
+    /// Annotation value at JSON pointer `at`
+    fn value(&mut self, at: &str) -> Result<JsonValue, Diagnostic> {
+        let t = self.next("Value")?;
+        Ok(match t.kind {
+            Kind::Str(s) => JsonValue::String(s),
+            Kind::Int(i) => JsonValue::from(i),
+            Kind::Ident(w) if w == "true" => JsonValue::Bool(true),
+            Kind::Ident(w) if w == "false" => JsonValue::Bool(false),
+            Kind::Ident(w) => JsonValue::String(w),
+            Kind::Punct('[') => {
+                let mut items = Vec::new();
+                while self.peek() != Some(&Kind::Punct(']')) {
+                    items.push(self.value(at)?);
+                    if self.peek() != Some(&Kind::Punct(']')) {
+                        self.punct(',')?;
+                    }
+                }
+                self.i += 1;
+                JsonValue::Array(items)
+            }
+            Kind::Punct('{') => {
+                let mut map = Map::new();
+                while self.peek() != Some(&Kind::Punct('}')) {
+                    let k = self.next("Key")?;
+                    let key = match &k.kind {
+                        Kind::Ident(key) | Kind::Str(key) => key.clone(),
+                        _ => return Err(k.error("Key expected")),
+                    };
+                    self.punct(':')?;
+                    let key_at = format!("{}/{}", at, key);
+                    let v = self.value(&key_at)?;
+                    if map.insert(key, v).is_some() {
+                        return Err(k.error(format!("Duplicate key {}", key_at)));
+                    }
+                    self.positions.insert(key_at, k.pos);
+                    if self.peek() != Some(&Kind::Punct('}')) {
+                        self.punct(',')?;
+                    }
+                }
+                self.i += 1;
+                JsonValue::Object(map)
+            }
+            _ => return Err(t.error("Value expected")),
+        })
+    }
+}
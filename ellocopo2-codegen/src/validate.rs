@@ -0,0 +1,144 @@
+//! Semantic checks of parsed scheme, run before generation
+//!
+//! Parser stops at the first malformed entry, here all problems of
+//! well formed scheme are collected at once.
+
+use std::collections::HashMap;
+
+use ellocopo2::MAX_PATH_SZ;
+
+use crate::diag::{Diagnostic, pointer};
+use crate::gen::camel_case_names;
+use crate::parser::*;
+
+pub fn validate(schema: &Schema) -> Vec<Diagnostic> {
+    let mut errs = Vec::new();
+    // Section enums share `msg` module with the root one
+    let mut enums = HashMap::new();
+    if let DslTree::SectionV(root) = &schema.tree {
+        enums.insert(root.name.clone(), String::new());
+    }
+
+    visit_tree(&schema.tree, false, false, &mut enums, &mut errs);
+    path_idents(&schema.tree, &mut errs);
+    error_names(&schema.errors, &mut errs);
+    errs
+}
+
+fn err(path: &[String], msg: String) -> Diagnostic {
+    Diagnostic::error(pointer(path, None), msg)
+}
+
+/// `name` turned into CamelCase identifier, if it is one
+fn ident(name: &str) -> Result<String, String> {
+    let id = camel_case_names(name);
+    match syn::parse_str::<syn::Ident>(&id) {
+        Ok(_) => Ok(id),
+        Err(_) => Err(format!("Name {:?} doesn't make valid Rust identifier: {:?}", name, id)),
+    }
+}
+
+/// `fast_w` is set under writable `@fast` section, which is reported once,
+/// `copy` under array elements after the first one: they repeat its names
+/// and enums, only their own paths are checked
+fn visit_tree(node: &DslTree, fast_w: bool, copy: bool, enums: &mut HashMap<String, String>, errs: &mut Vec<Diagnostic>) {
+    match node {
+        DslTree::SectionV(section) => {
+            let Section{path, name, meta, children, index, ..} = section;
+            let here = meta.fast && meta.w;
+            let copy = copy || index.unwrap_or(0) > 0;
+            if !path.is_empty() && !copy {
+                if here && !fast_w {
+                    errs.push(err(path, "@fast section can't have write access".to_string()));
+                }
+                match ident(name) {
+                    // Fast sections get no enum
+                    Ok(id) if !meta.fast => {
+                        let at = pointer(path, None);
+                        if let Some(other) = enums.get(&id) {
+                            let other = if other.is_empty() { "root" } else { other.as_str() };
+                            errs.push(err(path, format!("Section enum {} is already generated for {}", id, other)));
+                        } else {
+                            enums.insert(id, at);
+                        }
+                    }
+                    Ok(_) => (),
+                    Err(e) => errs.push(err(path, e)),
+                }
+            }
+
+            if !copy {
+                siblings(children, errs);
+            }
+            for c in children {
+                visit_tree(c, fast_w || here, copy, enums, errs);
+            }
+        }
+        DslTree::RegisterV(register) => {
+            let Register{path, name, meta, ..} = register;
+            if !copy {
+                if meta.fast && meta.w && !fast_w {
+                    errs.push(err(path, "@fast register can't have write access".to_string()));
+                }
+                if let Err(e) = ident(name) {
+                    errs.push(err(path, e));
+                }
+            }
+            let sz = path.iter().map(|s| s.len() + REGISTER_PATH_DELIMETR.len()).sum::<usize>();
+            if sz > MAX_PATH_SZ {
+                errs.push(err(path, format!("Path is {} bytes long, MAX_PATH_SZ is {}", sz, MAX_PATH_SZ)));
+            }
+        }
+    }
+}
+
+/// Variants of one section enum must differ after CamelCase
+fn siblings(children: &[DslTree], errs: &mut Vec<Diagnostic>) {
+    let mut seen: HashMap<String, &String> = HashMap::new();
+    for c in children {
+        let (path, name) = match c {
+            DslTree::SectionV(s) if s.index.unwrap_or(0) > 0 => continue,
+            DslTree::SectionV(s) => (&s.path, &s.name),
+            DslTree::RegisterV(r) => (&r.path, &r.name),
+        };
+        let id = camel_case_names(name);
+        match seen.get(&id) {
+            Some(other) => errs.push(err(path, format!("{:?} and {:?} both become {}", other, name, id))),
+            None => { seen.insert(id, name); }
+        }
+    }
+}
+
+/// Handler and client methods and `@txn` fields are named after register path
+fn path_idents(tree: &DslTree, errs: &mut Vec<Diagnostic>) {
+    let mut seen: HashMap<String, String> = HashMap::new();
+    tree.visit_regs(&mut |reg| {
+        let name = reg.path.join("_");
+        let method = name.clone() + "_read";
+        if syn::parse_str::<syn::Ident>(&method).is_err() {
+            errs.push(err(&reg.path, format!("Method name isn't valid Rust identifier: {:?}", method)));
+        } else if reg.meta.txn && syn::parse_str::<syn::Ident>(&name).is_err() {
+            errs.push(err(&reg.path, format!("@txn field name isn't valid Rust identifier: {:?}", name)));
+        }
+        let at = pointer(&reg.path, None);
+        match seen.get(&name) {
+            Some(other) => errs.push(err(&reg.path, format!("Path name {} is already used by {}", name, other))),
+            None => { seen.insert(name, at); }
+        }
+    });
+}
+
+/// `@errors` names are variants of generated `CustomErr`
+fn error_names(errors: &[ErrorDesc], errs: &mut Vec<Diagnostic>) {
+    let mut seen: HashMap<String, &String> = HashMap::new();
+    for e in errors {
+        let path = ["@errors".to_string(), e.name.clone()];
+        match ident(&e.name) {
+            Ok(id) => match seen.get(&id) {
+                Some(other) => errs.push(err(&path, format!("{:?} and {:?} both become {}", other, e.name, id))),
+                None => { seen.insert(id, &e.name); }
+            },
+            Err(msg) => errs.push(err(&path, msg)),
+        }
+    }
+}
@@ -0,0 +1,430 @@
+//! Challenge-response elevation of session `PrivLvl`
+//!
+//! Host asks for a level with `CHALLENGE` and gets fresh nonce back,
+//! then proves it knows the shared secret by sending `AUTH` with
+//! HMAC-SHA256 over the nonce and the level. Device grants the level
+//! for `timeout` ticks, `AUTH` with UNIT payload drops it earlier.
+//!
+//! Nonces are derived from the secret, `seed` and a counter, so device
+//! needs no RNG while serving. Seed has to differ from boot to boot,
+//! otherwise nonces and so responses to them repeat.
+//!
+//! `FrameAuth` signs and checks single frames with a key known to both
+//! sides. Counters of both directions start over with each `FrameAuth`,
+//! so frames captured before restart pass again, unless the key changes
+//! or counters are kept in `Storage` with `device_stored`/`host_stored`.
+//! Both counters are stored in blocks to spare flash: sending counter is
+//! stored `COUNTER_RESERVE` ahead and continues from there, peer only needs
+//! it to grow. Device stores received counter `COUNTER_RESERVE` ahead too,
+//! before the frame that runs out of the reservation is acted upon, and
+//! refuses everything up to the stored value after restart. Host keeps
+//! sending below that, so it calls `resync` once its signed request is
+//! answered with `ERR_AUTH`. Host records every received counter, device
+//! has no way to skip its own ahead for it.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::parser::ParserError;
+use crate::persist::Storage;
+use crate::priv_lvl::PrivLvl;
+use crate::protocol::*;
+
+pub const NONCE_SZ: usize = 16;
+pub const MAC_SZ: usize = 32;
+pub const TAG_SZ: usize = 8;
+/// Frames signed between stores of `FrameAuth` sending counter
+pub const COUNTER_RESERVE: u32 = 1024;
+// Stored counters: tx and rx, little endian
+const COUNTERS_SZ: usize = 8;
+
+// Direction bytes under the tag, request can't be passed off as answer
+const DIR_REQUEST: u8 = b'Q';
+const DIR_ANSWER: u8 = b'A';
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn keyed(secret: &[u8]) -> HmacSha256 {
+    HmacSha256::new_from_slice(secret).expect("HMAC takes key of any size")
+}
+
+fn challenge_mac(secret: &[u8], nonce: &[u8], lvl: PrivLvl) -> HmacSha256 {
+    let mut m = keyed(secret);
+    m.update(nonce);
+    m.update(&[lvl as u8]);
+    m
+}
+
+/// Response to challenge `nonce` issued for `lvl`, computed by host
+pub fn mac(secret: &[u8], nonce: &[u8], lvl: PrivLvl) -> [u8; MAC_SZ] {
+    challenge_mac(secret, nonce, lvl).finalize().into_bytes().into()
+}
+
+/// Device side authentication state
+pub struct Auth<'s> {
+    secret: &'s [u8],
+    seed: [u8; NONCE_SZ],
+    counter: u32,
+    timeout: u32,
+    // Nonce and level of the challenge waiting for response
+    challenge: Option<([u8; NONCE_SZ], PrivLvl)>,
+    // Granted level and tick it was granted at
+    session: Option<(PrivLvl, u32)>,
+}
+
+impl<'s> Auth<'s> {
+    /// `timeout` is in the ticks later passed as `now`
+    pub fn new(secret: &'s [u8], seed: [u8; NONCE_SZ], timeout: u32) -> Self {
+        Self {
+            secret,
+            seed,
+            counter: 0,
+            timeout,
+            challenge: None,
+            session: None,
+        }
+    }
+
+    /// Issue nonce for `lvl`, replaces pending challenge
+    pub fn challenge(&mut self, lvl: PrivLvl) -> Result<[u8; NONCE_SZ], AnswerCode> {
+        if lvl == PrivLvl::NORMAL_LVL || lvl == PrivLvl::UNDEF_LVL {
+            return Err(AnswerCode::ERR_AUTH);
+        }
+        let mut m = keyed(self.secret);
+        m.update(&self.seed);
+        m.update(&self.counter.to_le_bytes());
+        self.counter = self.counter.wrapping_add(1);
+
+        let mut nonce = [0u8; NONCE_SZ];
+        nonce.copy_from_slice(&m.finalize().into_bytes()[..NONCE_SZ]);
+        self.challenge = Some((nonce, lvl));
+        Ok(nonce)
+    }
+
+    /// Check response to pending challenge, each nonce gets single attempt
+    pub fn respond(&mut self, mac: &[u8], now: u32) -> Result<PrivLvl, AnswerCode> {
+        let (nonce, lvl) = self.challenge.take().ok_or(AnswerCode::ERR_AUTH)?;
+        challenge_mac(self.secret, &nonce, lvl)
+            .verify_slice(mac)
+            .map_err(|_| AnswerCode::ERR_AUTH)?;
+        self.session = Some((lvl, now));
+        Ok(lvl)
+    }
+
+    /// Drop session and pending challenge
+    pub fn logout(&mut self) {
+        self.challenge = None;
+        self.session = None;
+    }
+
+    /// Session level at `now`, `NORMAL_LVL` once timed out
+    pub fn lvl(&mut self, now: u32) -> PrivLvl {
+        match self.session {
+            Some((lvl, since)) if now.wrapping_sub(since) < self.timeout => lvl,
+            _ => {
+                self.session = None;
+                PrivLvl::NORMAL_LVL
+            }
+        }
+    }
+}
+
+/// Per-frame authentication with truncated HMAC and counters
+#[derive(Clone)]
+pub struct FrameAuth<S = ()> {
+    key: HmacSha256,
+    tx_dir: u8,
+    rx_dir: u8,
+    tx: u32,
+    rx: u32,
+    // Counters recorded in `storage`, start values after restart
+    tx_mark: u32,
+    rx_mark: u32,
+    // How far ahead of received counter `rx_mark` is recorded
+    rx_reserve: u32,
+    storage: S,
+}
+
+impl FrameAuth {
+    /// Device side, checks requests and signs answers
+    pub fn device(key: &[u8]) -> Self {
+        match Self::device_stored(key, ()) {
+            Ok(frames) => frames,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Host side, signs requests and checks answers
+    pub fn host(key: &[u8]) -> Self {
+        match Self::host_stored(key, ()) {
+            Ok(frames) => frames,
+            Err(e) => match e {},
+        }
+    }
+}
+
+impl<S: Storage> FrameAuth<S> {
+    /// Device side continuing counters kept in `storage`
+    pub fn device_stored(key: &[u8], storage: S) -> Result<Self, S::Error> {
+        Self::new(key, DIR_ANSWER, DIR_REQUEST, COUNTER_RESERVE, storage)
+    }
+
+    /// Host side continuing counters kept in `storage`
+    pub fn host_stored(key: &[u8], storage: S) -> Result<Self, S::Error> {
+        Self::new(key, DIR_REQUEST, DIR_ANSWER, 0, storage)
+    }
+
+    fn new(key: &[u8], tx_dir: u8, rx_dir: u8, rx_reserve: u32, mut storage: S) -> Result<Self, S::Error> {
+        let mut buf = [0u8; COUNTERS_SZ];
+        let (tx, rx) = match storage.load(&mut buf)? {
+            COUNTERS_SZ => (
+                u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+                u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]),
+            ),
+            _ => (0, 0),
+        };
+        Ok(Self {
+            key: keyed(key),
+            tx_dir,
+            rx_dir,
+            tx,
+            rx,
+            tx_mark: tx,
+            rx_mark: rx,
+            rx_reserve,
+            storage,
+        })
+    }
+
+    fn record(&mut self, tx_mark: u32, rx_mark: u32) -> Result<(), S::Error> {
+        let mut buf = [0u8; COUNTERS_SZ];
+        buf[..4].copy_from_slice(&tx_mark.to_le_bytes());
+        buf[4..].copy_from_slice(&rx_mark.to_le_bytes());
+        self.storage.store(&buf)?;
+        self.tx_mark = tx_mark;
+        self.rx_mark = rx_mark;
+        Ok(())
+    }
+
+    /// Skip sending counter past the block restarted peer may have reserved
+    pub fn resync(&mut self) {
+        self.tx = self.tx.saturating_add(COUNTER_RESERVE);
+    }
+
+    fn tag(&self, dir: u8, signed: &[u8]) -> HmacSha256 {
+        let mut m = self.key.clone();
+        m.update(&[dir]);
+        m.update(signed);
+        m
+    }
+
+    /// Sign frame of `sz` bytes at the start of `buf`, returns signed frame size
+    ///
+    /// Counter stops at `u32::MAX` or when it can't be stored, frames signed
+    /// after that are refused by the peer
+    pub fn sign(&mut self, buf: &mut [u8], sz: usize) -> usize {
+        assert!(buf.len() >= sz + FRAME_TRAILER_SZ, "FrameAuth, no room for trailer!");
+        let tx = self.tx.saturating_add(1);
+        if tx <= self.tx_mark || self.record(tx.saturating_add(COUNTER_RESERVE), self.rx_mark).is_ok() {
+            self.tx = tx;
+        }
+        buf[0] = SIGN_AUTH;
+        buf[sz..sz + 4].copy_from_slice(&self.tx.to_le_bytes());
+        let tag = self.tag(self.tx_dir, &buf[..sz + 4]).finalize().into_bytes();
+        buf[sz + 4..sz + FRAME_TRAILER_SZ].copy_from_slice(&tag[..TAG_SZ]);
+        sz + FRAME_TRAILER_SZ
+    }
+
+    /// Check signed `frame` ending with trailer, accepted counter becomes the last one
+    pub fn verify(&mut self, frame: &[u8]) -> Result<(), ParserError> {
+        if frame.len() < HEADER_SZ + FRAME_TRAILER_SZ || frame[0] != SIGN_AUTH {
+            return Err(ParserError::BadAuth);
+        }
+        let (signed, tag) = frame.split_at(frame.len() - TAG_SZ);
+        self.tag(self.rx_dir, signed)
+            .verify_truncated_left(tag)
+            .map_err(|_| ParserError::BadAuth)?;
+
+        let mut counter = [0u8; 4];
+        counter.copy_from_slice(&signed[signed.len() - 4..]);
+        let counter = u32::from_le_bytes(counter);
+        if counter <= self.rx {
+            return Err(ParserError::Replay);
+        }
+        // Recorded before the frame is acted upon, so it can't pass after restart
+        if counter > self.rx_mark {
+            self.record(self.tx_mark, counter.saturating_add(self.rx_reserve))
+                .map_err(|_| ParserError::Counter)?;
+        }
+        self.rx = counter;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"shared secret";
+
+    #[test]
+    fn challenge_response() {
+        let mut auth = Auth::new(SECRET, [7u8; NONCE_SZ], 1000);
+        assert_eq!(auth.lvl(0), PrivLvl::NORMAL_LVL);
+        assert_eq!(auth.challenge(PrivLvl::NORMAL_LVL), Err(AnswerCode::ERR_AUTH));
+        assert_eq!(auth.respond(&[0u8; MAC_SZ], 0), Err(AnswerCode::ERR_AUTH));
+
+        let nonce = auth.challenge(PrivLvl::SECUR_LVL).unwrap();
+        assert_eq!(auth.respond(&mac(SECRET, &nonce, PrivLvl::SECUR_LVL), 100), Ok(PrivLvl::SECUR_LVL));
+        assert_eq!(auth.lvl(1099), PrivLvl::SECUR_LVL);
+        assert_eq!(auth.lvl(1100), PrivLvl::NORMAL_LVL);
+        assert_eq!(auth.lvl(0), PrivLvl::NORMAL_LVL);
+
+        // Same response can't be replayed, nonces don't repeat
+        assert_eq!(auth.respond(&mac(SECRET, &nonce, PrivLvl::SECUR_LVL), 0), Err(AnswerCode::ERR_AUTH));
+        let next = auth.challenge(PrivLvl::SECUR_LVL).unwrap();
+        assert_ne!(next, nonce);
+
+        // Wrong secret, response for other level
+        assert_eq!(auth.respond(&mac(b"guess", &next, PrivLvl::SECUR_LVL), 0), Err(AnswerCode::ERR_AUTH));
+        let next = auth.challenge(PrivLvl::MODE1_LVL).unwrap();
+        assert_eq!(auth.respond(&mac(SECRET, &next, PrivLvl::DEVEL_LVL), 0), Err(AnswerCode::ERR_AUTH));
+        assert_eq!(auth.lvl(0), PrivLvl::NORMAL_LVL);
+
+        let next = auth.challenge(PrivLvl::DEVEL_LVL).unwrap();
+        assert_eq!(auth.respond(&mac(SECRET, &next, PrivLvl::DEVEL_LVL), u32::MAX), Ok(PrivLvl::DEVEL_LVL));
+        // Tick counter wraps
+        assert_eq!(auth.lvl(10), PrivLvl::DEVEL_LVL);
+        auth.logout();
+        assert_eq!(auth.lvl(10), PrivLvl::NORMAL_LVL);
+    }
+
+    #[test]
+    fn signed_frames() {
+        use crate::parser::{Msg, ParseMsg};
+        use crate::ty::Value;
+
+        let mut host = FrameAuth::host(SECRET);
+        let mut device = FrameAuth::device(SECRET);
+        let mut buf = [0u8; MAX_SIGNED_MSG_SZ];
+
+        let sz = RequestBuilder::new(&mut buf)
+            .code(RequestCode::WRITE)
+            .path("/ctrl/erase")
+            .build()
+            .unwrap();
+        let sz = host.sign(&mut buf, sz);
+        let frame = buf;
+        let Msg(code, path, v) = ParseMsg::new().try_parse_signed(&frame[..sz], &mut device).unwrap();
+        assert_eq!((code, path, v), (AnswerCode::OK_WRITE, "/ctrl/erase", Value::UNIT(())));
+
+        // Replayed
+        assert_eq!(ParseMsg::new().try_parse_signed(&frame[..sz], &mut device), Err(ParserError::Replay));
+
+        // Tampered path, tag and counter
+        let next = host.sign(&mut buf, sz - FRAME_TRAILER_SZ);
+        for pos in [HEADER_SZ + 1, next - 1, next - TAG_SZ - 1] {
+            let mut bad = buf;
+            bad[pos] ^= 0x01;
+            assert_eq!(ParseMsg::new().try_parse_signed(&bad[..next], &mut device), Err(ParserError::BadAuth));
+        }
+        // Unsigned, signed with other key
+        let mut unsigned = buf;
+        unsigned[0] = SIGN;
+        assert_eq!(ParseMsg::new().try_parse_signed(&unsigned[..next], &mut device), Err(ParserError::BadAuth));
+        let mut other = buf;
+        FrameAuth::host(b"other key").sign(&mut other, sz - FRAME_TRAILER_SZ);
+        assert_eq!(ParseMsg::new().try_parse_signed(&other[..next], &mut device), Err(ParserError::BadAuth));
+        // Request reflected back as answer
+        assert_eq!(ParseMsg::new().try_parse_signed(&buf[..next], &mut host), Err(ParserError::BadAuth));
+
+        assert!(ParseMsg::new().try_parse_signed(&buf[..next], &mut device).is_ok());
+    }
+
+    /// Counters in RAM standing in for flash, `fail` breaks stores
+    #[derive(Default)]
+    struct Cell {
+        data: [u8; COUNTERS_SZ],
+        sz: usize,
+        stores: usize,
+        fail: bool,
+    }
+
+    impl Storage for &mut Cell {
+        type Error = ();
+
+        fn load(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+            buf[..self.sz].copy_from_slice(&self.data[..self.sz]);
+            Ok(self.sz)
+        }
+
+        fn store(&mut self, image: &[u8]) -> Result<(), ()> {
+            if self.fail {
+                return Err(());
+            }
+            self.data.copy_from_slice(image);
+            self.sz = image.len();
+            self.stores += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn stored_counters() {
+        use crate::parser::ParseMsg;
+
+        let (mut host_cell, mut device_cell) = (Cell::default(), Cell::default());
+        let mut buf = [0u8; MAX_SIGNED_MSG_SZ];
+        let sz = RequestBuilder::new(&mut buf)
+            .code(RequestCode::WRITE)
+            .path("/ctrl/erase")
+            .build()
+            .unwrap();
+        let mut frames = [[0u8; MAX_SIGNED_MSG_SZ]; 3];
+        let mut signed = 0;
+        let mut device = FrameAuth::device_stored(SECRET, &mut device_cell).unwrap();
+        {
+            let mut host = FrameAuth::host_stored(SECRET, &mut host_cell).unwrap();
+            // More frames than one reservation of either counter covers
+            for i in 0..COUNTER_RESERVE as usize + 2 {
+                let mut frame = buf;
+                signed = host.sign(&mut frame, sz);
+                assert!(ParseMsg::new().try_parse_signed(&frame[..signed], &mut device).is_ok());
+                frames[i % 3] = frame;
+            }
+
+            // Freshly built after restart, captured frames are still refused
+            device = FrameAuth::device_stored(SECRET, device.storage).unwrap();
+            assert_eq!(device.storage.stores, 2);
+            for frame in &frames {
+                assert_eq!(ParseMsg::new().try_parse_signed(&frame[..signed], &mut device), Err(ParserError::Replay));
+            }
+            // Without stored counters they would pass
+            assert!(ParseMsg::new().try_parse_signed(&frames[0][..signed], &mut FrameAuth::device(SECRET)).is_ok());
+
+            // Running host is below device reservation until it resyncs
+            let mut frame = buf;
+            let signed = host.sign(&mut frame, sz);
+            assert_eq!(ParseMsg::new().try_parse_signed(&frame[..signed], &mut device), Err(ParserError::Replay));
+            host.resync();
+            let mut frame = buf;
+            let signed = host.sign(&mut frame, sz);
+            assert!(ParseMsg::new().try_parse_signed(&frame[..signed], &mut device).is_ok());
+            assert_eq!(device.storage.stores, 3);
+        }
+        assert_eq!(host_cell.stores, 3);
+
+        // Restarted host goes on above what device has seen
+        let mut host = FrameAuth::host_stored(SECRET, &mut host_cell).unwrap();
+        let mut frame = buf;
+        let signed = host.sign(&mut frame, sz);
+        assert!(ParseMsg::new().try_parse_signed(&frame[..signed], &mut device).is_ok());
+
+        // Frame whose counter can't be recorded isn't accepted
+        let mut cell = Cell { fail: true, ..Cell::default() };
+        let mut device = FrameAuth::device_stored(SECRET, &mut cell).unwrap();
+        let mut frame = buf;
+        let signed = FrameAuth::host(SECRET).sign(&mut frame, sz);
+        assert_eq!(ParseMsg::new().try_parse_signed(&frame[..signed], &mut device), Err(ParserError::Counter));
+    }
+}
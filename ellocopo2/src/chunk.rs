@@ -0,0 +1,248 @@
+//! Chunked access to BYTES/STR values bigger than one frame
+//!
+//! Device side keeps `ChunkBuf` to assemble values written by chunks,
+//! reads are sliced by server from the whole value. Host side walks
+//! the value with `ChunkReader` and `ChunkWriter`.
+
+use core::convert::TryFrom;
+
+use crate::parser::{Chunk, ParserError};
+use crate::protocol::*;
+use crate::ty::{TypeTag, Value};
+
+/// Assembly buffer for values up to `N` bytes written by WRITE_CHUNK
+pub struct ChunkBuf<const N: usize> {
+    active: bool,
+    path: [u8; MAX_PATH_SZ],
+    path_sz: u8,
+    ty: TypeTag,
+    total: usize,
+    len: usize,
+    data: [u8; N],
+}
+
+impl<const N: usize> Default for ChunkBuf<N> {
+    fn default() -> Self {
+        Self {
+            active: false,
+            path: [0u8; MAX_PATH_SZ],
+            path_sz: 0,
+            ty: TypeTag::BYTES,
+            total: 0,
+            len: 0,
+            data: [0u8; N],
+        }
+    }
+}
+
+impl<const N: usize> ChunkBuf<N> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Accept next chunk of value at `path`, returns whole value once the last chunk is in
+    ///
+    /// Chunk at offset 0 starts new value, dropping unfinished one
+    pub fn push(&mut self, path: &str, c: Chunk<'_>) -> Result<Option<Value<'_>>, AnswerCode> {
+        if c.offset == 0 {
+            if c.size as usize > N {
+                return Err(AnswerCode::ERR_CHUNK);
+            }
+            if c.ty != TypeTag::BYTES && c.ty != TypeTag::STR {
+                return Err(AnswerCode::ERR_TYPE);
+            }
+            self.active = true;
+            self.path[..path.len()].copy_from_slice(path.as_bytes());
+            self.path_sz = path.len() as u8;
+            self.ty = c.ty;
+            self.total = c.size as usize;
+            self.len = 0;
+        } else if !self.active
+            || &self.path[..self.path_sz as usize] != path.as_bytes()
+            || c.offset as usize != self.len
+            || c.size as usize != self.total
+            || c.ty != self.ty
+        {
+            self.active = false;
+            return Err(AnswerCode::ERR_CHUNK);
+        }
+
+        let end = self.len + c.data.len();
+        if end > self.total {
+            self.active = false;
+            return Err(AnswerCode::ERR_CHUNK);
+        }
+        self.data[self.len..end].copy_from_slice(c.data);
+        self.len = end;
+
+        if self.len < self.total {
+            return Ok(None);
+        }
+
+        self.active = false;
+        let data = &self.data[..self.total];
+        match self.ty {
+            TypeTag::STR => core::str::from_utf8(data)
+                .map(|s| Some(Value::STR(s)))
+                .map_err(|_| AnswerCode::ERR_TYPE),
+            _ => Ok(Some(Value::BYTES(data))),
+        }
+    }
+}
+
+/// Host side READ_CHUNK walker
+///
+/// Build request with `request`, pass answer payload to `accept`
+/// and repeat until `request` gives `None`.
+pub struct ChunkReader<'a> {
+    path: &'a str,
+    offset: u32,
+    total: Option<u32>,
+}
+
+impl<'a> ChunkReader<'a> {
+    pub fn new(path: &'a str) -> Self {
+        Self {
+            path,
+            offset: 0,
+            total: None,
+        }
+    }
+
+    /// Next READ_CHUNK request, `None` once the whole value is read
+    pub fn request(&self, buf: &mut [u8]) -> Option<usize> {
+        if matches!(self.total, Some(total) if self.offset >= total) {
+            return None;
+        }
+        RequestBuilder::new(buf)
+            .path(self.path)
+            .read_chunk(self.offset, 0)
+            .build()
+            .ok()
+    }
+
+    /// Take `OK_READ_CHUNK` answer payload, returns chunk data
+    pub fn accept<'c>(&mut self, v: Value<'c>) -> Result<Chunk<'c>, ParserError> {
+        let c = Chunk::try_from(v)?;
+        if c.offset != self.offset || matches!(self.total, Some(total) if total != c.size) {
+            return Err(ParserError::BadValue);
+        }
+        // Empty chunk before the end would loop forever
+        if c.data.is_empty() && c.offset < c.size {
+            return Err(ParserError::BadPayloadSz);
+        }
+        self.offset += c.data.len() as u32;
+        self.total = Some(c.size);
+        Ok(c)
+    }
+
+    /// Total value size, known after the first answer
+    pub fn total(&self) -> Option<u32> {
+        self.total
+    }
+}
+
+/// Host side WRITE_CHUNK splitter
+pub struct ChunkWriter<'a> {
+    path: &'a str,
+    ty: TypeTag,
+    data: &'a [u8],
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> ChunkWriter<'a> {
+    /// Panics if `v` is not BYTES or STR
+    pub fn new(path: &'a str, v: Value<'a>) -> Self {
+        let ty = TypeTag::from(&v);
+        assert!(ty == TypeTag::BYTES || ty == TypeTag::STR, "ChunkWriter, only BYTES and STR values are chunked");
+        Self {
+            path,
+            ty,
+            data: (&v).into(),
+            offset: 0,
+            done: false,
+        }
+    }
+
+    /// Next WRITE_CHUNK request, `None` once all chunks are built
+    pub fn request(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if self.done {
+            return None;
+        }
+        let end = self.data.len().min(self.offset + MAX_CHUNK_SZ);
+        let sz = RequestBuilder::new(buf)
+            .path(self.path)
+            .write_chunk(self.offset as u32, self.data.len() as u32, self.ty, &self.data[self.offset..end])
+            .build()
+            .ok()?;
+        self.offset = end;
+        self.done = end == self.data.len();
+        Some(sz)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::parser::{Msg, ParseMsg};
+
+    #[test]
+    fn assemble_chunks() {
+        let blob: [u8; 600] = core::array::from_fn(|i| i as u8);
+        let mut storage = ChunkBuf::<1024>::new();
+        let mut writer = ChunkWriter::new("/io/store/line", Value::BYTES(&blob));
+        let mut buf = [0u8; MAX_MSG_SZ];
+
+        let mut chunks = 0;
+        let mut whole = None;
+        while let Some(sz) = writer.request(&mut buf) {
+            let Msg(code, path, v) = ParseMsg::new().try_parse(&buf[..sz]).unwrap();
+            assert_eq!(RequestCode::try_from(code), Ok(RequestCode::WRITE_CHUNK));
+            let done = storage.push(path, Chunk::try_from(v).unwrap()).unwrap();
+            chunks += 1;
+            if let Some(v) = done {
+                whole = Some(<&[u8]>::from(&v).to_owned());
+            }
+        }
+        assert_eq!(chunks, 3);
+        assert_eq!(whole.as_deref(), Some(&blob[..]));
+
+        // Out of order and oversized chunks
+        let c = |offset, size, data| Chunk { offset, size, ty: TypeTag::STR, data };
+        assert_eq!(storage.push("/s", c(0, 4, b"ab")), Ok(None));
+        assert_eq!(storage.push("/s", c(3, 4, b"cd")), Err(AnswerCode::ERR_CHUNK));
+        assert_eq!(storage.push("/s", c(2, 4, b"cd")), Err(AnswerCode::ERR_CHUNK));
+        assert_eq!(storage.push("/s", c(0, 4, b"ab")), Ok(None));
+        assert_eq!(storage.push("/x", c(2, 4, b"cd")), Err(AnswerCode::ERR_CHUNK));
+        assert_eq!(storage.push("/s", c(0, 4, b"ab")), Ok(None));
+        assert_eq!(storage.push("/s", c(2, 4, b"cd")), Ok(Some(Value::STR("abcd"))));
+        assert_eq!(storage.push("/s", c(0, 2000, b"")), Err(AnswerCode::ERR_CHUNK));
+        assert_eq!(storage.push("/s", c(0, 1, &[0xFF])), Err(AnswerCode::ERR_TYPE));
+    }
+
+    #[test]
+    fn read_chunks() {
+        let mut reader = ChunkReader::new("/io/store/line");
+        let mut buf = [0u8; MAX_MSG_SZ];
+
+        let sz = reader.request(&mut buf).unwrap();
+        let Msg(code, _, v) = ParseMsg::new().try_parse(&buf[..sz]).unwrap();
+        assert_eq!(RequestCode::try_from(code), Ok(RequestCode::READ_CHUNK));
+        assert_eq!(Chunk::try_from(v).unwrap(), Chunk { offset: 0, size: 0, ty: TypeTag::UNIT, data: &[] });
+
+        let mut answer = [0u8; CHUNK_HEADER_SZ + 3];
+        answer[4..8].copy_from_slice(&5u32.to_ne_bytes());
+        answer[8] = TypeTag::BYTES as u8;
+        answer[CHUNK_HEADER_SZ..].copy_from_slice(b"abc");
+        assert_eq!(reader.accept(Value::BYTES(&answer)).unwrap().data, b"abc");
+        assert_eq!(reader.total(), Some(5));
+
+        // Wrong offset
+        assert_eq!(reader.accept(Value::BYTES(&answer)), Err(ParserError::BadValue));
+
+        answer[0..4].copy_from_slice(&3u32.to_ne_bytes());
+        assert_eq!(reader.accept(Value::BYTES(&answer[..CHUNK_HEADER_SZ + 2])).unwrap().data, b"ab");
+        assert!(reader.request(&mut buf).is_none());
+    }
+}
@@ -0,0 +1,127 @@
+//! Host side requests over any `Transport`
+//!
+//! `Client` builds the request, exchanges it and checks the answer code,
+//! codegen wraps it into typed register accessors.
+
+use core::convert::TryInto;
+
+use crate::parser::{Msg, ParseMsg, ParserError};
+use crate::protocol::*;
+use crate::ty::{TypeTag, Value};
+
+/// Link to the device, one request frame out, one answer frame back
+pub trait Transport {
+    type Error;
+
+    /// Send `req`, receive answer into `ans` and return its size
+    fn exchange(&mut self, req: &[u8], ans: &mut [u8]) -> Result<usize, Self::Error>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientError<E> {
+    Transport(E),
+    /// Request doesn't fit the frame
+    Request(&'static str),
+    Parse(ParserError),
+    /// Device answered with this code instead of success
+    Answer(AnswerCode),
+    /// Answer payload is not of the register type
+    Type(TypeTag),
+    /// Value breaks register constraints, request isn't sent
+    Range,
+}
+
+pub struct Client<T> {
+    transport: T,
+    req: [u8; MAX_MSG_SZ],
+    ans: [u8; MAX_MSG_SZ],
+}
+
+impl<T: Transport> Client<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            req: [0; MAX_MSG_SZ],
+            ans: [0; MAX_MSG_SZ],
+        }
+    }
+
+    pub fn transport(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    /// Exchange request, payload of successful answer is returned
+    pub fn request(&mut self, code: RequestCode, path: &str, v: Value<'_>) -> Result<Value<'_>, ClientError<T::Error>> {
+        let sz = RequestBuilder::new(&mut self.req)
+            .code(code)
+            .path(path)
+            .payload(v)
+            .build()
+            .map_err(ClientError::Request)?;
+        let sz = self.transport
+            .exchange(&self.req[..sz], &mut self.ans)
+            .map_err(ClientError::Transport)?;
+        let Msg(ans, _, v) = ParseMsg::new()
+            .try_parse(&self.ans[..sz])
+            .map_err(ClientError::Parse)?;
+        if ans != AnswerCode::from(code) {
+            return Err(ClientError::Answer(ans));
+        }
+        Ok(v)
+    }
+
+    pub fn read(&mut self, path: &str) -> Result<Value<'_>, ClientError<T::Error>> {
+        self.request(RequestCode::READ, path, Value::UNIT(()))
+    }
+
+    /// READ with answer converted to register type
+    pub fn read_as<'s, R>(&'s mut self, path: &str) -> Result<R, ClientError<T::Error>>
+    where
+        Value<'s>: TryInto<R, Error = AnswerCode>,
+    {
+        let v = self.read(path)?;
+        let ty = TypeTag::from(&v);
+        v.try_into().map_err(|_| ClientError::Type(ty))
+    }
+
+    pub fn write(&mut self, path: &str, v: Value<'_>) -> Result<(), ClientError<T::Error>> {
+        self.request(RequestCode::WRITE, path, v).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers READ of `/reg` with U8, everything else with ERR_PATH
+    struct Loopback;
+
+    impl Transport for Loopback {
+        type Error = ();
+
+        fn exchange(&mut self, req: &[u8], ans: &mut [u8]) -> Result<usize, ()> {
+            let Msg(code, path, _) = ParseMsg::new().try_parse(req).map_err(|_| ())?;
+            let mut b = AnswerBuilder::new(ans);
+            match (code, path) {
+                (AnswerCode::OK_READ, "/reg") => b.code(code).payload(Value::U8(7)),
+                (AnswerCode::OK_WRITE, "/reg") => b.code(code),
+                _ => b.code(AnswerCode::ERR_PATH),
+            };
+            Ok(b.build())
+        }
+    }
+
+    #[test]
+    fn typed_requests() {
+        let mut c = Client::new(Loopback);
+        assert_eq!(c.read("/reg"), Ok(Value::U8(7)));
+        assert_eq!(c.read_as::<u8>("/reg"), Ok(7));
+        assert_eq!(c.read_as::<bool>("/reg"), Err(ClientError::Type(TypeTag::U8)));
+        assert_eq!(c.write("/reg", Value::U8(1)), Ok(()));
+        assert_eq!(c.read("/nope"), Err(ClientError::Answer(AnswerCode::ERR_PATH)));
+    }
+}
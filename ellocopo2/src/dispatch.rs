@@ -0,0 +1,647 @@
+use crate::protocol::*;
+use crate::ty::Value;
+
+/// Result of generated `req2msg` dispatcher
+///
+/// `M` is the generated `Msg` enum, `D` is the list of `@default` writes
+/// produced by `RESET` request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DispatchResult<M, D = [M; 0]> {
+    /// Request decoded into application message
+    Msg(M),
+    /// Request answered right away by `@fast` handler
+    Answer(Value<'static>),
+    /// Request rejected by dispatcher
+    Err(AnswerCode),
+    /// `RESET` request decoded into writes of default values
+    Reset(D),
+}
+
+impl<M, D> DispatchResult<M, D> {
+    pub fn unwrap(self) -> M {
+        match self {
+            DispatchResult::Msg(m) => m,
+            DispatchResult::Answer(v) => panic!("DispatchResult::unwrap on fast answer: {:?}", v),
+            DispatchResult::Err(e) => panic!("DispatchResult::unwrap on error: {:?}", e),
+            DispatchResult::Reset(_) => panic!("DispatchResult::unwrap on reset"),
+        }
+    }
+
+    /// Turn dispatch result into answer value, decoded messages are passed to `f`
+    ///
+    /// On reset every default write is passed to `f`, first error stops it
+    pub fn answer<'r, E, F>(self, mut f: F) -> Result<Value<'r>, E>
+    where
+        D: IntoIterator,
+        D::Item: Into<M>,
+        E: From<AnswerCode>,
+        F: FnMut(M) -> Result<Value<'r>, E>,
+    {
+        match self {
+            DispatchResult::Msg(m) => f(m),
+            DispatchResult::Answer(v) => Ok(v),
+            DispatchResult::Err(e) => Err(e.into()),
+            DispatchResult::Reset(defaults) => {
+                for m in defaults {
+                    f(m.into())?;
+                }
+                Ok(Value::UNIT(()))
+            }
+        }
+    }
+
+    /// Like `answer`, but handler may park request
+    ///
+    /// Default writes can't be parked, such reset is answered with `ERR_UNIMPL`
+    pub fn reply<'r, R, F>(self, mut f: F) -> Reply<'r>
+    where
+        D: IntoIterator,
+        D::Item: Into<M>,
+        R: Into<Reply<'r>>,
+        F: FnMut(M) -> R,
+    {
+        match self {
+            DispatchResult::Msg(m) => f(m).into(),
+            DispatchResult::Answer(v) => Reply::Ok(v),
+            DispatchResult::Err(e) => Reply::Err(e.into()),
+            DispatchResult::Reset(defaults) => {
+                for m in defaults {
+                    match f(m.into()).into() {
+                        Reply::Ok(_) => (),
+                        Reply::Err(e) => return Reply::Err(e),
+                        Reply::Pending(_) => return Reply::Err(AnswerCode::ERR_UNIMPL.into()),
+                    }
+                }
+                Reply::Ok(Value::UNIT(()))
+            }
+        }
+    }
+}
+
+/// Handler reply to request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reply<'a> {
+    Ok(Value<'a>),
+    Err(AnswerErr<'a>),
+    /// Request parked, answered with `OK_PENDING` and finished later by completion
+    Pending(Ticket),
+}
+
+impl<'a, E: Into<AnswerErr<'a>>> From<Result<Value<'a>, E>> for Reply<'a> {
+    fn from(r: Result<Value<'a>, E>) -> Self {
+        match r {
+            Ok(v) => Reply::Ok(v),
+            Err(e) => Reply::Err(e.into()),
+        }
+    }
+}
+
+impl From<Ticket> for Reply<'_> {
+    fn from(t: Ticket) -> Self {
+        Reply::Pending(t)
+    }
+}
+
+/// Sequence number of parked request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ticket(pub u16);
+
+#[derive(Clone, Copy)]
+struct Parked {
+    seq: u16,
+    code: RequestCode,
+    path: [u8; MAX_PATH_SZ],
+    path_sz: u8,
+}
+
+/// Requests parked by handlers, up to `N` at once
+///
+/// Handler parks slow request with `park` and replies with returned ticket,
+/// once the operation is done `finish` builds completion frame to send.
+pub struct Parking<const N: usize> {
+    next_seq: u16,
+    slots: [Option<Parked>; N],
+}
+
+impl<const N: usize> Default for Parking<N> {
+    fn default() -> Self {
+        Self {
+            next_seq: 0,
+            slots: [None; N],
+        }
+    }
+}
+
+impl<const N: usize> Parking<N> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Park request, `ERR_BUSY` if all slots are taken
+    pub fn park(&mut self, code: RequestCode, path: &str) -> Result<Ticket, AnswerCode> {
+        let free = self.slots.iter()
+            .position(|s| s.is_none())
+            .ok_or(AnswerCode::ERR_BUSY)?;
+
+        // Seq of request still parked since the last wrap is skipped
+        let mut seq = self.next_seq;
+        while self.slots.iter().flatten().any(|p| p.seq == seq) {
+            seq = seq.wrapping_add(1);
+        }
+        self.next_seq = seq.wrapping_add(1);
+
+        let mut parked = Parked {
+            seq,
+            code,
+            path: [0u8; MAX_PATH_SZ],
+            path_sz: path.len() as u8,
+        };
+        parked.path[..path.len()].copy_from_slice(path.as_bytes());
+        self.slots[free] = Some(parked);
+
+        Ok(Ticket(seq))
+    }
+
+    /// Free ticket without completion, `false` if it is not parked
+    pub fn cancel(&mut self, t: Ticket) -> bool {
+        match self.slots.iter_mut().find(|s| matches!(s, Some(p) if p.seq == t.0)) {
+            Some(slot) => {
+                *slot = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_parked(&self, t: Ticket) -> bool {
+        self.slots.iter().flatten().any(|p| p.seq == t.0)
+    }
+
+    /// Free ticket and build its completion frame in `buf`
+    ///
+    /// Returns frame size or `None` if ticket is not parked
+    pub fn finish<'r, E>(&mut self, t: Ticket, res: Result<Value<'r>, E>, buf: &mut [u8]) -> Option<usize>
+    where
+        E: Into<AnswerErr<'r>>,
+    {
+        let slot = self.slots.iter_mut().find(|s| matches!(s, Some(p) if p.seq == t.0))?;
+        let parked = slot.take()?;
+        let path = unsafe { core::str::from_utf8_unchecked(&parked.path[..parked.path_sz as usize]) };
+
+        let mut builder = CompletionBuilder::new(buf);
+        builder.path(path).seq(parked.seq);
+        match res {
+            Ok(v) => builder.code(parked.code.into()).payload(v),
+            Err(e) => builder.error(e.into()),
+        };
+        builder.build().ok()
+    }
+}
+
+/// Staging storage of `@txn` registers, generated as `Staging`
+///
+/// Participating registers are numbered `0..LEN` in schema order
+pub trait Stage<M>: Default {
+    const LEN: usize;
+
+    /// Keep write of participating register, other messages are given back
+    fn stage(&mut self, m: M) -> Result<(), M>;
+    /// Write message of `i`-th register if it is staged
+    fn write(&self, i: usize) -> Option<M>;
+    /// Read message of `i`-th register
+    fn read(i: usize) -> M;
+    /// Stage value for `i`-th register
+    fn set(&mut self, i: usize, v: Value<'_>) -> Result<(), AnswerCode>;
+}
+
+/// Transaction state for BEGIN/COMMIT/ABORT requests
+///
+/// While transaction is open writes of `@txn` registers are staged in `S`,
+/// everything else is handled right away. `RESET`, `CAS`, `WRITE_VERIFY` and
+/// `WRITE_CHUNK` would write around staging and are refused with `ERR_TXN`;
+/// `Server` splits the last three into plain reads and writes, so their writes
+/// are staged as well. On commit staged writes are checked
+/// by validator as a whole and applied in one go; if one of them fails,
+/// the registers already written get their previous values back.
+pub struct Txn<S> {
+    staging: Option<S>,
+    validate: fn(&S) -> Result<(), AnswerErr<'static>>,
+}
+
+impl<S> Default for Txn<S> {
+    fn default() -> Self {
+        Self::with_validator(|_| Ok(()))
+    }
+}
+
+impl<S> Txn<S> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_validator(validate: fn(&S) -> Result<(), AnswerErr<'static>>) -> Self {
+        Self {
+            staging: None,
+            validate,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.staging.is_some()
+    }
+
+    /// Like `DispatchResult::answer`, with transaction requests and staging handled
+    pub fn answer<'r, M, D, F>(&mut self, code: RequestCode, res: DispatchResult<M, D>, mut f: F) -> Result<Value<'r>, AnswerErr<'r>>
+    where
+        S: Stage<M>,
+        D: IntoIterator,
+        D::Item: Into<M>,
+        F: FnMut(M) -> Result<Value<'r>, AnswerErr<'r>>,
+    {
+        match (code, &mut self.staging) {
+            (RequestCode::BEGIN, None) => {
+                self.staging = Some(S::default());
+                Ok(Value::UNIT(()))
+            }
+            (RequestCode::ABORT, Some(_)) => {
+                self.staging = None;
+                Ok(Value::UNIT(()))
+            }
+            (RequestCode::COMMIT, Some(_)) => {
+                let staging = self.staging.take().unwrap();
+                (self.validate)(&staging)?;
+                commit(&staging, f)
+            }
+            (RequestCode::BEGIN | RequestCode::ABORT | RequestCode::COMMIT, _)
+                | (RequestCode::RESET | RequestCode::CAS | RequestCode::WRITE_VERIFY | RequestCode::WRITE_CHUNK, Some(_)) => {
+                Err(AnswerCode::ERR_TXN.into())
+            }
+            (RequestCode::WRITE, Some(staging)) => match res {
+                DispatchResult::Msg(m) => match staging.stage(m) {
+                    Ok(()) => Ok(Value::UNIT(())),
+                    Err(m) => f(m),
+                },
+                res => res.answer(f),
+            },
+            _ => res.answer(f),
+        }
+    }
+}
+
+fn commit<'r, M, S, F>(staging: &S, mut f: F) -> Result<Value<'r>, AnswerErr<'r>>
+where
+    S: Stage<M>,
+    F: FnMut(M) -> Result<Value<'r>, AnswerErr<'r>>,
+{
+    let mut backup = S::default();
+    for i in 0..S::LEN {
+        if staging.write(i).is_some() {
+            let v = f(S::read(i))?;
+            backup.set(i, v)?;
+        }
+    }
+
+    for i in 0..S::LEN {
+        if let Some(m) = staging.write(i) {
+            if let Err(e) = f(m) {
+                for m in (0..i).filter_map(|j| backup.write(j)) {
+                    let _ = f(m);
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(Value::UNIT(()))
+}
+
+/// FNV-1a hash with `seed` for the offset basis, generated `path2idx` is built on it
+#[inline]
+pub fn fnv1a(seed: u32, data: &[u8]) -> u32 {
+    data.iter().fold(seed ^ 0x811c_9dc5, |h, b| (h ^ *b as u32).wrapping_mul(0x0100_0193))
+}
+
+/// Convert request `Value` into register type or return `ERR_TYPE` from dispatcher
+#[macro_export]
+macro_rules! map_ty_error {
+    ($v:expr) => {
+        match core::convert::TryInto::try_into($v) {
+            Ok(v) => v,
+            Err(e) => return $crate::DispatchResult::Err(e),
+        }
+    };
+}
+
+/// Check converted write value against scheme constraints or return `ERR_RANGE` from dispatcher
+///
+/// `map_range_error!(map_ty_error!(v), |x: u8| x >= 1 && x <= 200)`
+#[macro_export]
+macro_rules! map_range_error {
+    ($v:expr, |$x:ident: $ty:ty| $ok:expr) => {{
+        let $x: $ty = $v;
+        if !($ok) {
+            return $crate::DispatchResult::Err($crate::AnswerCode::ERR_RANGE);
+        }
+        $x
+    }};
+}
+
+/// One arm of generated dispatcher
+///
+/// `impl_arm!([FAST,] RW|WO|RO, code, sys_lvl, required_lvl, [write_action,] [read_action])`
+///
+/// RW arm may take separate levels: `impl_arm!([FAST,] RW, code, sys_lvl, write_lvl, read_lvl, write_action, read_action)`
+///
+/// Request codes other than `READ`/`WRITE` are routed by dispatcher itself and get `ERR_ACCESS` here
+#[macro_export]
+macro_rules! impl_arm {
+    (@arm $kind:ident, RW, $code:expr, $sys_lvl:expr, $w_lvl:expr, $r_lvl:expr, $w:expr, $r:expr) => {
+        match $code {
+            $crate::RequestCode::WRITE if $sys_lvl < $w_lvl => $crate::DispatchResult::Err($crate::AnswerCode::ERR_PRIV),
+            $crate::RequestCode::READ if $sys_lvl < $r_lvl => $crate::DispatchResult::Err($crate::AnswerCode::ERR_PRIV),
+            $crate::RequestCode::WRITE => $crate::DispatchResult::$kind($w),
+            $crate::RequestCode::READ  => $crate::DispatchResult::$kind($r),
+            _ => $crate::DispatchResult::Err($crate::AnswerCode::ERR_ACCESS),
+        }
+    };
+    (@arm $kind:ident, RW, $code:expr, $sys_lvl:expr, $lvl:expr, $w:expr, $r:expr) => {
+        $crate::impl_arm!(@arm $kind, RW, $code, $sys_lvl, $lvl, $lvl, $w, $r)
+    };
+    (@arm $kind:ident, WO, $code:expr, $sys_lvl:expr, $lvl:expr, $w:expr) => {
+        if $sys_lvl < $lvl {
+            $crate::DispatchResult::Err($crate::AnswerCode::ERR_PRIV)
+        } else {
+            match $code {
+                $crate::RequestCode::WRITE => $crate::DispatchResult::$kind($w),
+                $crate::RequestCode::READ  => $crate::DispatchResult::Err($crate::AnswerCode::ERR_ACCESS),
+                _ => $crate::DispatchResult::Err($crate::AnswerCode::ERR_ACCESS),
+            }
+        }
+    };
+    (@arm $kind:ident, RO, $code:expr, $sys_lvl:expr, $lvl:expr, $r:expr) => {
+        if $sys_lvl < $lvl {
+            $crate::DispatchResult::Err($crate::AnswerCode::ERR_PRIV)
+        } else {
+            match $code {
+                $crate::RequestCode::WRITE => $crate::DispatchResult::Err($crate::AnswerCode::ERR_ACCESS),
+                $crate::RequestCode::READ  => $crate::DispatchResult::$kind($r),
+                _ => $crate::DispatchResult::Err($crate::AnswerCode::ERR_ACCESS),
+            }
+        }
+    };
+    (FAST, $($rest:tt)*) => {
+        $crate::impl_arm!(@arm Answer, $($rest)*)
+    };
+    ($($rest:tt)*) => {
+        $crate::impl_arm!(@arm Msg, $($rest)*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::{TryFrom, TryInto};
+    use crate::*;
+    use crate::priv_lvl::PrivLvl;
+
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum TestMsg {
+        Reg_W(u32),
+        Reg_R,
+    }
+
+    fn req2msg(code: RequestCode, v: Value, sys_lvl: PrivLvl) -> DispatchResult<TestMsg> {
+        impl_arm!(RW, code, sys_lvl, PrivLvl::MODE1_LVL, TestMsg::Reg_W(map_ty_error!(v)), TestMsg::Reg_R)
+    }
+
+    fn split2msg(code: RequestCode, v: Value, sys_lvl: PrivLvl) -> DispatchResult<TestMsg> {
+        impl_arm!(RW, code, sys_lvl, PrivLvl::SECUR_LVL, PrivLvl::NORMAL_LVL, TestMsg::Reg_W(map_ty_error!(v)), TestMsg::Reg_R)
+    }
+
+    fn range2msg(code: RequestCode, v: Value, sys_lvl: PrivLvl) -> DispatchResult<TestMsg> {
+        impl_arm!(RW, code, sys_lvl, PrivLvl::MODE1_LVL, TestMsg::Reg_W(map_range_error!(map_ty_error!(v), |x: u32| x <= 100)), TestMsg::Reg_R)
+    }
+
+    fn fast2msg(code: RequestCode, sys_lvl: PrivLvl) -> DispatchResult<TestMsg> {
+        impl_arm!(FAST, RO, code, sys_lvl, PrivLvl::NORMAL_LVL, Value::U8(42))
+    }
+
+    #[test]
+    fn dispatch_arms() {
+        assert_eq!(req2msg(RequestCode::WRITE, Value::U32(7), PrivLvl::MODE1_LVL), DispatchResult::Msg(TestMsg::Reg_W(7)));
+        assert_eq!(req2msg(RequestCode::READ, Value::UNIT(()), PrivLvl::MODE1_LVL), DispatchResult::Msg(TestMsg::Reg_R));
+        assert_eq!(req2msg(RequestCode::WRITE, Value::U8(7), PrivLvl::MODE1_LVL), DispatchResult::Err(AnswerCode::ERR_TYPE));
+        assert_eq!(req2msg(RequestCode::READ, Value::UNIT(()), PrivLvl::NORMAL_LVL), DispatchResult::Err(AnswerCode::ERR_PRIV));
+
+        assert_eq!(split2msg(RequestCode::READ, Value::UNIT(()), PrivLvl::NORMAL_LVL), DispatchResult::Msg(TestMsg::Reg_R));
+        assert_eq!(split2msg(RequestCode::WRITE, Value::U32(7), PrivLvl::MODE1_LVL), DispatchResult::Err(AnswerCode::ERR_PRIV));
+        assert_eq!(split2msg(RequestCode::WRITE, Value::U32(7), PrivLvl::DEVEL_LVL), DispatchResult::Msg(TestMsg::Reg_W(7)));
+
+        assert_eq!(range2msg(RequestCode::WRITE, Value::U32(100), PrivLvl::MODE1_LVL), DispatchResult::Msg(TestMsg::Reg_W(100)));
+        assert_eq!(range2msg(RequestCode::WRITE, Value::U32(101), PrivLvl::MODE1_LVL), DispatchResult::Err(AnswerCode::ERR_RANGE));
+        assert_eq!(range2msg(RequestCode::WRITE, Value::U32(101), PrivLvl::NORMAL_LVL), DispatchResult::Err(AnswerCode::ERR_PRIV));
+
+        assert_eq!(fast2msg(RequestCode::READ, PrivLvl::NORMAL_LVL), DispatchResult::Answer(Value::U8(42)));
+        assert_eq!(fast2msg(RequestCode::WRITE, PrivLvl::NORMAL_LVL), DispatchResult::Err(AnswerCode::ERR_ACCESS));
+    }
+
+    #[test]
+    fn reset_defaults() {
+        const DEFAULTS: [TestMsg; 2] = [TestMsg::Reg_W(1), TestMsg::Reg_W(2)];
+
+        let mut written = 0;
+        let res: DispatchResult<TestMsg, _> = DispatchResult::Reset(DEFAULTS.iter().copied());
+        let a = res.answer(|m| match m {
+            TestMsg::Reg_W(v) => { written = written * 10 + v; Ok::<_, AnswerCode>(Value::UNIT(())) }
+            TestMsg::Reg_R => Err(AnswerCode::ERR_ACCESS),
+        });
+        assert_eq!(a, Ok(Value::UNIT(())));
+        assert_eq!(written, 12);
+
+        let res: DispatchResult<TestMsg, _> = DispatchResult::Reset(DEFAULTS.iter().copied());
+        let r = res.reply(|m| match m {
+            TestMsg::Reg_W(1) => Err(AnswerCode::ERR_BUSY),
+            _ => Ok(Value::UNIT(())),
+        });
+        assert_eq!(r, Reply::Err(AnswerCode::ERR_BUSY.into()));
+
+        assert_eq!(req2msg(RequestCode::RESET, Value::UNIT(()), PrivLvl::MODE1_LVL), DispatchResult::Err(AnswerCode::ERR_ACCESS));
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum TxnMsg {
+        A_W(u8),
+        A_R,
+        B_W(u8),
+        B_R,
+    }
+
+    #[derive(Default)]
+    struct TxnStage {
+        a: Option<u8>,
+        b: Option<u8>,
+    }
+
+    impl Stage<TxnMsg> for TxnStage {
+        const LEN: usize = 2;
+
+        fn stage(&mut self, m: TxnMsg) -> Result<(), TxnMsg> {
+            match m {
+                TxnMsg::A_W(v) => self.a = Some(v),
+                TxnMsg::B_W(v) => self.b = Some(v),
+                m => return Err(m),
+            }
+            Ok(())
+        }
+
+        fn write(&self, i: usize) -> Option<TxnMsg> {
+            match i {
+                0 => self.a.map(TxnMsg::A_W),
+                1 => self.b.map(TxnMsg::B_W),
+                _ => None,
+            }
+        }
+
+        fn read(i: usize) -> TxnMsg {
+            [TxnMsg::A_R, TxnMsg::B_R][i]
+        }
+
+        fn set(&mut self, i: usize, v: Value<'_>) -> Result<(), AnswerCode> {
+            match i {
+                0 => self.a = Some(v.try_into()?),
+                _ => self.b = Some(v.try_into()?),
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn txn_commit_and_rollback() {
+        // Register B refuses 0xFF
+        let mut regs = (1u8, 2u8);
+        let mut handle = |m: TxnMsg| -> Result<Value<'static>, AnswerErr<'static>> {
+            match m {
+                TxnMsg::A_W(v) => regs.0 = v,
+                TxnMsg::B_W(0xFF) => return Err(AnswerCode::ERR_TYPE.into()),
+                TxnMsg::B_W(v) => regs.1 = v,
+                TxnMsg::A_R => return Ok(Value::U8(regs.0)),
+                TxnMsg::B_R => return Ok(Value::U8(regs.1)),
+            }
+            Ok(Value::UNIT(()))
+        };
+
+        let mut txn = Txn::<TxnStage>::new();
+        let msg = |m| DispatchResult::<TxnMsg>::Msg(m);
+        let ctl = || DispatchResult::<TxnMsg>::Err(AnswerCode::ERR_PATH);
+        let mut answer = |txn: &mut Txn<TxnStage>, code, res| txn.answer(code, res, &mut handle);
+
+        assert_eq!(answer(&mut txn, RequestCode::COMMIT, ctl()), Err(AnswerCode::ERR_TXN.into()));
+        assert_eq!(answer(&mut txn, RequestCode::BEGIN, ctl()), Ok(Value::UNIT(())));
+        assert_eq!(answer(&mut txn, RequestCode::BEGIN, ctl()), Err(AnswerCode::ERR_TXN.into()));
+        assert_eq!(answer(&mut txn, RequestCode::WRITE, msg(TxnMsg::A_W(10))), Ok(Value::UNIT(())));
+        assert_eq!(answer(&mut txn, RequestCode::WRITE, msg(TxnMsg::B_W(20))), Ok(Value::UNIT(())));
+        // Staged writes are not visible yet
+        assert_eq!(answer(&mut txn, RequestCode::READ, msg(TxnMsg::A_R)), Ok(Value::U8(1)));
+        assert_eq!(answer(&mut txn, RequestCode::COMMIT, ctl()), Ok(Value::UNIT(())));
+        assert!(!txn.is_open());
+        assert_eq!(answer(&mut txn, RequestCode::READ, msg(TxnMsg::B_R)), Ok(Value::U8(20)));
+
+        // B fails, A is rolled back
+        answer(&mut txn, RequestCode::BEGIN, ctl()).unwrap();
+        answer(&mut txn, RequestCode::WRITE, msg(TxnMsg::A_W(11))).unwrap();
+        answer(&mut txn, RequestCode::WRITE, msg(TxnMsg::B_W(0xFF))).unwrap();
+        assert_eq!(answer(&mut txn, RequestCode::COMMIT, ctl()), Err(AnswerCode::ERR_TYPE.into()));
+        assert_eq!(answer(&mut txn, RequestCode::READ, msg(TxnMsg::A_R)), Ok(Value::U8(10)));
+
+        // Aborted writes are dropped
+        answer(&mut txn, RequestCode::BEGIN, ctl()).unwrap();
+        answer(&mut txn, RequestCode::WRITE, msg(TxnMsg::A_W(12))).unwrap();
+        assert_eq!(answer(&mut txn, RequestCode::ABORT, ctl()), Ok(Value::UNIT(())));
+        assert_eq!(answer(&mut txn, RequestCode::READ, msg(TxnMsg::A_R)), Ok(Value::U8(10)));
+
+        // Validator sees staged values as a whole
+        let mut txn = Txn::<TxnStage>::with_validator(|s| match (s.a, s.b) {
+            (Some(a), Some(b)) if a > b => Err(AnswerErr(AnswerCode::ERR_CUSTOM, ErrDetail::Msg("a > b"))),
+            _ => Ok(()),
+        });
+        answer(&mut txn, RequestCode::BEGIN, ctl()).unwrap();
+        answer(&mut txn, RequestCode::WRITE, msg(TxnMsg::A_W(30))).unwrap();
+        answer(&mut txn, RequestCode::WRITE, msg(TxnMsg::B_W(25))).unwrap();
+        assert_eq!(answer(&mut txn, RequestCode::COMMIT, ctl()), Err(AnswerErr(AnswerCode::ERR_CUSTOM, ErrDetail::Msg("a > b"))));
+        assert_eq!(answer(&mut txn, RequestCode::READ, msg(TxnMsg::A_R)), Ok(Value::U8(10)));
+    }
+
+    #[test]
+    fn txn_refuses_writes_around_staging() {
+        let mut regs = (1u8, 2u8);
+        let mut handle = |m: TxnMsg| -> Result<Value<'static>, AnswerErr<'static>> {
+            match m {
+                TxnMsg::A_W(v) => regs.0 = v,
+                TxnMsg::B_W(v) => regs.1 = v,
+                TxnMsg::A_R => return Ok(Value::U8(regs.0)),
+                TxnMsg::B_R => return Ok(Value::U8(regs.1)),
+            }
+            Ok(Value::UNIT(()))
+        };
+        let mut txn = Txn::<TxnStage>::new();
+        let ctl = DispatchResult::<TxnMsg, [TxnMsg; 2]>::Err(AnswerCode::ERR_PATH);
+        let msg = DispatchResult::<TxnMsg, [TxnMsg; 2]>::Msg;
+        let reset = DispatchResult::<TxnMsg, [TxnMsg; 2]>::Reset([TxnMsg::A_W(0), TxnMsg::B_W(0)]);
+
+        // Nothing reaches registers, staging stays empty
+        txn.answer(RequestCode::BEGIN, ctl, &mut handle).unwrap();
+        assert_eq!(txn.answer(RequestCode::RESET, reset, &mut handle), Err(AnswerCode::ERR_TXN.into()));
+        for code in [RequestCode::CAS, RequestCode::WRITE_VERIFY, RequestCode::WRITE_CHUNK] {
+            let res = txn.answer(code, msg(TxnMsg::A_W(13)), &mut handle);
+            assert_eq!(res, Err(AnswerCode::ERR_TXN.into()), "{:?}", code);
+        }
+        assert_eq!(txn.answer(RequestCode::ABORT, ctl, &mut handle), Ok(Value::UNIT(())));
+        assert_eq!(txn.answer(RequestCode::READ, msg(TxnMsg::A_R), &mut handle), Ok(Value::U8(1)));
+        assert_eq!(txn.answer(RequestCode::READ, msg(TxnMsg::B_R), &mut handle), Ok(Value::U8(2)));
+
+        // Outside of transaction they pass
+        assert_eq!(txn.answer(RequestCode::RESET, reset, &mut handle), Ok(Value::UNIT(())));
+        assert_eq!(txn.answer(RequestCode::READ, msg(TxnMsg::A_R), &mut handle), Ok(Value::U8(0)));
+    }
+
+    #[test]
+    fn park_and_finish() {
+        let mut parking = Parking::<2>::new();
+        let t0 = parking.park(RequestCode::WRITE, "/ctrl/erase").unwrap();
+        let t1 = parking.park(RequestCode::READ, "/io/status").unwrap();
+        assert_ne!(t0, t1);
+        assert_eq!(parking.park(RequestCode::WRITE, "/ctrl/erase"), Err(AnswerCode::ERR_BUSY));
+
+        let mut buf = [0u8; MAX_MSG_SZ];
+        let sz = parking.finish(t1, Ok::<_, AnswerCode>(Value::U32(5)), &mut buf).unwrap();
+        assert!(!parking.is_parked(t1));
+        assert!(parking.finish(t1, Ok::<_, AnswerCode>(Value::U32(5)), &mut buf).is_none());
+
+        let Msg(code, path, v) = ParseMsg::new().try_parse(&buf[..sz]).unwrap();
+        assert_eq!((code, path), (AnswerCode::ASYNC_DONE, "/io/status"));
+        let done = Completion::try_from(v).unwrap();
+        assert_eq!(done, Completion { seq: t1.0, code: AnswerCode::OK_READ, value: Value::U32(5) });
+
+        let sz = parking.finish(t0, Err(AnswerCode::ERR_CUSTOM), &mut buf).unwrap();
+        let Msg(_, path, v) = ParseMsg::new().try_parse(&buf[..sz]).unwrap();
+        assert_eq!(path, "/ctrl/erase");
+        let done = Completion::try_from(v).unwrap();
+        assert_eq!(done, Completion { seq: t0.0, code: AnswerCode::ERR_CUSTOM, value: Value::UNIT(()) });
+
+        // Slot is free again
+        assert!(parking.park(RequestCode::WRITE, "/ctrl/erase").is_ok());
+    }
+
+    #[test]
+    fn park_seq_wrap() {
+        let mut parking = Parking::<2>::new();
+        let long = parking.park(RequestCode::WRITE, "/ctrl/erase").unwrap();
+        for _ in 0..u16::MAX {
+            let t = parking.park(RequestCode::READ, "/io/status").unwrap();
+            assert_ne!(t, long);
+            assert!(parking.cancel(t));
+        }
+        // Seq of request parked all along is skipped after wrap
+        assert_eq!(parking.park(RequestCode::READ, "/io/status"), Ok(Ticket(1)));
+    }
+}
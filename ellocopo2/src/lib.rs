@@ -5,12 +5,24 @@
 mod protocol;
 mod ty;
 mod parser;
+mod dispatch;
 
 pub mod priv_lvl;
+pub mod chunk;
+pub mod client;
+pub mod persist;
+#[cfg(feature = "auth")]
+pub mod auth;
+#[cfg(feature = "embedded-io")]
+pub mod server;
 
 pub use protocol::*;
 pub use ty::*;
 pub use parser::*;
+pub use dispatch::*;
+pub use priv_lvl::PrivLvl;
+pub use client::{Transport, ClientError};
+pub use persist::{PersistError, Storage};
 #[cfg(feature = "std")]
 pub use crate::parser::owned;
 
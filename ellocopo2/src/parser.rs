@@ -4,7 +4,7 @@ use core::ops::Range;
 use crate::protocol::*;
 use crate::ty::*;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum ParserError {
     NeedMoreData,
     BadCode,
@@ -13,6 +13,12 @@ pub enum ParserError {
     BadPayloadSz,
     BadTypeID,
     BadValue,
+    /// Frame is not signed or its tag doesn't match
+    BadAuth,
+    /// Signed frame counter is not above the last accepted one
+    Replay,
+    /// Signed frame counter couldn't be recorded in storage, frame is refused
+    Counter,
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -20,6 +26,83 @@ pub struct Msg<'a>(pub AnswerCode, pub &'a str, pub Value<'a>);
 
 pub type ParseResult<'a> = Result<Msg<'a>, ParserError>;
 
+/// Result of pending request, payload of `ASYNC_DONE` frame
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Completion<'a> {
+    pub seq: u16,
+    pub code: AnswerCode,
+    pub value: Value<'a>,
+}
+
+impl<'a> TryFrom<Value<'a>> for Completion<'a> {
+    type Error = ParserError;
+
+    fn try_from(v: Value<'a>) -> Result<Self, Self::Error> {
+        let b = match v {
+            Value::BYTES(b) if b.len() >= COMPLETION_HEADER_SZ => b,
+            _ => return Err(ParserError::BadValue),
+        };
+        let seq = u16::from_ne_bytes([b[0], b[1]]);
+        let code = AnswerCode::try_from(b[2]).map_err(|_| ParserError::BadCode)?;
+        let value = value_parser(&b[COMPLETION_HEADER_SZ..], b[3])?;
+        Ok(Completion { seq, code, value })
+    }
+}
+
+/// Payload of `CAS` request
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Cas<'a> {
+    pub expected: Value<'a>,
+    pub new: Value<'a>,
+}
+
+impl<'a> TryFrom<Value<'a>> for Cas<'a> {
+    type Error = ParserError;
+
+    fn try_from(v: Value<'a>) -> Result<Self, Self::Error> {
+        let b = match v {
+            Value::BYTES(b) if b.len() >= CAS_HEADER_SZ => b,
+            _ => return Err(ParserError::BadValue),
+        };
+        let (ty, expected_sz) = (b[0], b[1] as usize);
+        if b.len() < CAS_HEADER_SZ + expected_sz {
+            return Err(ParserError::BadPayloadSz);
+        }
+        let (expected, new) = b[CAS_HEADER_SZ..].split_at(expected_sz);
+        Ok(Cas {
+            expected: value_parser(expected, ty)?,
+            new: value_parser(new, ty)?,
+        })
+    }
+}
+
+/// Payload of chunk requests and `OK_READ_CHUNK` answer
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Chunk<'a> {
+    pub offset: u32,
+    /// Max data size in READ_CHUNK request, total value size otherwise
+    pub size: u32,
+    pub ty: TypeTag,
+    pub data: &'a [u8],
+}
+
+impl<'a> TryFrom<Value<'a>> for Chunk<'a> {
+    type Error = ParserError;
+
+    fn try_from(v: Value<'a>) -> Result<Self, Self::Error> {
+        let b = match v {
+            Value::BYTES(b) if b.len() >= CHUNK_HEADER_SZ => b,
+            _ => return Err(ParserError::BadValue),
+        };
+        Ok(Chunk {
+            offset: read_scalar(&b[0..4])?,
+            size: read_scalar(&b[4..8])?,
+            ty: TypeTag::try_from(b[8]).map_err(|_| ParserError::BadTypeID)?,
+            data: &b[CHUNK_HEADER_SZ..],
+        })
+    }
+}
+
 pub enum ParseState {
     ParsingHeader,
     ParsingPath,
@@ -102,6 +185,23 @@ impl ParseMsg {
     }
 }
 
+#[cfg(feature = "auth")]
+impl ParseMsg {
+    /// Parse frame signed by `frames` peer, tag and counter are checked before anything else
+    pub fn try_parse_signed<'a, S: crate::persist::Storage>(&mut self, i: &'a [u8], frames: &mut crate::auth::FrameAuth<S>) -> ParseResult<'a> {
+        let header = header_parser(i)?;
+        if header.sign != SIGN_AUTH {
+            return Err(ParserError::BadAuth);
+        }
+        let sz = HEADER_SZ + header.path_sz as usize + header.payload_sz as usize;
+        if i.len() < sz + FRAME_TRAILER_SZ {
+            return Err(ParserError::NeedMoreData);
+        }
+        frames.verify(&i[..sz + FRAME_TRAILER_SZ])?;
+        self.try_parse(&i[..sz])
+    }
+}
+
 #[inline(always)]
 fn header_parser(i: &[u8]) -> Result<&Header, ParserError> {
     if i.len() >= HEADER_SZ {
@@ -120,19 +220,13 @@ fn value_parser<'a>(payload: &'a [u8], ty_id: u8) -> Result<Value<'a>, ParserErr
 
     match ty_id {
         UNIT => Ok(Value::UNIT(())),
-        BOOL => Ok(Value::BOOL({
-            if payload[0] == 0 {
-                false
-            } else {
-                true
-            }
-        })),
-        I32 => Ok(Value::I32( unsafe { *(payload.as_ptr() as *const _) } )),
-        I16 => Ok(Value::I16( unsafe { *(payload.as_ptr() as *const _) } )),
-        I8  => Ok(Value::I8(  unsafe { *(payload.as_ptr() as *const _) } )),
-        U32 => Ok(Value::U32( unsafe { *(payload.as_ptr() as *const _) } )),
-        U16 => Ok(Value::U16( unsafe { *(payload.as_ptr() as *const _) } )),
-        U8  => Ok(Value::U8(  unsafe { *(payload.as_ptr() as *const _) } )),
+        BOOL => Ok(Value::BOOL(read_scalar::<u8>(payload)? != 0)),
+        I32 => Ok(Value::I32( read_scalar(payload)? )),
+        I16 => Ok(Value::I16( read_scalar(payload)? )),
+        I8  => Ok(Value::I8(  read_scalar(payload)? )),
+        U32 => Ok(Value::U32( read_scalar(payload)? )),
+        U16 => Ok(Value::U16( read_scalar(payload)? )),
+        U8  => Ok(Value::U8(  read_scalar(payload)? )),
         STR => Ok(Value::STR({
             unsafe {
                 core::str::from_utf8_unchecked(core::slice::from_raw_parts(
@@ -147,6 +241,15 @@ fn value_parser<'a>(payload: &'a [u8], ty_id: u8) -> Result<Value<'a>, ParserErr
     }
 }
 
+// Payload follows path of arbitrary length, so it is not aligned
+#[inline(always)]
+fn read_scalar<T: Copy>(payload: &[u8]) -> Result<T, ParserError> {
+    if payload.len() < core::mem::size_of::<T>() {
+        return Err(ParserError::BadValue);
+    }
+    Ok(unsafe { core::ptr::read_unaligned(payload.as_ptr() as *const T) })
+}
+
 #[cfg(feature = "std")]
 pub mod owned {
     use serde::{Deserialize, Serialize};
@@ -0,0 +1,303 @@
+//! Storage image of `@persist` registers
+//!
+//! Image is a header followed by one WRITE request frame per register,
+//! codegen builds `persist_save`/`persist_load` on top of it. Entries are
+//! found by path, so image written by other scheme revision still loads:
+//! unknown, retyped or out of range entries are skipped and registers
+//! missing from image keep their values.
+//!
+//! ```text
+//! | MAGIC | FORMAT | 0 | SCHEME_MAJOR | SCHEME_MINOR | BODY_SZ | CRC32 | entries... |
+//! |  4    |   1    | 1 |      2       |      2       |    2    |   4   |  BODY_SZ   |
+//! ```
+//!
+//! Header numbers are little endian, CRC-32 (IEEE) covers header before it and
+//! the body. Entry payloads are native endian, like on the wire.
+
+use crate::parser::{Msg, ParseMsg, ParserError};
+use crate::protocol::*;
+use crate::ty::Value;
+
+pub const IMAGE_MAGIC: [u8; 4] = *b"EL2P";
+/// Layout version of the image itself, scheme version is stored apart
+pub const IMAGE_FORMAT: u8 = 1;
+pub const IMAGE_HEADER_SZ: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PersistError {
+    /// Image doesn't fit the buffer
+    Size,
+    /// Nothing is stored: erased or foreign data
+    Magic,
+    /// Image of unknown layout version
+    Format(u8),
+    Crc,
+    Entry(ParserError),
+    /// Value of register couldn't be read for saving
+    Read(AnswerCode),
+}
+
+/// Backing medium of the image: flash page, EEPROM, file
+pub trait Storage {
+    type Error;
+
+    /// Read stored image into `buf` and return its size, 0 if nothing is stored
+    fn load(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+    /// Replace stored image with `image`
+    fn store(&mut self, image: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// Keeps nothing, loads find empty storage
+impl Storage for () {
+    type Error = core::convert::Infallible;
+
+    fn load(&mut self, _: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(0)
+    }
+
+    fn store(&mut self, _: &[u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// CRC-32 (IEEE 802.3) continued from `crc`, start with 0
+pub fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for b in data {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & 0u32.wrapping_sub(crc & 1));
+        }
+    }
+    !crc
+}
+
+/// Builds image entry by entry
+pub struct ImageWriter<'b> {
+    buf: &'b mut [u8],
+    pos: usize,
+}
+
+impl<'b> ImageWriter<'b> {
+    /// Image for scheme `version` in `buf`
+    pub fn new(buf: &'b mut [u8], version: (u16, u16)) -> Result<Self, PersistError> {
+        if buf.len() < IMAGE_HEADER_SZ {
+            return Err(PersistError::Size);
+        }
+        buf[..4].copy_from_slice(&IMAGE_MAGIC);
+        buf[4] = IMAGE_FORMAT;
+        buf[5] = 0;
+        buf[6..8].copy_from_slice(&version.0.to_le_bytes());
+        buf[8..10].copy_from_slice(&version.1.to_le_bytes());
+        Ok(Self { buf, pos: IMAGE_HEADER_SZ })
+    }
+
+    pub fn push(&mut self, path: &str, v: Value<'_>) -> Result<(), PersistError> {
+        let payload_sz = <&[u8]>::from(&v).len();
+        if path.len() > MAX_PATH_SZ || payload_sz > MAX_PAYLOAD_SZ {
+            return Err(PersistError::Size);
+        }
+        let sz = HEADER_SZ + path.len() + payload_sz;
+        if self.pos + sz > self.buf.len() || self.pos + sz - IMAGE_HEADER_SZ > u16::MAX as usize {
+            return Err(PersistError::Size);
+        }
+        RequestBuilder::new(&mut self.buf[self.pos..])
+            .code(RequestCode::WRITE)
+            .path(path)
+            .payload(v)
+            .build()
+            .map_err(|_| PersistError::Size)?;
+        self.pos += sz;
+        Ok(())
+    }
+
+    /// Seal the image with size and CRC, returns image size
+    pub fn finish(self) -> usize {
+        let body_sz = (self.pos - IMAGE_HEADER_SZ) as u16;
+        self.buf[10..12].copy_from_slice(&body_sz.to_le_bytes());
+        let crc = crc32(crc32(0, &self.buf[..12]), &self.buf[IMAGE_HEADER_SZ..self.pos]);
+        self.buf[12..16].copy_from_slice(&crc.to_le_bytes());
+        self.pos
+    }
+}
+
+/// Checked image, iterates over stored `(path, value)` entries
+#[derive(Debug, Clone)]
+pub struct ImageReader<'a> {
+    version: (u16, u16),
+    body: &'a [u8],
+}
+
+impl<'a> ImageReader<'a> {
+    pub fn new(image: &'a [u8]) -> Result<Self, PersistError> {
+        if image.len() < IMAGE_HEADER_SZ || image[..4] != IMAGE_MAGIC {
+            return Err(PersistError::Magic);
+        }
+        if image[4] != IMAGE_FORMAT {
+            return Err(PersistError::Format(image[4]));
+        }
+        let le = |i: usize| u16::from_le_bytes([image[i], image[i + 1]]);
+        let end = IMAGE_HEADER_SZ + le(10) as usize;
+        if end > image.len() {
+            return Err(PersistError::Size);
+        }
+        let crc = u32::from_le_bytes([image[12], image[13], image[14], image[15]]);
+        if crc32(crc32(0, &image[..12]), &image[IMAGE_HEADER_SZ..end]) != crc {
+            return Err(PersistError::Crc);
+        }
+        Ok(Self {
+            version: (le(6), le(8)),
+            body: &image[IMAGE_HEADER_SZ..end],
+        })
+    }
+
+    /// Scheme version image was written with
+    pub fn version(&self) -> (u16, u16) {
+        self.version
+    }
+}
+
+impl<'a> Iterator for ImageReader<'a> {
+    type Item = Result<(&'a str, Value<'a>), PersistError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.body.is_empty() {
+            return None;
+        }
+        let entry = match self.body {
+            [_, path_sz, payload_sz, ..] => HEADER_SZ + *path_sz as usize + *payload_sz as usize,
+            _ => usize::MAX,
+        };
+        let res = match self.body.get(..entry).map(|b| ParseMsg::new().try_parse(b)) {
+            Some(Ok(Msg(code, path, v))) if code == AnswerCode::from(RequestCode::WRITE) => Ok((path, v)),
+            Some(Ok(_)) => Err(PersistError::Entry(ParserError::BadCode)),
+            Some(Err(e)) => Err(PersistError::Entry(e)),
+            None => Err(PersistError::Entry(ParserError::NeedMoreData)),
+        };
+        // Entry sizes can't be trusted after a bad one
+        self.body = match res {
+            Ok(_) => &self.body[entry..],
+            Err(_) => &[],
+        };
+        Some(res)
+    }
+}
+
+/// Image kept in a file, replaced atomically through a temporary next to it
+#[cfg(feature = "std")]
+pub struct FileStorage {
+    path: std::path::PathBuf,
+}
+
+#[cfg(feature = "std")]
+impl FileStorage {
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Storage for FileStorage {
+    type Error = std::io::Error;
+
+    fn load(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        use std::io::Read;
+
+        let mut file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let mut sz = 0;
+        while sz < buf.len() {
+            match file.read(&mut buf[sz..])? {
+                0 => break,
+                n => sz += n,
+            }
+        }
+        Ok(sz)
+    }
+
+    fn store(&mut self, image: &[u8]) -> Result<(), Self::Error> {
+        use std::io::Write;
+
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(image)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(buf: &mut [u8]) -> usize {
+        let mut w = ImageWriter::new(buf, (1, 2)).unwrap();
+        w.push("/conf/time", Value::U32(60)).unwrap();
+        w.push("/survey/name", Value::STR("Ivan")).unwrap();
+        w.finish()
+    }
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(0, b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(crc32(0, b"1234"), b"56789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn image_roundtrip() {
+        let mut buf = [0u8; 64];
+        let sz = image(&mut buf);
+        assert_eq!(sz, IMAGE_HEADER_SZ + (HEADER_SZ + 10 + 4) + (HEADER_SZ + 12 + 4));
+
+        let mut r = ImageReader::new(&buf[..sz]).unwrap();
+        assert_eq!(r.version(), (1, 2));
+        assert_eq!(r.next(), Some(Ok(("/conf/time", Value::U32(60)))));
+        assert_eq!(r.next(), Some(Ok(("/survey/name", Value::STR("Ivan")))));
+        assert_eq!(r.next(), None);
+
+        // Trailing bytes of storage are ignored
+        assert_eq!(ImageReader::new(&buf).unwrap().count(), 2);
+
+        let mut small = [0u8; 40];
+        let mut w = ImageWriter::new(&mut small, (1, 2)).unwrap();
+        w.push("/conf/time", Value::U32(60)).unwrap();
+        assert_eq!(w.push("/survey/name", Value::STR("Ivan")), Err(PersistError::Size));
+    }
+
+    #[test]
+    fn image_damage() {
+        let mut buf = [0u8; 64];
+        let sz = image(&mut buf);
+
+        assert_eq!(ImageReader::new(&[0xFF; 64]).err(), Some(PersistError::Magic));
+        assert_eq!(ImageReader::new(&buf[..sz - 1]).err(), Some(PersistError::Size));
+
+        let mut bad = buf;
+        bad[sz - 1] ^= 1;
+        assert_eq!(ImageReader::new(&bad[..sz]).err(), Some(PersistError::Crc));
+
+        let mut bad = buf;
+        bad[4] = IMAGE_FORMAT + 1;
+        assert_eq!(ImageReader::new(&bad[..sz]).err(), Some(PersistError::Format(IMAGE_FORMAT + 1)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn file_storage() {
+        let path = std::env::temp_dir().join(format!("ellocopo2-persist-{}", std::process::id()));
+        let mut storage = FileStorage::new(&path);
+        let mut buf = [0u8; 64];
+        assert_eq!(storage.load(&mut buf).unwrap(), 0);
+
+        let sz = image(&mut buf);
+        storage.store(&buf[..sz]).unwrap();
+        let mut loaded = [0u8; 64];
+        assert_eq!(storage.load(&mut loaded).unwrap(), sz);
+        assert_eq!(loaded[..sz], buf[..sz]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
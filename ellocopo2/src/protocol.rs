@@ -1,11 +1,13 @@
 #![allow(non_camel_case_types)]
 
+use core::fmt;
 use core::mem::size_of;
 use core::convert::{From, TryFrom};
 
 use num_enum::TryFromPrimitive;
 
 use crate::ty::{Value, TypeTag};
+use crate::parser::ParserError;
 
 pub const MAX_MSG_SZ: usize = 512;
 pub const HEADER_SZ: usize = size_of::<Header>();
@@ -15,6 +17,8 @@ pub const MAX_PAYLOAD_SZ: usize = MAX_MSG_SZ / 2 - 1;
 
 // Signature and protocol version
 pub const SIGN: u8 = 0x8E;
+// Signature of frame followed by authentication trailer
+pub const SIGN_AUTH: u8 = 0x8F;
 
 // REQUEST:
 //  Client -> Server
@@ -39,6 +43,26 @@ pub const SIGN: u8 = 0x8E;
 pub enum RequestCode {
     READ = 0,
     WRITE = 1,
+    /// Restore register or whole section to `@default` values, answered with `OK_RESET`
+    RESET = 11,
+    /// Write only if current value equals expected one, payload built by `RequestBuilder::cas`
+    CAS = 12,
+    /// Write, then answer with value actually stored
+    WRITE_VERIFY = 13,
+    /// Open transaction, following writes of `@txn` registers are staged
+    BEGIN = 15,
+    /// Validate and apply staged writes at once
+    COMMIT = 16,
+    /// Drop staged writes
+    ABORT = 17,
+    /// Read part of BYTES/STR value, payload built by `RequestBuilder::read_chunk`
+    READ_CHUNK = 19,
+    /// Write part of BYTES/STR value, payload built by `RequestBuilder::write_chunk`
+    WRITE_CHUNK = 20,
+    /// Ask for nonce to raise session to `PrivLvl` given as U8 payload
+    CHALLENGE = 22,
+    /// Answer challenge with HMAC as BYTES payload, UNIT payload drops session to NORMAL_LVL
+    AUTH = 23,
 }
 
 #[repr(u8)]
@@ -52,6 +76,32 @@ pub enum AnswerCode {
     ERR_ACCESS = 5,
     ERR_TYPE = 6,
     ERR_PRIV = 7,
+    OK_PENDING = 8,
+    ASYNC_DONE = 9,
+    ERR_BUSY = 10,
+    OK_RESET = 11,
+    OK_CAS = 12,
+    OK_WRITE_VERIFY = 13,
+    /// CAS expected value differs, payload is the current value
+    ERR_MISMATCH = 14,
+    OK_BEGIN = 15,
+    OK_COMMIT = 16,
+    OK_ABORT = 17,
+    /// BEGIN inside transaction, COMMIT or ABORT outside of it, request
+    /// writing around staging inside of it
+    ERR_TXN = 18,
+    OK_READ_CHUNK = 19,
+    OK_WRITE_CHUNK = 20,
+    /// Chunk out of order or out of value bounds, or value too big to answer without chunks
+    ERR_CHUNK = 21,
+    /// Payload is nonce to sign
+    OK_CHALLENGE = 22,
+    /// Payload is session level granted
+    OK_AUTH = 23,
+    /// Level can't be granted, no pending challenge or bad HMAC
+    ERR_AUTH = 24,
+    /// Written value breaks register `@min`/`@max`/`@maxlen`/`@oneof`
+    ERR_RANGE = 25,
     ERR_UNIMPL = 253,
     ERR_CUSTOM = 254,
 }
@@ -70,9 +120,10 @@ pub struct Header {
 pub struct RequestBuilder<'a> {
     buf: &'a mut [u8],
     path: Option<&'a str>,
-    payload: &'a [u8],
+    payload: Value<'a>,
+    expected: Option<Value<'a>>,
+    chunk: Option<[u8; CHUNK_HEADER_SZ]>,
     req_code: Option<RequestCode>,
-    payload_ty: TypeTag,
 }
 
 impl <'a> RequestBuilder<'a> {
@@ -91,11 +142,10 @@ impl <'a> RequestBuilder<'a> {
     }
 
     pub fn payload(&mut self, value: Value<'a>) -> &mut Self {
-        self.payload = (&value).into();
-        self.payload_ty = (&value).into();
-
-        assert!(self.payload.len() <= MAX_PAYLOAD_SZ, "RequestBuilder, payload too big!");
+        let payload: &[u8] = (&value).into();
+        assert!(payload.len() <= MAX_PAYLOAD_SZ, "RequestBuilder, payload too big!");
 
+        self.payload = value;
         self
     }
 
@@ -104,14 +154,57 @@ impl <'a> RequestBuilder<'a> {
         self
     }
 
+    /// CAS request: write `new` only if register holds `expected`
+    pub fn cas(&mut self, expected: Value<'a>, new: Value<'a>) -> &mut Self {
+        let expected_sz = <&[u8]>::from(&expected).len();
+        let new_sz = <&[u8]>::from(&new).len();
+        assert!(TypeTag::from(&expected) == TypeTag::from(&new), "RequestBuilder, CAS values of different types!");
+        assert!(expected_sz <= u8::MAX as usize, "RequestBuilder, CAS expected value too big!");
+        assert!(CAS_HEADER_SZ + expected_sz + new_sz <= MAX_PAYLOAD_SZ, "RequestBuilder, payload too big!");
+
+        self.req_code = Some(RequestCode::CAS);
+        self.expected = Some(expected);
+        self.payload = new;
+        self
+    }
+
+    /// READ_CHUNK request: up to `max` bytes from `offset`, 0 for as many as fit
+    pub fn read_chunk(&mut self, offset: u32, max: u32) -> &mut Self {
+        self.req_code = Some(RequestCode::READ_CHUNK);
+        self.chunk = Some(chunk_header(offset, max, TypeTag::UNIT));
+        self.payload = Value::UNIT(());
+        self
+    }
+
+    /// WRITE_CHUNK request: `data` at `offset` of `ty` value with `total` size
+    pub fn write_chunk(&mut self, offset: u32, total: u32, ty: TypeTag, data: &'a [u8]) -> &mut Self {
+        assert!(data.len() <= MAX_CHUNK_SZ, "RequestBuilder, chunk too big!");
+
+        self.req_code = Some(RequestCode::WRITE_CHUNK);
+        self.chunk = Some(chunk_header(offset, total, ty));
+        self.payload = Value::BYTES(data);
+        self
+    }
+
     pub fn build(&mut self) -> Result<usize, &'static str> {
+        // Payload bytes borrow the value stored in builder
+        let payload: &[u8] = (&self.payload).into();
+        // CAS and chunk envelopes go ahead of payload
+        let prefix_sz = match (&self.expected, &self.chunk) {
+            (Some(expected), _) => CAS_HEADER_SZ + <&[u8]>::from(expected).len(),
+            (None, Some(_)) => CHUNK_HEADER_SZ,
+            (None, None) => 0,
+        };
         {
             let header : &mut Header = unsafe { &mut*(self.buf.as_mut_ptr() as *mut _)};
             header.sign = SIGN;
-            header.payload_sz = self.payload.len() as u8;
+            header.payload_sz = (prefix_sz + payload.len()) as u8;
             header.path_sz = self.path.ok_or("No path")?.len() as u8;
             header.code = self.req_code.ok_or("No req code")? as u8;
-            header.payload_ty = self.payload_ty as u8;
+            header.payload_ty = match prefix_sz {
+                0 => TypeTag::from(&self.payload) as u8,
+                _ => TypeTag::BYTES as u8,
+            };
         }
 
         let header : &Header = unsafe { &*(self.buf.as_ptr() as *const _)};
@@ -123,19 +216,134 @@ impl <'a> RequestBuilder<'a> {
         let path_dst = &mut self.buf[header_sz .. path_end_pos];
         path_dst.copy_from_slice(self.path.ok_or("No path")?.as_bytes());
 
-        let payload_dst = &mut self.buf[path_end_pos .. payload_end_pos];
-        payload_dst.copy_from_slice(self.payload);
+        if let Some(expected) = &self.expected {
+            let expected_b: &[u8] = expected.into();
+            self.buf[path_end_pos] = TypeTag::from(expected) as u8;
+            self.buf[path_end_pos + 1] = expected_b.len() as u8;
+            self.buf[path_end_pos + CAS_HEADER_SZ .. path_end_pos + prefix_sz].copy_from_slice(expected_b);
+        } else if let Some(chunk) = &self.chunk {
+            self.buf[path_end_pos .. path_end_pos + prefix_sz].copy_from_slice(chunk);
+        }
+
+        let payload_dst = &mut self.buf[path_end_pos + prefix_sz .. payload_end_pos];
+        payload_dst.copy_from_slice(payload);
 
         Ok(payload_end_pos)
     }
 }
 
+// CAS:
+//  Client -> Server, payload of CAS request is BYTES:
+// |  VALUE_TY  | EXPECTED_SZ |    EXPECTED      |        NEW        |
+// |:----------:|:-----------:|:----------------:|:-----------------:|
+// |   1 байт   |   1 байт    | EXPECTED_SZ байт |  остаток payload  |
+//
+//  Answer is OK_CAS, or ERR_MISMATCH with current value as payload
+//
+pub const CAS_HEADER_SZ: usize = 2;
+
+// CHUNK:
+//  payload of READ_CHUNK/WRITE_CHUNK requests and OK_READ_CHUNK answer is BYTES:
+// |  OFFSET  |   SIZE   | VALUE_TY |      DATA       |
+// |:--------:|:--------:|:--------:|:---------------:|
+// | 4 байта  | 4 байта  |  1 байт  | остаток payload |
+//
+//  READ_CHUNK request:  SIZE is max data size wanted (0 - as much as fits), no DATA
+//  OK_READ_CHUNK:       SIZE is total value size, VALUE_TY is BYTES or STR
+//  WRITE_CHUNK request: SIZE is total value size, chunks go in order starting from OFFSET 0,
+//                       value is written once the last chunk is in
+//
+pub const CHUNK_HEADER_SZ: usize = 9;
+pub const MAX_CHUNK_SZ: usize = MAX_PAYLOAD_SZ - CHUNK_HEADER_SZ;
+
+fn chunk_header(offset: u32, size: u32, ty: TypeTag) -> [u8; CHUNK_HEADER_SZ] {
+    let mut hdr = [0u8; CHUNK_HEADER_SZ];
+    hdr[0..4].copy_from_slice(&offset.to_ne_bytes());
+    hdr[4..8].copy_from_slice(&size.to_ne_bytes());
+    hdr[8] = ty as u8;
+    hdr
+}
+
+// SIGNED FRAME:
+//  Header SIGN is SIGN_AUTH, frame is followed by trailer not counted in PAYLOAD_SZ:
+// |  COUNTER  |   TAG    |
+// |:---------:|:--------:|
+// |  4 байта  |  8 байт  |
+//
+//  COUNTER grows with each frame sent, receiver drops frames with counter not above the last one.
+//  TAG is HMAC-SHA256 truncated to 8 bytes over direction byte, frame and COUNTER.
+//
+pub const FRAME_TRAILER_SZ: usize = 12;
+pub const MAX_SIGNED_MSG_SZ: usize = MAX_MSG_SZ + FRAME_TRAILER_SZ;
+
+/// Detail carried by error answer
+///
+/// On the wire: `Code` is `U16` payload, `Msg` is `STR` payload,
+/// `CodeMsg` is `BYTES` payload with 2 bytes of code followed by message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrDetail<'a> {
+    #[default]
+    None,
+    Code(u16),
+    Msg(&'a str),
+    CodeMsg(u16, &'a str),
+}
+
+/// Error answer: code plus optional detail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnswerErr<'a>(pub AnswerCode, pub ErrDetail<'a>);
+
+impl From<AnswerCode> for AnswerErr<'_> {
+    fn from(c: AnswerCode) -> Self {
+        AnswerErr(c, ErrDetail::None)
+    }
+}
+
+impl<'a> TryFrom<Value<'a>> for ErrDetail<'a> {
+    type Error = ParserError;
+
+    fn try_from(v: Value<'a>) -> Result<Self, Self::Error> {
+        match v {
+            Value::UNIT(_) => Ok(ErrDetail::None),
+            Value::U16(c) => Ok(ErrDetail::Code(c)),
+            Value::STR(m) => Ok(ErrDetail::Msg(m)),
+            Value::BYTES(b) if b.len() >= 2 => {
+                let code = u16::from_ne_bytes([b[0], b[1]]);
+                let msg = core::str::from_utf8(&b[2..]).map_err(|_| ParserError::BadValue)?;
+                Ok(ErrDetail::CodeMsg(code, msg))
+            }
+            _ => Err(ParserError::BadValue),
+        }
+    }
+}
+
+impl fmt::Display for ErrDetail<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrDetail::None => Ok(()),
+            ErrDetail::Code(c) => write!(f, "#{}", c),
+            ErrDetail::Msg(m) => write!(f, "{}", m),
+            ErrDetail::CodeMsg(c, m) => write!(f, "#{} {}", c, m),
+        }
+    }
+}
+
+impl fmt::Display for AnswerErr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            ErrDetail::None => write!(f, "{:?}", self.0),
+            d => write!(f, "{:?}: {}", self.0, d),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct AnswerBuilder<'a> {
     buf: &'a mut [u8],
     ans_code: Option<AnswerCode>,
-    payload: &'a [u8],
-    payload_ty: TypeTag,
+    payload: Value<'a>,
+    detail: ErrDetail<'a>,
+    chunk: Option<[u8; CHUNK_HEADER_SZ]>,
 }
 
 impl <'a> AnswerBuilder<'a> {
@@ -154,10 +362,46 @@ impl <'a> AnswerBuilder<'a> {
     }
 
     pub fn payload(&mut self, value: Value<'a>) -> &mut Self {
-        self.payload = (&value).into();
-        self.payload_ty = (&value).into();
+        let payload: &[u8] = (&value).into();
+        assert!(payload.len() <= MAX_PAYLOAD_SZ, "AnswerBuilder, payload too big! {}", payload.len());
+
+        self.payload = value;
+        self.chunk = None;
+        self
+    }
+
+    /// Error answer with detail, replaces payload
+    pub fn error(&mut self, err: AnswerErr<'a>) -> &mut Self {
+        let AnswerErr(code, detail) = err;
+        let msg_sz = match detail {
+            ErrDetail::Msg(m) | ErrDetail::CodeMsg(_, m) => m.len(),
+            _ => 0,
+        };
+        assert!(msg_sz + 2 <= MAX_PAYLOAD_SZ, "AnswerBuilder, error message too big! {}", msg_sz);
+
+        self.ans_code = Some(code);
+        self.payload = Value::UNIT(());
+        self.detail = detail;
+        self.chunk = None;
+        self
+    }
+
+    /// Part of BYTES/STR value of `total` size, starting at `offset`
+    pub fn chunk(&mut self, offset: u32, total: u32, ty: TypeTag, data: &'a [u8]) -> &mut Self {
+        assert!(data.len() <= MAX_CHUNK_SZ, "AnswerBuilder, chunk too big! {}", data.len());
+
+        self.chunk = Some(chunk_header(offset, total, ty));
+        self.payload = Value::BYTES(data);
+        self.detail = ErrDetail::None;
+        self
+    }
 
-        assert!(self.payload.len() <= MAX_PAYLOAD_SZ, "AnswerBuilder, payload too big! {}", self.payload.len());
+    /// Request accepted, result follows in completion with `seq`
+    pub fn pending(&mut self, seq: u16) -> &mut Self {
+        self.ans_code = Some(AnswerCode::OK_PENDING);
+        self.payload = Value::U16(seq);
+        self.detail = ErrDetail::None;
+        self.chunk = None;
         self
     }
 
@@ -166,36 +410,163 @@ impl <'a> AnswerBuilder<'a> {
         let header : &mut Header = unsafe { &mut*(self.buf.as_mut_ptr() as *mut _)};
         let header_sz = core::mem::size_of::<Header>();
 
+        // Answer to signed request is sent unsigned unless sealed later
+        header.sign = SIGN;
         // Update answer code if needed
         if let Some(code) = self.ans_code {
             header.code = code as u8;
         }
 
-        if let TypeTag::UNIT = self.payload_ty {
-            header.payload_ty = TypeTag::UNIT as u8;
-            header.payload_sz = 0;
-            header_sz + header.path_sz as usize
-        } else {
-            header.payload_ty = self.payload_ty as u8;
-            header.payload_sz = self.payload.len() as u8;
+        let path_end_pos = header_sz + (header.path_sz as usize);
 
-            let path_end_pos = header_sz + (header.path_sz as usize);
-            let payload_end_pos = path_end_pos + (header.payload_sz as usize);
+        let (payload_ty, payload_sz) = match self.chunk {
+            Some(chunk) => {
+                self.buf[path_end_pos .. path_end_pos + CHUNK_HEADER_SZ].copy_from_slice(&chunk);
+                let data: &[u8] = (&self.payload).into();
+                self.buf[path_end_pos + CHUNK_HEADER_SZ .. path_end_pos + CHUNK_HEADER_SZ + data.len()].copy_from_slice(data);
+                (TypeTag::BYTES, CHUNK_HEADER_SZ + data.len())
+            }
+            None => write_payload(&mut self.buf[path_end_pos..], &self.payload, self.detail),
+        };
 
-            let payload_dst = &mut self.buf[path_end_pos .. payload_end_pos];
-            payload_dst.copy_from_slice(self.payload);
+        let header : &mut Header = unsafe { &mut*(self.buf.as_mut_ptr() as *mut _)};
+        header.payload_ty = payload_ty as u8;
+        header.payload_sz = payload_sz as u8;
 
-            payload_end_pos
+        path_end_pos + payload_sz
+    }
+}
+
+/// Writes answer payload: value or error detail if set
+fn write_payload(dst: &mut [u8], payload: &Value, detail: ErrDetail) -> (TypeTag, usize) {
+    match detail {
+        ErrDetail::None => {
+            let payload_b: &[u8] = payload.into();
+            dst[.. payload_b.len()].copy_from_slice(payload_b);
+            (TypeTag::from(payload), payload_b.len())
+        }
+        ErrDetail::Code(c) => {
+            dst[.. 2].copy_from_slice(&c.to_ne_bytes());
+            (TypeTag::U16, 2)
+        }
+        ErrDetail::Msg(m) => {
+            dst[.. m.len()].copy_from_slice(m.as_bytes());
+            (TypeTag::STR, m.len())
+        }
+        ErrDetail::CodeMsg(c, m) => {
+            dst[.. 2].copy_from_slice(&c.to_ne_bytes());
+            dst[2 .. 2 + m.len()].copy_from_slice(m.as_bytes());
+            (TypeTag::BYTES, 2 + m.len())
         }
     }
 }
 
+// COMPLETION:
+//  Server -> Client, finishes request answered with OK_PENDING
+//  Frame with ANS_CODE = ASYNC_DONE, request PATH and BYTES payload:
+// |    SEQ    | ANS_CODE  | PAYLOAD_TY  |     PAYLOAD          |
+// |:---------:|:---------:|:-----------:|:--------------------:|
+// |  2 байта  |  1 байт   |    1 байт   |  PAYLOAD_SZ - 4 байт |
+//
+pub const COMPLETION_HEADER_SZ: usize = 4;
+
+#[derive(Default)]
+pub struct CompletionBuilder<'a> {
+    buf: &'a mut [u8],
+    path: Option<&'a str>,
+    seq: u16,
+    ans_code: Option<AnswerCode>,
+    payload: Value<'a>,
+    detail: ErrDetail<'a>,
+}
+
+impl <'a> CompletionBuilder<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            ..
+            Default::default()
+        }
+    }
+
+    pub fn path(&mut self, path: &'a str) -> &mut Self {
+        assert!(path.len() <= MAX_PATH_SZ, "CompletionBuilder, path too big!");
+        self.path = Some(path);
+        self
+    }
+
+    pub fn seq(&mut self, seq: u16) -> &mut Self {
+        self.seq = seq;
+        self
+    }
+
+    pub fn code(&mut self, code : AnswerCode) -> &mut Self {
+        self.ans_code = Some(code);
+        self
+    }
+
+    pub fn payload(&mut self, value: Value<'a>) -> &mut Self {
+        let payload: &[u8] = (&value).into();
+        assert!(payload.len() + COMPLETION_HEADER_SZ <= MAX_PAYLOAD_SZ, "CompletionBuilder, payload too big!");
+
+        self.payload = value;
+        self
+    }
+
+    pub fn error(&mut self, err: AnswerErr<'a>) -> &mut Self {
+        let msg_sz = match err.1 {
+            ErrDetail::Msg(m) | ErrDetail::CodeMsg(_, m) => m.len(),
+            _ => 0,
+        };
+        assert!(msg_sz + 2 + COMPLETION_HEADER_SZ <= MAX_PAYLOAD_SZ, "CompletionBuilder, error message too big! {}", msg_sz);
+
+        self.ans_code = Some(err.0);
+        self.payload = Value::UNIT(());
+        self.detail = err.1;
+        self
+    }
+
+    pub fn build(&mut self) -> Result<usize, &'static str> {
+        let path = self.path.ok_or("No path")?;
+        let code = self.ans_code.ok_or("No answer code")?;
+
+        let path_end_pos = HEADER_SZ + path.len();
+        let inner_pos = path_end_pos + COMPLETION_HEADER_SZ;
+
+        self.buf[HEADER_SZ .. path_end_pos].copy_from_slice(path.as_bytes());
+        let (payload_ty, payload_sz) = write_payload(&mut self.buf[inner_pos..], &self.payload, self.detail);
+
+        self.buf[path_end_pos .. path_end_pos + 2].copy_from_slice(&self.seq.to_ne_bytes());
+        self.buf[path_end_pos + 2] = code as u8;
+        self.buf[path_end_pos + 3] = payload_ty as u8;
+
+        let header : &mut Header = unsafe { &mut*(self.buf.as_mut_ptr() as *mut _)};
+        header.sign = SIGN;
+        header.path_sz = path.len() as u8;
+        header.payload_sz = (COMPLETION_HEADER_SZ + payload_sz) as u8;
+        header.code = AnswerCode::ASYNC_DONE as u8;
+        header.payload_ty = TypeTag::BYTES as u8;
+
+        Ok(inner_pos + payload_sz)
+    }
+}
+
 impl From<RequestCode> for AnswerCode {
     fn from(c: RequestCode) -> Self {
         use RequestCode::*;
         match c {
             READ => AnswerCode::OK_READ,
             WRITE => AnswerCode::OK_WRITE,
+            RESET => AnswerCode::OK_RESET,
+            CAS => AnswerCode::OK_CAS,
+            WRITE_VERIFY => AnswerCode::OK_WRITE_VERIFY,
+            BEGIN => AnswerCode::OK_BEGIN,
+            COMMIT => AnswerCode::OK_COMMIT,
+            ABORT => AnswerCode::OK_ABORT,
+            READ_CHUNK => AnswerCode::OK_READ_CHUNK,
+            WRITE_CHUNK => AnswerCode::OK_WRITE_CHUNK,
+            CHALLENGE => AnswerCode::OK_CHALLENGE,
+            AUTH => AnswerCode::OK_AUTH,
         }
     }
 }
@@ -207,6 +578,16 @@ impl TryFrom<AnswerCode> for RequestCode {
         match c {
             OK_READ => Ok(RequestCode::READ),
             OK_WRITE => Ok(RequestCode::WRITE),
+            OK_RESET => Ok(RequestCode::RESET),
+            OK_CAS => Ok(RequestCode::CAS),
+            OK_WRITE_VERIFY => Ok(RequestCode::WRITE_VERIFY),
+            OK_BEGIN => Ok(RequestCode::BEGIN),
+            OK_COMMIT => Ok(RequestCode::COMMIT),
+            OK_ABORT => Ok(RequestCode::ABORT),
+            OK_READ_CHUNK => Ok(RequestCode::READ_CHUNK),
+            OK_WRITE_CHUNK => Ok(RequestCode::WRITE_CHUNK),
+            OK_CHALLENGE => Ok(RequestCode::CHALLENGE),
+            OK_AUTH => Ok(RequestCode::AUTH),
             _ => Err(())
         }
     }
@@ -450,8 +831,57 @@ mod test {
             ]
         );
     }
-}
 
+    #[test]
+    fn test_error_detail() {
+        let mut buf = [0u8; 0x40];
 
+        let details = [
+            ErrDetail::None,
+            ErrDetail::Code(0x1234),
+            ErrDetail::Msg("flash busy"),
+            ErrDetail::CodeMsg(7, "flash busy"),
+        ];
+
+        for detail in details.iter() {
+            let _ = RequestBuilder::new(&mut buf)
+                .code(RequestCode::WRITE)
+                .path("path")
+                .payload(Value::U32(1))
+                .build();
+            let ans_sz = AnswerBuilder::new(&mut buf)
+                .error(AnswerErr(AnswerCode::ERR_CUSTOM, *detail))
+                .build();
+
+            let msg = crate::parser::ParseMsg::new().try_parse(&buf[..ans_sz]).unwrap();
+            assert_eq!(msg.0, AnswerCode::ERR_CUSTOM);
+            assert_eq!(msg.1, "path");
+            assert_eq!(ErrDetail::try_from(msg.2).unwrap(), *detail);
+        }
 
+        assert_eq!(ErrDetail::try_from(Value::BYTES(&[1])), Err(ParserError::BadValue));
+    }
 
+    #[test]
+    fn test_cas_request() {
+        let mut buf = [0u8; MAX_MSG_SZ];
+        let sz = RequestBuilder::new(&mut buf)
+            .path("/io/file/pos")
+            .cas(Value::U32(10), Value::U32(20))
+            .build().unwrap();
+
+        let msg = crate::parser::ParseMsg::new().try_parse(&buf[..sz]).unwrap();
+        assert_eq!(RequestCode::try_from(msg.0), Ok(RequestCode::CAS));
+        assert_eq!(msg.1, "/io/file/pos");
+        assert_eq!(crate::parser::Cas::try_from(msg.2), Ok(crate::parser::Cas { expected: Value::U32(10), new: Value::U32(20) }));
+
+        let sz = RequestBuilder::new(&mut buf)
+            .path("/dbg/last")
+            .cas(Value::STR("old"), Value::STR("newer"))
+            .build().unwrap();
+        let msg = crate::parser::ParseMsg::new().try_parse(&buf[..sz]).unwrap();
+        assert_eq!(crate::parser::Cas::try_from(msg.2), Ok(crate::parser::Cas { expected: Value::STR("old"), new: Value::STR("newer") }));
+
+        assert_eq!(crate::parser::Cas::try_from(Value::BYTES(&[TypeTag::U8 as u8, 5, 1])), Err(ParserError::BadPayloadSz));
+    }
+}
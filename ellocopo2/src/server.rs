@@ -0,0 +1,864 @@
+//! Request/answer server loop over `embedded-io` transport
+//!
+//! Frames are read one by one: header first, then path and payload
+//! of the sizes announced by the header. Each request is passed to the
+//! application callback, usually built around the generated `req2msg`,
+//! `app` implements generated `Handler` for `@fast` registers:
+//!
+//! ```ignore
+//! let mut server = Server::new(uart);
+//! server.serve(|code, path, v| {
+//!     req2msg(&mut app, code, path, v, PrivLvl::NORMAL_LVL).answer(|msg| app.handle(msg))
+//! });
+//! ```
+//!
+//! `CAS` and `WRITE_VERIFY` requests never reach the callback as is,
+//! server splits them into `READ` and `WRITE` calls made back to back.
+//! The same goes for chunks: `READ_CHUNK` is cut from the whole value
+//! given by `READ`, `WRITE_CHUNK` is collected in the server's `ChunkBuf`
+//! and passed to `WRITE` once complete. Values of up to `C` bytes may be
+//! written by chunks with server created by `with_chunks`. None of these
+//! calls can be parked: such request is answered `ERR_UNIMPL` and its ticket
+//! is freed by `release`.
+//!
+//! With `auth` feature `serve_once_auth` answers `CHALLENGE` and `AUTH`
+//! requests from `Auth` state and passes session level to the callback:
+//!
+//! ```ignore
+//! server.serve_once_auth(&mut auth, ticks(), |code, path, v, lvl| {
+//!     req2msg(&mut app, code, path, v, lvl).answer(|msg| app.handle(msg))
+//! });
+//! ```
+//!
+//! Server given `FrameAuth` with `set_frame_auth` accepts signed requests
+//! only, anything else is answered with `ERR_AUTH`. Answers are signed too.
+//! Server of `FrameAuth` keeping counters in storage `S` is `Server<T, C, S>`.
+
+use core::convert::TryFrom;
+
+use crate::parser::*;
+use crate::protocol::*;
+use crate::dispatch::*;
+use crate::chunk::ChunkBuf;
+use crate::ty::{TypeTag, Value};
+use crate::persist::Storage;
+#[cfg(feature = "auth")]
+use crate::{auth::{Auth, FrameAuth}, priv_lvl::PrivLvl};
+
+#[derive(Debug)]
+pub enum ServeError<E> {
+    /// Transport error
+    Io(E),
+    /// Transport closed
+    Eof,
+    /// Stream is out of sync, answer can't be sent
+    Parse(ParserError),
+}
+
+impl<E> From<embedded_io::ReadExactError<E>> for ServeError<E> {
+    fn from(e: embedded_io::ReadExactError<E>) -> Self {
+        match e {
+            embedded_io::ReadExactError::UnexpectedEof => ServeError::Eof,
+            embedded_io::ReadExactError::Other(e) => ServeError::Io(e),
+        }
+    }
+}
+
+pub struct Server<T, const C: usize = 0, S = ()> {
+    io: T,
+    buf: [u8; MAX_SIGNED_MSG_SZ],
+    chunks: ChunkBuf<C>,
+    /// Ticket parked by part of composite request, answered `ERR_UNIMPL`
+    refused: Option<Ticket>,
+    #[cfg(feature = "auth")]
+    frames: Option<FrameAuth<S>>,
+    #[cfg(not(feature = "auth"))]
+    frames: core::marker::PhantomData<S>,
+}
+
+impl<T> Server<T> {
+    pub fn new(io: T) -> Self {
+        Self::with_chunks(io)
+    }
+}
+
+impl<T, const C: usize, S: Storage> Server<T, C, S> {
+    /// Server accepting chunked writes of values up to `C` bytes
+    pub fn with_chunks(io: T) -> Self {
+        Self {
+            io,
+            buf: [0u8; MAX_SIGNED_MSG_SZ],
+            chunks: ChunkBuf::new(),
+            refused: None,
+            #[cfg(feature = "auth")]
+            frames: None,
+            #[cfg(not(feature = "auth"))]
+            frames: core::marker::PhantomData,
+        }
+    }
+
+    /// Require requests signed with `FrameAuth` key and sign answers, `None` turns it off
+    #[cfg(feature = "auth")]
+    pub fn set_frame_auth(&mut self, frames: Option<FrameAuth<S>>) {
+        self.frames = frames;
+    }
+
+    /// Free slot of request parked while serving CAS, WRITE_VERIFY or chunk
+    /// request, which is answered `ERR_UNIMPL` instead of `OK_PENDING`
+    ///
+    /// Handlers parking requests call it after every served request,
+    /// otherwise such tickets hold `parking` slots forever.
+    pub fn release<const N: usize>(&mut self, parking: &mut Parking<N>) -> Option<Ticket> {
+        let t = self.refused.take()?;
+        parking.cancel(t);
+        Some(t)
+    }
+
+    pub fn io(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+
+    /// Answer request of `frame_sz` in `buf` with `p`, checking and signing frames if required
+    #[cfg_attr(not(feature = "auth"), allow(unused_variables))]
+    fn handle<P>(&mut self, frame_sz: usize, p: P) -> usize
+    where
+        P: FnOnce(&mut [u8], &mut ChunkBuf<C>, &mut Option<Ticket>) -> usize,
+    {
+        #[cfg(feature = "auth")]
+        if let Some(frames) = &mut self.frames {
+            let sz = match ParseMsg::new().try_parse_signed(&self.buf[..frame_sz], frames) {
+                Ok(_) => p(&mut self.buf, &mut self.chunks, &mut self.refused),
+                Err(_) => AnswerBuilder::new(&mut self.buf).error(AnswerCode::ERR_AUTH.into()).build(),
+            };
+            return frames.sign(&mut self.buf, sz);
+        }
+        p(&mut self.buf, &mut self.chunks, &mut self.refused)
+    }
+
+    /// Sign completion of `sz` in `buf` if required
+    fn seal(&mut self, sz: usize) -> usize {
+        #[cfg(feature = "auth")]
+        if let Some(frames) = &mut self.frames {
+            return frames.sign(&mut self.buf, sz);
+        }
+        sz
+    }
+}
+
+impl<T: embedded_io::Read + embedded_io::Write, const C: usize, S: Storage> Server<T, C, S> {
+    /// Serve single request
+    pub fn serve_once<'r, R, F>(&mut self, f: F) -> Result<(), ServeError<T::Error>>
+    where
+        R: Into<Reply<'r>>,
+        F: FnMut(RequestCode, &str, Value<'_>) -> R,
+    {
+        self.io.read_exact(&mut self.buf[..HEADER_SZ])?;
+        let frame_sz = frame_sz(&self.buf)?;
+        self.io.read_exact(&mut self.buf[HEADER_SZ..frame_sz])?;
+
+        let answer_sz = self.handle(frame_sz, |buf, chunks, refused| process(buf, frame_sz, chunks, refused, f));
+
+        self.io.write_all(&self.buf[..answer_sz]).map_err(ServeError::Io)?;
+        self.io.flush().map_err(ServeError::Io)
+    }
+
+    /// Send completion of parked request, `false` if ticket is not parked
+    pub fn complete<'r, E, const N: usize>(&mut self, parking: &mut Parking<N>, t: Ticket, res: Result<Value<'r>, E>) -> Result<bool, ServeError<T::Error>>
+    where
+        E: Into<AnswerErr<'r>>,
+    {
+        let sz = match parking.finish(t, res, &mut self.buf) {
+            Some(sz) => self.seal(sz),
+            None => return Ok(false),
+        };
+        self.io.write_all(&self.buf[..sz]).map_err(ServeError::Io)?;
+        self.io.flush().map_err(ServeError::Io)?;
+        Ok(true)
+    }
+
+    /// Serve requests until error
+    pub fn serve<'r, R, F>(&mut self, mut f: F) -> ServeError<T::Error>
+    where
+        R: Into<Reply<'r>>,
+        F: FnMut(RequestCode, &str, Value<'_>) -> R,
+    {
+        loop {
+            if let Err(e) = self.serve_once(&mut f) {
+                return e;
+            }
+        }
+    }
+
+    /// Serve single request of session authenticated by `auth`, `now` is current tick
+    #[cfg(feature = "auth")]
+    pub fn serve_once_auth<'r, R, F>(&mut self, auth: &mut Auth<'_>, now: u32, mut f: F) -> Result<(), ServeError<T::Error>>
+    where
+        R: Into<Reply<'r>>,
+        F: FnMut(RequestCode, &str, Value<'_>, PrivLvl) -> R,
+    {
+        self.io.read_exact(&mut self.buf[..HEADER_SZ])?;
+        let frame_sz = frame_sz(&self.buf)?;
+        self.io.read_exact(&mut self.buf[HEADER_SZ..frame_sz])?;
+
+        let answer_sz = self.handle(frame_sz, |buf, chunks, refused| match process_auth(buf, frame_sz, auth, now) {
+            Some(sz) => sz,
+            None => {
+                let lvl = auth.lvl(now);
+                process(buf, frame_sz, chunks, refused, |code, path, v| f(code, path, v, lvl))
+            }
+        });
+
+        self.io.write_all(&self.buf[..answer_sz]).map_err(ServeError::Io)?;
+        self.io.flush().map_err(ServeError::Io)
+    }
+}
+
+#[cfg(feature = "embedded-io-async")]
+impl<T: embedded_io_async::Read + embedded_io_async::Write, const C: usize, S: Storage> Server<T, C, S> {
+    /// Serve single request, async variant of `serve_once`
+    pub async fn serve_once_async<'r, R, F>(&mut self, f: F) -> Result<(), ServeError<T::Error>>
+    where
+        R: Into<Reply<'r>>,
+        F: FnMut(RequestCode, &str, Value<'_>) -> R,
+    {
+        self.io.read_exact(&mut self.buf[..HEADER_SZ]).await?;
+        let frame_sz = frame_sz(&self.buf)?;
+        self.io.read_exact(&mut self.buf[HEADER_SZ..frame_sz]).await?;
+
+        let answer_sz = self.handle(frame_sz, |buf, chunks, refused| process(buf, frame_sz, chunks, refused, f));
+
+        self.io.write_all(&self.buf[..answer_sz]).await.map_err(ServeError::Io)?;
+        self.io.flush().await.map_err(ServeError::Io)
+    }
+
+    /// Async variant of `complete`
+    pub async fn complete_async<'r, E, const N: usize>(&mut self, parking: &mut Parking<N>, t: Ticket, res: Result<Value<'r>, E>) -> Result<bool, ServeError<T::Error>>
+    where
+        E: Into<AnswerErr<'r>>,
+    {
+        let sz = match parking.finish(t, res, &mut self.buf) {
+            Some(sz) => self.seal(sz),
+            None => return Ok(false),
+        };
+        self.io.write_all(&self.buf[..sz]).await.map_err(ServeError::Io)?;
+        self.io.flush().await.map_err(ServeError::Io)?;
+        Ok(true)
+    }
+
+    /// Serve requests until error, async variant of `serve`
+    pub async fn serve_async<'r, R, F>(&mut self, mut f: F) -> ServeError<T::Error>
+    where
+        R: Into<Reply<'r>>,
+        F: FnMut(RequestCode, &str, Value<'_>) -> R,
+    {
+        loop {
+            if let Err(e) = self.serve_once_async(&mut f).await {
+                return e;
+            }
+        }
+    }
+
+    /// Async variant of `serve_once_auth`
+    #[cfg(feature = "auth")]
+    pub async fn serve_once_auth_async<'r, R, F>(&mut self, auth: &mut Auth<'_>, now: u32, mut f: F) -> Result<(), ServeError<T::Error>>
+    where
+        R: Into<Reply<'r>>,
+        F: FnMut(RequestCode, &str, Value<'_>, PrivLvl) -> R,
+    {
+        self.io.read_exact(&mut self.buf[..HEADER_SZ]).await?;
+        let frame_sz = frame_sz(&self.buf)?;
+        self.io.read_exact(&mut self.buf[HEADER_SZ..frame_sz]).await?;
+
+        let answer_sz = self.handle(frame_sz, |buf, chunks, refused| match process_auth(buf, frame_sz, auth, now) {
+            Some(sz) => sz,
+            None => {
+                let lvl = auth.lvl(now);
+                process(buf, frame_sz, chunks, refused, |code, path, v| f(code, path, v, lvl))
+            }
+        });
+
+        self.io.write_all(&self.buf[..answer_sz]).await.map_err(ServeError::Io)?;
+        self.io.flush().await.map_err(ServeError::Io)
+    }
+}
+
+/// Validate header and return full frame size, with trailer of signed frame
+fn frame_sz<E>(buf: &[u8]) -> Result<usize, ServeError<E>> {
+    let header: &Header = unsafe { &*(buf.as_ptr() as *const _) };
+    let trailer_sz = match header.sign {
+        SIGN => 0,
+        SIGN_AUTH => FRAME_TRAILER_SZ,
+        _ => return Err(ServeError::Parse(ParserError::BadHeader)),
+    };
+    if header.path_sz as usize > MAX_PATH_SZ {
+        return Err(ServeError::Parse(ParserError::BadPathSz));
+    }
+    if header.payload_sz as usize > MAX_PAYLOAD_SZ {
+        return Err(ServeError::Parse(ParserError::BadPayloadSz));
+    }
+    Ok(HEADER_SZ + header.path_sz as usize + header.payload_sz as usize + trailer_sz)
+}
+
+/// Parse request in `buf`, call `f` and build answer in place, returns answer size
+fn process<'r, R, F, const C: usize>(buf: &mut [u8], frame_sz: usize, chunks: &mut ChunkBuf<C>, refused: &mut Option<Ticket>, mut f: F) -> usize
+where
+    R: Into<Reply<'r>>,
+    F: FnMut(RequestCode, &str, Value<'_>) -> R,
+{
+    // Answer code other than the one mirroring request code
+    let mut ans_code = None;
+    // Offset and max size of READ_CHUNK
+    let mut read_chunk = None;
+    let reply = match ParseMsg::new().try_parse(&buf[..frame_sz]) {
+        Ok(Msg(code, path, v)) => match RequestCode::try_from(code) {
+            Ok(RequestCode::CAS) => match Cas::try_from(v) {
+                Ok(cas) => match unparked(f(RequestCode::READ, path, Value::UNIT(())).into(), refused) {
+                    Reply::Ok(cur) if cur == cas.expected => unparked(f(RequestCode::WRITE, path, cas.new).into(), refused),
+                    Reply::Ok(cur) => {
+                        ans_code = Some(AnswerCode::ERR_MISMATCH);
+                        Reply::Ok(cur)
+                    }
+                    r => r,
+                },
+                Err(_) => Reply::Err(AnswerCode::ERR_BAD_FORMAT.into()),
+            },
+            Ok(RequestCode::WRITE_VERIFY) => match unparked(f(RequestCode::WRITE, path, v).into(), refused) {
+                Reply::Ok(_) => match unparked(f(RequestCode::READ, path, Value::UNIT(())).into(), refused) {
+                    Reply::Ok(stored) => Reply::Ok(stored),
+                    // Written, but can't be read back
+                    _ => {
+                        ans_code = Some(AnswerCode::OK_WRITE);
+                        Reply::Ok(Value::UNIT(()))
+                    }
+                },
+                r => r,
+            },
+            Ok(RequestCode::READ_CHUNK) => match Chunk::try_from(v) {
+                Ok(c) => {
+                    read_chunk = Some((c.offset, c.size));
+                    unparked(f(RequestCode::READ, path, Value::UNIT(())).into(), refused)
+                }
+                Err(_) => Reply::Err(AnswerCode::ERR_BAD_FORMAT.into()),
+            },
+            Ok(RequestCode::WRITE_CHUNK) => match Chunk::try_from(v) {
+                Ok(c) => match chunks.push(path, c) {
+                    Ok(Some(v)) => unparked(f(RequestCode::WRITE, path, v).into(), refused),
+                    Ok(None) => Reply::Ok(Value::UNIT(())),
+                    Err(e) => Reply::Err(e.into()),
+                },
+                Err(_) => Reply::Err(AnswerCode::ERR_BAD_FORMAT.into()),
+            },
+            Ok(code) => f(code, path, v).into(),
+            Err(_) => Reply::Err(AnswerCode::ERR_BAD_PROTO.into()),
+        },
+        Err(ParserError::BadCode) => Reply::Err(AnswerCode::ERR_BAD_PROTO.into()),
+        Err(_) => Reply::Err(AnswerCode::ERR_BAD_FORMAT.into()),
+    };
+
+    let reply = match (reply, read_chunk) {
+        (Reply::Ok(v @ (Value::BYTES(_) | Value::STR(_))), Some((offset, max))) => {
+            let data: &[u8] = (&v).into();
+            if offset as usize > data.len() {
+                Reply::Err(AnswerCode::ERR_CHUNK.into())
+            } else {
+                let max = if max == 0 { MAX_CHUNK_SZ } else { MAX_CHUNK_SZ.min(max as usize) };
+                let end = data.len().min(offset as usize + max);
+                let sz = AnswerBuilder::new(buf)
+                    .chunk(offset, data.len() as u32, TypeTag::from(&v), &data[offset as usize..end])
+                    .build();
+                return sz;
+            }
+        }
+        (Reply::Ok(_), Some(_)) => Reply::Err(AnswerCode::ERR_TYPE.into()),
+        // Too big for one frame, has to be read by chunks
+        (Reply::Ok(v), None) if <&[u8]>::from(&v).len() > MAX_PAYLOAD_SZ => Reply::Err(AnswerCode::ERR_CHUNK.into()),
+        (reply, _) => reply,
+    };
+
+    let mut answer = AnswerBuilder::new(buf);
+    if let Some(code) = ans_code {
+        answer.code(code);
+    }
+    match reply {
+        Reply::Ok(v) => answer.payload(v).build(),
+        Reply::Err(e) => answer.error(e).build(),
+        Reply::Pending(t) => answer.pending(t.0).build(),
+    }
+}
+
+/// Answer `CHALLENGE` and `AUTH` requests in `buf`, `None` for the rest
+#[cfg(feature = "auth")]
+fn process_auth(buf: &mut [u8], frame_sz: usize, auth: &mut Auth<'_>, now: u32) -> Option<usize> {
+    let res = match ParseMsg::new().try_parse(&buf[..frame_sz]) {
+        Ok(Msg(code, _, v)) => match (RequestCode::try_from(code), v) {
+            (Ok(RequestCode::CHALLENGE), Value::U8(lvl)) => PrivLvl::try_from(lvl)
+                .map_err(|_| AnswerCode::ERR_AUTH)
+                .and_then(|lvl| auth.challenge(lvl))
+                .map(Some),
+            (Ok(RequestCode::AUTH), Value::BYTES(mac)) => auth.respond(mac, now).map(|_| None),
+            (Ok(RequestCode::AUTH), Value::UNIT(_)) => {
+                auth.logout();
+                Ok(None)
+            }
+            (Ok(RequestCode::CHALLENGE), _) | (Ok(RequestCode::AUTH), _) => Err(AnswerCode::ERR_BAD_FORMAT),
+            _ => return None,
+        },
+        Err(_) => return None,
+    };
+
+    let lvl = auth.lvl(now);
+    let mut answer = AnswerBuilder::new(buf);
+    Some(match res {
+        Ok(Some(nonce)) => answer.payload(Value::BYTES(&nonce)).build(),
+        Ok(None) => answer.payload(Value::U8(lvl as u8)).build(),
+        Err(e) => answer.error(e.into()).build(),
+    })
+}
+
+/// Part of CAS, WRITE_VERIFY or chunk request can't be parked, its ticket
+/// goes to `refused` for `Server::release`
+fn unparked<'r>(r: Reply<'r>, refused: &mut Option<Ticket>) -> Reply<'r> {
+    match r {
+        Reply::Pending(t) => {
+            *refused = Some(t);
+            Reply::Err(AnswerCode::ERR_UNIMPL.into())
+        }
+        r => r,
+    }
+}
+
+/// In-memory transport for testing servers on host
+#[cfg(feature = "std")]
+pub mod pipe {
+    use std::collections::VecDeque;
+    use std::vec::Vec;
+
+    /// Reads bytes pushed with `push`, collects written bytes for `take`
+    #[derive(Debug, Default)]
+    pub struct Pipe {
+        rx: VecDeque<u8>,
+        tx: Vec<u8>,
+    }
+
+    impl Pipe {
+        pub fn new() -> Self {
+            Default::default()
+        }
+
+        /// Queue bytes for reading
+        pub fn push(&mut self, data: &[u8]) {
+            self.rx.extend(data);
+        }
+
+        /// Take everything written so far
+        pub fn take(&mut self) -> Vec<u8> {
+            core::mem::take(&mut self.tx)
+        }
+    }
+
+    impl embedded_io::ErrorType for Pipe {
+        type Error = core::convert::Infallible;
+    }
+
+    impl embedded_io::Read for Pipe {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let n = buf.len().min(self.rx.len());
+            for (dst, src) in buf.iter_mut().zip(self.rx.drain(..n)) {
+                *dst = src;
+            }
+            Ok(n)
+        }
+    }
+
+    impl embedded_io::Write for Pipe {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.tx.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "embedded-io-async")]
+    impl embedded_io_async::Read for Pipe {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            embedded_io::Read::read(self, buf)
+        }
+    }
+
+    #[cfg(feature = "embedded-io-async")]
+    impl embedded_io_async::Write for Pipe {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            embedded_io::Write::write(self, buf)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::pipe::Pipe;
+    use super::*;
+
+    fn handler(code: RequestCode, path: &str, v: Value) -> Result<Value<'static>, AnswerErr<'static>> {
+        match (code, path, v) {
+            (RequestCode::READ, "/reg", _) => Ok(Value::U32(0xDEAD_BEAF)),
+            (RequestCode::WRITE, "/reg", Value::U32(0)) => Err(AnswerErr(AnswerCode::ERR_CUSTOM, ErrDetail::CodeMsg(1, "zero"))),
+            (RequestCode::WRITE, "/reg", Value::U32(_)) => Ok(Value::UNIT(())),
+            (RequestCode::WRITE, "/reg", _) => Err(AnswerCode::ERR_TYPE.into()),
+            _ => Err(AnswerCode::ERR_PATH.into()),
+        }
+    }
+
+    fn push_request(pipe: &mut Pipe, code: RequestCode, path: &str, v: Value) {
+        let mut buf = [0u8; MAX_MSG_SZ];
+        let sz = RequestBuilder::new(&mut buf)
+            .code(code)
+            .path(path)
+            .payload(v)
+            .build()
+            .unwrap();
+        pipe.push(&buf[..sz]);
+    }
+
+    fn parse_answers(data: &[u8]) -> Vec<owned::Msg> {
+        let mut answers = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            let header: &Header = unsafe { &*(data[pos..].as_ptr() as *const _) };
+            let sz = HEADER_SZ + header.path_sz as usize + header.payload_sz as usize;
+            let msg = ParseMsg::new().try_parse(&data[pos..pos + sz]).unwrap();
+            answers.push(msg.into());
+            pos += sz;
+        }
+        answers
+    }
+
+    #[test]
+    fn serve_pipe() {
+        let mut pipe = Pipe::new();
+        push_request(&mut pipe, RequestCode::READ, "/reg", Value::UNIT(()));
+        push_request(&mut pipe, RequestCode::WRITE, "/reg", Value::U32(1));
+        push_request(&mut pipe, RequestCode::WRITE, "/reg", Value::BOOL(true));
+        push_request(&mut pipe, RequestCode::READ, "/nope", Value::UNIT(()));
+        push_request(&mut pipe, RequestCode::WRITE, "/reg", Value::U32(0));
+
+        let mut server = Server::new(pipe);
+        match server.serve(handler) {
+            ServeError::Eof => (),
+            e => panic!("{:?}", e),
+        }
+
+        let answers = parse_answers(&server.io().take());
+        assert_eq!(answers, vec![
+            owned::Msg(AnswerCode::OK_READ, "/reg".to_string(), owned::Value::U32(0xDEAD_BEAF)),
+            owned::Msg(AnswerCode::OK_WRITE, "/reg".to_string(), owned::Value::UNIT(())),
+            owned::Msg(AnswerCode::ERR_TYPE, "/reg".to_string(), owned::Value::UNIT(())),
+            owned::Msg(AnswerCode::ERR_PATH, "/nope".to_string(), owned::Value::UNIT(())),
+            owned::Msg(AnswerCode::ERR_CUSTOM, "/reg".to_string(), owned::Value::BYTES(vec![1, 0, b'z', b'e', b'r', b'o'])),
+        ]);
+    }
+
+    #[test]
+    fn serve_bad_frames() {
+        let mut pipe = Pipe::new();
+        push_request(&mut pipe, RequestCode::READ, "/reg", Value::UNIT(()));
+        // Corrupt request code
+        let mut bad = [0u8; MAX_MSG_SZ];
+        let sz = RequestBuilder::new(&mut bad)
+            .code(RequestCode::READ)
+            .path("/reg")
+            .build()
+            .unwrap();
+        bad[3] = 0x42;
+        pipe.push(&bad[..sz]);
+        // Wrong signature, stream out of sync
+        pipe.push(&[0x00, 0x00, 0x00, 0x00, 0x00]);
+
+        let mut server = Server::new(pipe);
+        server.serve_once(handler).unwrap();
+        server.serve_once(handler).unwrap();
+        match server.serve_once(handler) {
+            Err(ServeError::Parse(ParserError::BadHeader)) => (),
+            e => panic!("{:?}", e),
+        }
+
+        let answers = parse_answers(&server.io().take());
+        assert_eq!(answers[1].0, AnswerCode::ERR_BAD_PROTO);
+    }
+
+    #[test]
+    fn serve_pending() {
+        let mut pipe = Pipe::new();
+        push_request(&mut pipe, RequestCode::WRITE, "/erase", Value::UNIT(()));
+
+        let mut parking = Parking::<1>::new();
+        let mut server = Server::new(pipe);
+        server.serve_once(|code, path, _| match parking.park(code, path) {
+            Ok(t) => Reply::Pending(t),
+            Err(e) => Reply::Err(e.into()),
+        }).unwrap();
+
+        let answers = parse_answers(&server.io().take());
+        assert_eq!(answers, vec![
+            owned::Msg(AnswerCode::OK_PENDING, "/erase".to_string(), owned::Value::U16(0)),
+        ]);
+
+        assert!(server.complete(&mut parking, Ticket(0), Ok::<_, AnswerCode>(Value::UNIT(()))).unwrap());
+        assert!(!server.complete(&mut parking, Ticket(0), Ok::<_, AnswerCode>(Value::UNIT(()))).unwrap());
+
+        let out = server.io().take();
+        let Msg(code, path, v) = ParseMsg::new().try_parse(&out).unwrap();
+        assert_eq!((code, path), (AnswerCode::ASYNC_DONE, "/erase"));
+        assert_eq!(Completion::try_from(v).unwrap(), Completion { seq: 0, code: AnswerCode::OK_WRITE, value: Value::UNIT(()) });
+    }
+
+    #[test]
+    fn composite_not_parked() {
+        let mut pipe = Pipe::new();
+        let mut buf = [0u8; MAX_MSG_SZ];
+        for _ in 0..3 {
+            let sz = RequestBuilder::new(&mut buf).path("/erase").cas(Value::U32(0), Value::U32(1)).build().unwrap();
+            pipe.push(&buf[..sz]);
+        }
+        push_request(&mut pipe, RequestCode::WRITE, "/erase", Value::U32(1));
+
+        let mut parking = Parking::<1>::new();
+        let mut server = Server::new(pipe);
+        for _ in 0..4 {
+            server.serve_once(|code, path, _| match parking.park(code, path) {
+                Ok(t) => Reply::Pending(t),
+                Err(e) => Reply::Err(e.into()),
+            }).unwrap();
+            server.release(&mut parking);
+        }
+
+        // Slot is free for the plain write after every CAS
+        let answers = parse_answers(&server.io().take());
+        assert_eq!(answers.iter().map(|a| a.0).collect::<Vec<_>>(), vec![
+            AnswerCode::ERR_UNIMPL, AnswerCode::ERR_UNIMPL, AnswerCode::ERR_UNIMPL, AnswerCode::OK_PENDING,
+        ]);
+        assert_eq!(server.release(&mut parking), None);
+        assert!(parking.is_parked(Ticket(3)));
+        assert!(parking.cancel(Ticket(3)));
+        assert!(!parking.cancel(Ticket(3)));
+    }
+
+    #[test]
+    fn serve_cas_and_verify() {
+        let mut pipe = Pipe::new();
+        let mut buf = [0u8; MAX_MSG_SZ];
+        for (expected, new) in [(5, 7), (5, 9)] {
+            let sz = RequestBuilder::new(&mut buf)
+                .path("/pos")
+                .cas(Value::U32(expected), Value::U32(new))
+                .build()
+                .unwrap();
+            pipe.push(&buf[..sz]);
+        }
+        push_request(&mut pipe, RequestCode::WRITE_VERIFY, "/pos", Value::U32(500));
+        push_request(&mut pipe, RequestCode::WRITE_VERIFY, "/wo", Value::U32(1));
+        push_request(&mut pipe, RequestCode::WRITE_VERIFY, "/pos", Value::U8(1));
+
+        // `/pos` is clamped to 100, `/wo` is write only
+        let mut pos = 5u32;
+        let mut server = Server::new(pipe);
+        let e = server.serve(|code, path, v| match (code, path, v) {
+            (RequestCode::READ, "/pos", _) => Ok(Value::U32(pos)),
+            (RequestCode::WRITE, "/pos", Value::U32(v)) => { pos = v.min(100); Ok(Value::UNIT(())) }
+            (RequestCode::WRITE, "/wo", _) => Ok(Value::UNIT(())),
+            (RequestCode::WRITE, _, _) => Err(AnswerErr::from(AnswerCode::ERR_TYPE)),
+            _ => Err(AnswerCode::ERR_ACCESS.into()),
+        });
+        assert!(matches!(e, ServeError::Eof));
+
+        let answers = parse_answers(&server.io().take());
+        assert_eq!(answers, vec![
+            owned::Msg(AnswerCode::OK_CAS, "/pos".to_string(), owned::Value::UNIT(())),
+            owned::Msg(AnswerCode::ERR_MISMATCH, "/pos".to_string(), owned::Value::U32(7)),
+            owned::Msg(AnswerCode::OK_WRITE_VERIFY, "/pos".to_string(), owned::Value::U32(100)),
+            owned::Msg(AnswerCode::OK_WRITE, "/wo".to_string(), owned::Value::UNIT(())),
+            owned::Msg(AnswerCode::ERR_TYPE, "/pos".to_string(), owned::Value::UNIT(())),
+        ]);
+    }
+
+    #[test]
+    fn serve_chunks() {
+        use crate::chunk::{ChunkReader, ChunkWriter};
+
+        let text: String = (0..100).map(|i| format!("line {};", i)).collect();
+        let mut buf = [0u8; MAX_MSG_SZ];
+
+        let mut pipe = Pipe::new();
+        let mut writer = ChunkWriter::new("/blob", Value::STR(&text));
+        while let Some(sz) = writer.request(&mut buf) {
+            pipe.push(&buf[..sz]);
+        }
+
+        let mut blob = String::new();
+        let mut server = Server::<_, 1024>::with_chunks(pipe);
+        let e = server.serve(|code, path, v| match (code, path, v) {
+            (RequestCode::WRITE, "/blob", Value::STR(s)) => { blob = s.to_string(); Ok(Value::UNIT(())) }
+            _ => Err(AnswerErr::from(AnswerCode::ERR_ACCESS)),
+        });
+        assert!(matches!(e, ServeError::Eof));
+        assert_eq!(blob, text);
+
+        let answers = parse_answers(&server.io().take());
+        assert_eq!(answers.len(), 4);
+        assert!(answers.iter().all(|a| a.0 == AnswerCode::OK_WRITE_CHUNK));
+
+        // Read it back by chunks
+        let mut reader = ChunkReader::new("/blob");
+        let mut server = Server::new(Pipe::new());
+        let mut read = Vec::new();
+        while let Some(sz) = reader.request(&mut buf) {
+            server.io().push(&buf[..sz]);
+            server.serve_once(|code, _, _| match code {
+                RequestCode::READ => Ok(Value::STR(&blob)),
+                _ => Err(AnswerErr::from(AnswerCode::ERR_ACCESS)),
+            }).unwrap();
+            let out = server.io().take();
+            let Msg(code, _, v) = ParseMsg::new().try_parse(&out).unwrap();
+            assert_eq!(code, AnswerCode::OK_READ_CHUNK);
+            read.extend_from_slice(reader.accept(v).unwrap().data);
+        }
+        assert_eq!(read, text.as_bytes());
+
+        // Chunks of non BYTES/STR value, past the end
+        let mut pipe = Pipe::new();
+        for path in ["/reg", "/blob"] {
+            let sz = RequestBuilder::new(&mut buf).path(path).read_chunk(5000, 0).build().unwrap();
+            pipe.push(&buf[..sz]);
+        }
+        // Too big to be read at once
+        push_request(&mut pipe, RequestCode::READ, "/blob", Value::UNIT(()));
+        let mut server = Server::new(pipe);
+        let _ = server.serve(|_, path, _| match path {
+            "/reg" => Ok::<_, AnswerCode>(Value::U32(1)),
+            _ => Ok(Value::STR(&blob)),
+        });
+        let answers = parse_answers(&server.io().take());
+        assert_eq!(answers[0].0, AnswerCode::ERR_TYPE);
+        assert_eq!(answers[1].0, AnswerCode::ERR_CHUNK);
+        assert_eq!(answers[2].0, AnswerCode::ERR_CHUNK);
+    }
+
+    #[cfg(feature = "auth")]
+    #[test]
+    fn serve_auth() {
+        use crate::auth::{mac, Auth};
+
+        const SECRET: &[u8] = b"shared secret";
+        fn handler(code: RequestCode, path: &str, _: Value, lvl: PrivLvl) -> Result<Value<'static>, AnswerErr<'static>> {
+            match (code, path) {
+                (RequestCode::READ, "/lvl") => Ok(Value::U8(lvl as u8)),
+                _ => Err(AnswerCode::ERR_PATH.into()),
+            }
+        }
+
+        let mut auth = Auth::new(SECRET, [1u8; 16], 1000);
+        let mut server = Server::new(Pipe::new());
+
+        push_request(server.io(), RequestCode::CHALLENGE, "/", Value::U8(PrivLvl::SECUR_LVL as u8));
+        server.serve_once_auth(&mut auth, 0, handler).unwrap();
+        let out = server.io().take();
+        let nonce = match ParseMsg::new().try_parse(&out).unwrap() {
+            Msg(AnswerCode::OK_CHALLENGE, _, Value::BYTES(nonce)) => nonce.to_vec(),
+            msg => panic!("{:?}", msg),
+        };
+
+        let response = mac(SECRET, &nonce, PrivLvl::SECUR_LVL);
+        for _ in 0..2 {
+            push_request(server.io(), RequestCode::AUTH, "/", Value::BYTES(&response));
+        }
+        push_request(server.io(), RequestCode::READ, "/lvl", Value::UNIT(()));
+        push_request(server.io(), RequestCode::CHALLENGE, "/", Value::U8(42));
+        push_request(server.io(), RequestCode::AUTH, "/", Value::UNIT(()));
+        push_request(server.io(), RequestCode::READ, "/lvl", Value::UNIT(()));
+        for _ in 0..6 {
+            server.serve_once_auth(&mut auth, 10, handler).unwrap();
+        }
+
+        let answers = parse_answers(&server.io().take());
+        assert_eq!(answers, vec![
+            owned::Msg(AnswerCode::OK_AUTH, "/".to_string(), owned::Value::U8(PrivLvl::SECUR_LVL as u8)),
+            // Replayed response
+            owned::Msg(AnswerCode::ERR_AUTH, "/".to_string(), owned::Value::UNIT(())),
+            owned::Msg(AnswerCode::OK_READ, "/lvl".to_string(), owned::Value::U8(PrivLvl::SECUR_LVL as u8)),
+            owned::Msg(AnswerCode::ERR_AUTH, "/".to_string(), owned::Value::UNIT(())),
+            owned::Msg(AnswerCode::OK_AUTH, "/".to_string(), owned::Value::U8(PrivLvl::NORMAL_LVL as u8)),
+            owned::Msg(AnswerCode::OK_READ, "/lvl".to_string(), owned::Value::U8(PrivLvl::NORMAL_LVL as u8)),
+        ]);
+    }
+
+    #[cfg(feature = "auth")]
+    #[test]
+    fn serve_signed() {
+        use crate::auth::FrameAuth;
+
+        const KEY: &[u8] = b"frame key";
+        let mut host = FrameAuth::host(KEY);
+        let mut buf = [0u8; MAX_SIGNED_MSG_SZ];
+        let mut signed = |pipe: &mut Pipe, code, v| {
+            let sz = RequestBuilder::new(&mut buf).code(code).path("/reg").payload(v).build().unwrap();
+            let sz = host.sign(&mut buf, sz);
+            pipe.push(&buf[..sz]);
+            buf
+        };
+
+        let mut pipe = Pipe::new();
+        let write = signed(&mut pipe, RequestCode::WRITE, Value::U32(1));
+        let sz = HEADER_SZ + 4 + 4 + FRAME_TRAILER_SZ;
+        // Replayed, tampered and unsigned writes
+        pipe.push(&write[..sz]);
+        let mut tampered = signed(&mut Pipe::new(), RequestCode::WRITE, Value::U32(2));
+        tampered[sz - FRAME_TRAILER_SZ - 1] ^= 0x01;
+        pipe.push(&tampered[..sz]);
+        push_request(&mut pipe, RequestCode::WRITE, "/reg", Value::U32(3));
+        signed(&mut pipe, RequestCode::READ, Value::UNIT(()));
+
+        let mut server = Server::new(pipe);
+        server.set_frame_auth(Some(FrameAuth::device(KEY)));
+        let e = server.serve(handler);
+        assert!(matches!(e, ServeError::Eof));
+
+        let out = server.io().take();
+        let mut pos = 0;
+        let mut answers = Vec::new();
+        while pos < out.len() {
+            let header: &Header = unsafe { &*(out[pos..].as_ptr() as *const _) };
+            let sz = HEADER_SZ + header.path_sz as usize + header.payload_sz as usize + FRAME_TRAILER_SZ;
+            let msg = ParseMsg::new().try_parse_signed(&out[pos..pos + sz], &mut host).unwrap();
+            answers.push(owned::Msg::from(msg));
+            pos += sz;
+        }
+        assert_eq!(answers, vec![
+            owned::Msg(AnswerCode::OK_WRITE, "/reg".to_string(), owned::Value::UNIT(())),
+            owned::Msg(AnswerCode::ERR_AUTH, "/reg".to_string(), owned::Value::UNIT(())),
+            owned::Msg(AnswerCode::ERR_AUTH, "/reg".to_string(), owned::Value::UNIT(())),
+            owned::Msg(AnswerCode::ERR_AUTH, "/reg".to_string(), owned::Value::UNIT(())),
+            owned::Msg(AnswerCode::OK_READ, "/reg".to_string(), owned::Value::U32(0xDEAD_BEAF)),
+        ]);
+    }
+
+    #[cfg(feature = "embedded-io-async")]
+    #[test]
+    fn serve_pipe_async() {
+        let mut pipe = Pipe::new();
+        push_request(&mut pipe, RequestCode::READ, "/reg", Value::UNIT(()));
+
+        let mut server = Server::new(pipe);
+        futures::executor::block_on(server.serve_once_async(handler)).unwrap();
+
+        let answers = parse_answers(&server.io().take());
+        assert_eq!(answers, vec![
+            owned::Msg(AnswerCode::OK_READ, "/reg".to_string(), owned::Value::U32(0xDEAD_BEAF)),
+        ]);
+    }
+}
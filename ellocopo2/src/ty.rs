@@ -42,6 +42,12 @@ pub enum TypeTag {
     BYTES = 9,    
 }
 
+impl Default for Value<'_> {
+    fn default() -> Self {
+        Value::UNIT(())
+    }
+}
+
 impl Default for TypeTag {
     fn default() -> Self {
         TypeTag::UNIT
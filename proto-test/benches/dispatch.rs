@@ -0,0 +1,68 @@
+//! Path lookup of generated dispatch: perfect hash `path2idx` against
+//! linear match over full paths, on every register of `scheme.json`
+//!
+//! `cargo bench -p proto-test`
+
+use std::hint::black_box;
+use std::time::Instant;
+
+#[allow(dead_code, unused_imports, clippy::all)]
+mod protocol {
+    pub use ellocopo2::*;
+    include!(concat!(env!("OUT_DIR"), "/codegen.rs"));
+}
+
+mod linear {
+    include!(concat!(env!("OUT_DIR"), "/linear.rs"));
+}
+
+use linear::{linear, PATHS};
+use protocol::path2idx;
+
+const ROUNDS: u32 = 2_000;
+const LOOKUPS: usize = 4096;
+const MISSES: &[&str] = &["/", "/nope", "/ctrl/nope", "/survey/name/x", "/build/"];
+
+/// Paths in pseudo random order, so branch predictor can't learn the sequence
+fn shuffled(paths: &[&'static str]) -> Vec<&'static str> {
+    let mut x = 0x2545_f491u32;
+    (0..LOOKUPS)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            paths[x as usize % paths.len()]
+        })
+        .collect()
+}
+
+fn bench(name: &str, paths: &[&str], f: fn(&str) -> Option<usize>) {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for p in paths {
+            black_box(f(black_box(p)));
+        }
+    }
+    let ns = start.elapsed().as_nanos() as f64 / (ROUNDS as f64 * paths.len() as f64);
+    println!("{:<10} {:>8.2} ns/lookup", name, ns);
+}
+
+fn main() {
+    for (i, p) in PATHS.iter().enumerate() {
+        assert_eq!(path2idx(p), Some(i), "{}", p);
+    }
+    for p in MISSES {
+        assert_eq!((path2idx(p), linear(p)), (None, None), "{}", p);
+    }
+
+    let hits = shuffled(PATHS);
+    let misses = shuffled(MISSES);
+
+    println!("{} registers, hits", PATHS.len());
+    bench("linear", &hits, linear);
+    bench("hash", &hits, path2idx);
+
+    println!("misses");
+    bench("linear", &misses, linear);
+    bench("hash", &misses, path2idx);
+}
@@ -1,8 +1,7 @@
 use std::env;
-use std::fs::read_to_string;
 use std::fs::write;
 use std::path::Path;
-use std::process::Command;
+use std::process::{exit, Command};
 
 fn main() {
 
@@ -13,9 +12,32 @@ fn main() {
         Err(_) => Path::new("../scheme.json"),
     };
 
-    let txt = read_to_string(path).unwrap();
-    let txt = ellocopo2_codegen::generate(&txt);
-    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("codegen.rs");
+    generate(path, "codegen.rs");
+    linear(path, "linear.rs");
+    // Register of every type, compiled by tests
+    generate(Path::new("all_types.json"), "all_types.rs");
+
+    println!("cargo:rerun-if-changed=scheme.json");
+    println!("cargo:rerun-if-changed=all_types.json");
+}
+
+fn generate(scheme: &Path, out: &str) {
+    // Includes are relative to the scheme file
+    let txt = match ellocopo2_codegen::generate_file(scheme) {
+        Ok(gen) => {
+            for w in gen.warnings {
+                println!("cargo:warning={}: {}", scheme.display(), w);
+            }
+            gen.code
+        }
+        Err(errors) => {
+            for e in errors {
+                eprintln!("{}: {}", scheme.display(), e);
+            }
+            exit(1);
+        }
+    };
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join(out);
     let _ = write(path.clone(), txt);
 
     // rustfmt
@@ -23,6 +45,23 @@ fn main() {
         .arg(path)
         .output()
         .expect("rustfmt: failed to execute");
+}
 
-    println!("cargo:rerun-if-changed=scheme.json");
+/// Full path match, as generated dispatch used to do it, for `benches/dispatch.rs`
+fn linear(scheme: &Path, out: &str) {
+    let schema = match ellocopo2_codegen::parser::parse_file(scheme) {
+        Ok(schema) => schema,
+        Err(_) => return, // reported by `generate`
+    };
+    let mut paths = Vec::new();
+    schema.tree.visit_regs(&mut |reg| paths.push(format!("{:?}", "/".to_string() + &reg.path.join("/"))));
+
+    let arms: String = paths.iter().enumerate()
+        .map(|(i, p)| format!("        {} => Some({}),\n", p, i))
+        .collect();
+    let txt = format!(
+        "pub const PATHS: &[&str] = &[{}];\n\npub fn linear(path: &str) -> Option<usize> {{\n    match path {{\n{}        _ => None,\n    }}\n}}\n",
+        paths.join(", "), arms,
+    );
+    let _ = write(Path::new(&env::var("OUT_DIR").unwrap()).join(out), txt);
 }
@@ -2,6 +2,28 @@
 pub mod protocol {
     pub use ellocopo2::*;
     include!(concat!(env!("OUT_DIR"), "/codegen.rs"));
+    pub use msg::Msg;
+
+    /// @fast registers of /build/*, the rest is left unimplemented
+    pub struct Build;
+
+    impl Handler for Build {
+        fn build_version_read(&mut self)  -> Result<&'static str, AnswerCode> { Ok(env!("CARGO_PKG_VERSION")) }
+        fn build_compiler_read(&mut self) -> Result<&'static str, AnswerCode> { Ok("rustc") }
+        fn build_git_read(&mut self)      -> Result<&'static str, AnswerCode> { Err(AnswerCode::ERR_UNIMPL) }
+        fn build_time_read(&mut self)     -> Result<&'static str, AnswerCode> { Err(AnswerCode::ERR_UNIMPL) }
+        fn build_target_read(&mut self)   -> Result<&'static str, AnswerCode> { Err(AnswerCode::ERR_UNIMPL) }
+        fn build_host_read(&mut self)     -> Result<&'static str, AnswerCode> { Err(AnswerCode::ERR_UNIMPL) }
+        fn build_profile_read(&mut self)  -> Result<&'static str, AnswerCode> { Err(AnswerCode::ERR_UNIMPL) }
+    }
+}
+
+/// Register of every type from `all_types.json`
+#[cfg(test)]
+pub mod all_types {
+    pub use ellocopo2::*;
+    include!(concat!(env!("OUT_DIR"), "/all_types.rs"));
+    pub use msg::Msg;
 }
 
 use protocol::*;
@@ -16,9 +38,9 @@ fn main() {
 
     let mut parser = ParseMsg::new();
 
-    let Msg(code, path, val) = parser.try_parse(&buf[.. request_sz]).unwrap();
-    
-    let msg = req2msg(code.try_into().unwrap(), path, val).unwrap();
+    let ellocopo2::Msg(code, path, val) = parser.try_parse(&buf[.. request_sz]).unwrap();
+
+    let msg = req2msg(&mut Build, code.try_into().unwrap(), path, val, PrivLvl::NORMAL_LVL).unwrap();
 
     println!("msg: {:?}", msg);
 
@@ -30,9 +52,505 @@ fn main() {
 
     let mut parser = ParseMsg::new();
 
-    let Msg(code, path, val) = parser.try_parse(&buf[.. request_sz]).unwrap();
-    
-    let msg = req2msg(code.try_into().unwrap(), path, val).unwrap();
+    let ellocopo2::Msg(code, path, val) = parser.try_parse(&buf[.. request_sz]).unwrap();
+
+    let msg = req2msg(&mut Build, code.try_into().unwrap(), path, val, PrivLvl::NORMAL_LVL).unwrap();
 
     println!("msg: {:?}", msg);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::protocol::*;
+    use ellocopo2::server::{Server, ServeError, pipe::Pipe};
+
+    /// Device without any `@fast` register implemented
+    struct Unimpl;
+
+    impl Handler for Unimpl {
+        fn build_version_read(&mut self)  -> Result<&'static str, AnswerCode> { Err(AnswerCode::ERR_UNIMPL) }
+        fn build_compiler_read(&mut self) -> Result<&'static str, AnswerCode> { Err(AnswerCode::ERR_UNIMPL) }
+        fn build_git_read(&mut self)      -> Result<&'static str, AnswerCode> { Err(AnswerCode::ERR_UNIMPL) }
+        fn build_time_read(&mut self)     -> Result<&'static str, AnswerCode> { Err(AnswerCode::ERR_UNIMPL) }
+        fn build_target_read(&mut self)   -> Result<&'static str, AnswerCode> { Err(AnswerCode::ERR_UNIMPL) }
+        fn build_host_read(&mut self)     -> Result<&'static str, AnswerCode> { Err(AnswerCode::ERR_UNIMPL) }
+        fn build_profile_read(&mut self)  -> Result<&'static str, AnswerCode> { Err(AnswerCode::ERR_UNIMPL) }
+    }
+
+    impl super::all_types::Handler for Unimpl {}
+
+    fn request(pipe: &mut Pipe, code: RequestCode, path: &str, v: Value) {
+        let mut buf = [0x00u8; MAX_MSG_SZ];
+        let sz = RequestBuilder::new(&mut buf)
+            .code(code)
+            .path(path)
+            .payload(v)
+            .build().unwrap();
+        pipe.push(&buf[..sz]);
+    }
+
+    fn answer(data: &[u8]) -> (owned::Msg, usize) {
+        let header: &Header = unsafe { &*(data.as_ptr() as *const _) };
+        let sz = HEADER_SZ + header.path_sz as usize + header.payload_sz as usize;
+        (ParseMsg::new().try_parse(&data[..sz]).unwrap().into(), sz)
+    }
+
+    #[test]
+    fn serve_generated_dispatch() {
+        let mut record = false;
+
+        let mut pipe = Pipe::new();
+        request(&mut pipe, RequestCode::WRITE, "/ctrl/record", Value::BOOL(true));
+        request(&mut pipe, RequestCode::READ, "/ctrl/record", Value::UNIT(()));
+        request(&mut pipe, RequestCode::WRITE, "/io/file/max", Value::U32(1));
+        request(&mut pipe, RequestCode::READ, "/build/compiler", Value::UNIT(()));
+        request(&mut pipe, RequestCode::WRITE, "/io/file/start", Value::UNIT(()));
+
+        let mut server = Server::new(pipe);
+        let e = server.serve(|code, path, v| {
+            req2msg(&mut Build, code, path, v, PrivLvl::NORMAL_LVL).answer(|msg| match msg {
+                Msg::Ctrl(Ctrl::Record_W(v)) => { record = v; Ok(Value::UNIT(())) }
+                Msg::Ctrl(Ctrl::Record_R) => Ok(Value::BOOL(record)),
+                Msg::Io(Io::File(File::Start_W(()))) => Err(CustomErr::Busy.msg("erasing")),
+                _ => Err(AnswerCode::ERR_UNIMPL.into()),
+            })
+        });
+        assert!(matches!(e, ServeError::Eof));
+
+        let out = server.io().take();
+        let mut pos = 0;
+        let mut answers = Vec::new();
+        while pos < out.len() {
+            let (msg, sz) = answer(&out[pos..]);
+            answers.push(msg);
+            pos += sz;
+        }
+
+        assert_eq!(answers, vec![
+            owned::Msg(AnswerCode::OK_WRITE, "/ctrl/record".to_string(), owned::Value::UNIT(())),
+            owned::Msg(AnswerCode::OK_READ, "/ctrl/record".to_string(), owned::Value::BOOL(true)),
+            owned::Msg(AnswerCode::ERR_ACCESS, "/io/file/max".to_string(), owned::Value::UNIT(())),
+            owned::Msg(AnswerCode::OK_READ, "/build/compiler".to_string(), owned::Value::STR("rustc".to_string())),
+            owned::Msg(AnswerCode::ERR_CUSTOM, "/io/file/start".to_string(), owned::Value::BYTES(b"\x01\x00erasing".to_vec())),
+        ]);
+    }
+
+    #[test]
+    fn path_lookup() {
+        for (i, p) in PATHS.iter().enumerate() {
+            assert_eq!(path2idx(p), Some(i), "{:?}", p);
+
+            // Near misses share length or tail with the real path
+            let cut = &p[..p.len() - 1];
+            let (longer, upper) = (format!("{}x", p), p.to_uppercase());
+            let other = format!("{}{}", cut, if p.ends_with('x') { 'y' } else { 'x' });
+            for miss in [cut, &longer, &upper, &other, &p[1..], &format!("/{}", p), &format!("{}/", p)] {
+                assert_eq!(path2idx(miss), PATHS.iter().position(|p| *p == miss), "{:?}", miss);
+            }
+        }
+        for p in ["", "/", "ctrl/record", "/ctrl", "/ctrl/", "/ctrl/record/", "//ctrl/record", "/ctrl/recordx", "/nope"] {
+            assert_eq!(path2idx(p), None, "{:?}", p);
+        }
+        let read = |path| req2msg(&mut Unimpl, RequestCode::READ, path, Value::UNIT(()), PrivLvl::NORMAL_LVL);
+        assert_eq!(read("/io/file/max"), DispatchResult::Msg(Msg::Io(Io::File(File::Max_R))));
+        assert_eq!(read("/io/file"), DispatchResult::Err(AnswerCode::ERR_PATH));
+    }
+
+    #[test]
+    fn fast_handler() {
+        fn read<H: Handler>(h: &mut H, path: &str) -> DispatchResult<Msg<'static>, &'static [Msg<'static>]> {
+            req2msg(h, RequestCode::READ, path, Value::UNIT(()), PrivLvl::NORMAL_LVL)
+        }
+
+        assert_eq!(read(&mut Build, "/build/compiler"), DispatchResult::Answer(Value::STR("rustc")));
+        assert_eq!(read(&mut Build, "/build/git"), DispatchResult::Err(AnswerCode::ERR_UNIMPL));
+        assert_eq!(read(&mut Unimpl, "/build/compiler"), DispatchResult::Err(AnswerCode::ERR_UNIMPL));
+        assert_eq!(req2msg(&mut Build, RequestCode::WRITE, "/build/git", Value::STR(""), PrivLvl::NORMAL_LVL), DispatchResult::Err(AnswerCode::ERR_ACCESS));
+    }
+
+    #[test]
+    fn typed_client() {
+        use std::convert::TryFrom;
+
+        #[derive(Default)]
+        struct Device { record: bool, name: String }
+
+        impl Transport for Device {
+            type Error = ParserError;
+
+            fn exchange(&mut self, req: &[u8], ans: &mut [u8]) -> Result<usize, ParserError> {
+                let ellocopo2::Msg(code, path, v) = ParseMsg::new().try_parse(req)?;
+                let code = RequestCode::try_from(code).unwrap();
+                let res: Result<Value, AnswerErr> = match req2msg(&mut Build, code, path, v, PrivLvl::NORMAL_LVL) {
+                    DispatchResult::Msg(Msg::Ctrl(Ctrl::Record_W(v))) => { self.record = v; Ok(Value::UNIT(())) }
+                    DispatchResult::Msg(Msg::Ctrl(Ctrl::Record_R)) => Ok(Value::BOOL(self.record)),
+                    DispatchResult::Msg(Msg::Survey(Survey::Name_W(v))) => { self.name = v.to_string(); Ok(Value::UNIT(())) }
+                    DispatchResult::Msg(Msg::Survey(Survey::Name_R)) => Ok(Value::STR(&self.name)),
+                    DispatchResult::Msg(Msg::Io(Io::File(File::Start_W(())))) => Err(CustomErr::Busy.into()),
+                    r => r.answer(|_| Err(AnswerCode::ERR_UNIMPL.into())),
+                };
+                let mut b = AnswerBuilder::new(ans);
+                match res {
+                    Ok(v) => b.code(code.into()).payload(v),
+                    Err(e) => b.error(e),
+                };
+                Ok(b.build())
+            }
+        }
+
+        let mut c = RegClient::new(Device::default());
+        assert_eq!(c.ctrl_record_write(true), Ok(()));
+        assert_eq!(c.ctrl_record_read(), Ok(true));
+        assert_eq!(c.survey_name_write("Ivan"), Ok(()));
+        assert_eq!(c.survey_name_read(), Ok("Ivan"));
+        assert_eq!(c.build_compiler_read(), Ok("rustc"));
+        assert_eq!(c.io_file_start_write(), Err(ClientError::Answer(AnswerCode::ERR_CUSTOM)));
+        assert_eq!(c.io_file_max_read(), Err(ClientError::Answer(AnswerCode::ERR_UNIMPL)));
+        assert_eq!(c.0.transport().name, "Ivan");
+    }
+
+    #[test]
+    fn range_constraints() {
+        fn write(path: &str, v: Value<'static>) -> DispatchResult<Msg<'static>, &'static [Msg<'static>]> {
+            req2msg(&mut Unimpl, RequestCode::WRITE, path, v, PrivLvl::NORMAL_LVL)
+        }
+
+        assert_eq!(write("/signal/ecgf/frq", Value::U8(200)), DispatchResult::Msg(Msg::Signal(Signal::Ecgf(Ecgf::Frq_W(200)))));
+        assert_eq!(write("/signal/ecgf/frq", Value::U8(0)), DispatchResult::Err(AnswerCode::ERR_RANGE));
+        assert_eq!(write("/signal/ecgf/frq", Value::U8(201)), DispatchResult::Err(AnswerCode::ERR_RANGE));
+        assert_eq!(write("/survey/sex", Value::STR("F")), DispatchResult::Msg(Msg::Survey(Survey::Sex_W("F"))));
+        assert_eq!(write("/survey/sex", Value::STR("X")), DispatchResult::Err(AnswerCode::ERR_RANGE));
+        assert_eq!(write("/survey/birth", Value::STR("01.01.19700")), DispatchResult::Err(AnswerCode::ERR_RANGE));
+        // Type is checked first
+        assert_eq!(write("/signal/ecgf/frq", Value::U16(0)), DispatchResult::Err(AnswerCode::ERR_TYPE));
+
+        /// Every request reaching it is a failure
+        struct Unreachable;
+
+        impl Transport for Unreachable {
+            type Error = ();
+
+            fn exchange(&mut self, _: &[u8], _: &mut [u8]) -> Result<usize, ()> {
+                Err(())
+            }
+        }
+
+        let mut c = RegClient::new(Unreachable);
+        assert_eq!(c.signal_ecgf_frq_write(0), Err(ClientError::Range));
+        assert_eq!(c.survey_sex_write("X"), Err(ClientError::Range));
+        assert_eq!(c.survey_name_write(&"x".repeat(65)), Err(ClientError::Range));
+        assert_eq!(c.signal_ecgf_frq_write(1), Err(ClientError::Transport(())));
+    }
+
+    #[test]
+    fn persist_image() {
+        use ellocopo2::persist::{FileStorage, ImageWriter};
+
+        #[derive(Debug, Default, PartialEq)]
+        struct Settings { time: u32, ecg_k: i32, name: String, sex: String }
+
+        impl Settings {
+            fn read(&self, m: Msg<'static>) -> Result<Value<'_>, AnswerErr<'static>> {
+                Ok(match m {
+                    Msg::Conf(Conf::Time_R) => Value::U32(self.time),
+                    Msg::Calib(Calib::Ecg(Ecg::K_R)) => Value::I32(self.ecg_k),
+                    Msg::Survey(Survey::Name_R) => Value::STR(&self.name),
+                    Msg::Survey(Survey::Sex_R) => Value::STR(&self.sex),
+                    Msg::Conf(Conf::Cyclic_R) => Value::BOOL(false),
+                    Msg::Survey(Survey::Id_R | Survey::StartTime_R) => Value::U32(0),
+                    Msg::Calib(_) => Value::I32(0),
+                    _ => Value::STR(""),
+                })
+            }
+
+            fn write(&mut self, m: Msg<'_>) -> Result<Value<'static>, AnswerErr<'static>> {
+                match m {
+                    Msg::Conf(Conf::Time_W(v)) => self.time = v,
+                    Msg::Calib(Calib::Ecg(Ecg::K_W(v))) => self.ecg_k = v,
+                    Msg::Survey(Survey::Name_W(v)) => self.name = v.to_string(),
+                    Msg::Survey(Survey::Sex_W(v)) => self.sex = v.to_string(),
+                    _ => return Err(AnswerCode::ERR_UNIMPL.into()),
+                }
+                Ok(Value::UNIT(()))
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("proto-test-persist-{}", std::process::id()));
+        let mut storage = FileStorage::new(&path);
+        let mut buf = [0u8; PERSIST_IMAGE_MAX_SZ];
+
+        let saved = Settings { time: 90, ecg_k: -3, name: "Ivan".to_string(), sex: "M".to_string() };
+        let sz = persist_save(&mut buf, |m| saved.read(m)).unwrap();
+        storage.store(&buf[..sz]).unwrap();
+
+        // Power cycle
+        let mut buf = [0u8; PERSIST_IMAGE_MAX_SZ];
+        let sz = storage.load(&mut buf).unwrap();
+        let mut loaded = Settings::default();
+        assert_eq!(persist_load(&buf[..sz], |m| loaded.write(m)), Ok(4));
+        assert_eq!(loaded, saved);
+
+        // Image of other scheme revision: removed, retyped, out of range and not persisted entries
+        let mut image = ImageWriter::new(&mut buf, (0, 9)).unwrap();
+        image.push("/conf/gone", Value::U32(1)).unwrap();
+        image.push("/conf/time", Value::U16(30)).unwrap();
+        image.push("/survey/sex", Value::STR("X")).unwrap();
+        image.push("/signal/ecgf/frq", Value::U8(10)).unwrap();
+        image.push("/survey/name", Value::STR("Petr")).unwrap();
+        let sz = image.finish();
+        storage.store(&buf[..sz]).unwrap();
+
+        let mut buf = [0u8; PERSIST_IMAGE_MAX_SZ];
+        let sz = storage.load(&mut buf).unwrap();
+        assert_eq!(persist_load(&buf[..sz], |m| loaded.write(m)), Ok(1));
+        assert_eq!(loaded, Settings { name: "Petr".to_string(), ..saved });
+
+        buf[sz - 1] ^= 1;
+        assert_eq!(persist_load(&buf[..sz], |m| loaded.write(m)), Err(PersistError::Crc));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reset_to_defaults() {
+        assert_eq!(reset2msgs("/conf/time"), Ok(&[Msg::Conf(Conf::Time_W(60))][..]));
+        assert_eq!(reset2msgs("/calib/reo"), Ok(&[
+            Msg::Calib(Calib::Reo(Reo::B_W(0))),
+            Msg::Calib(Calib::Reo(Reo::K_W(1))),
+        ][..]));
+        assert_eq!(reset2msgs("/calib").unwrap().len(), 6);
+        assert_eq!(reset2msgs("/").unwrap().len(), 12);
+        assert_eq!(reset2msgs("/ctrl/record"), Err(AnswerCode::ERR_ACCESS));
+        assert_eq!(reset2msgs("/build"), Err(AnswerCode::ERR_ACCESS));
+        assert_eq!(reset2msgs("/nope"), Err(AnswerCode::ERR_PATH));
+
+        let mut conf = (true, 0u32);
+        let mut pipe = Pipe::new();
+        request(&mut pipe, RequestCode::RESET, "/conf", Value::UNIT(()));
+        request(&mut pipe, RequestCode::RESET, "/ctrl", Value::UNIT(()));
+
+        let mut server = Server::new(pipe);
+        let _ = server.serve(|code, path, v| {
+            req2msg(&mut Unimpl, code, path, v, PrivLvl::NORMAL_LVL).answer(|msg| match msg {
+                Msg::Conf(Conf::Cyclic_W(v)) => { conf.0 = v; Ok(Value::UNIT(())) }
+                Msg::Conf(Conf::Time_W(v)) => { conf.1 = v; Ok(Value::UNIT(())) }
+                _ => Err(AnswerErr::from(AnswerCode::ERR_UNIMPL)),
+            })
+        });
+        assert_eq!(conf, (false, 60));
+
+        let out = server.io().take();
+        let (a0, sz) = answer(&out);
+        let (a1, _) = answer(&out[sz..]);
+        assert_eq!(a0, owned::Msg(AnswerCode::OK_RESET, "/conf".to_string(), owned::Value::UNIT(())));
+        assert_eq!(a1, owned::Msg(AnswerCode::ERR_ACCESS, "/ctrl".to_string(), owned::Value::UNIT(())));
+    }
+
+    #[test]
+    fn txn_recording_config() {
+        #[derive(Default)]
+        struct Recording { cyclic: bool, time: u32, ecgf: u8, reof: u8 }
+
+        fn handle(c: &mut Recording, msg: Msg) -> Result<Value<'static>, AnswerErr<'static>> {
+            match msg {
+                Msg::Conf(Conf::Cyclic_W(v)) => c.cyclic = v,
+                Msg::Conf(Conf::Time_W(v)) => c.time = v,
+                Msg::Signal(Signal::Ecgf(Ecgf::Frq_W(v))) => c.ecgf = v,
+                Msg::Signal(Signal::Reof(Reof::Frq_W(0))) => return Err(CustomErr::BadState.into()),
+                Msg::Signal(Signal::Reof(Reof::Frq_W(v))) => c.reof = v,
+                Msg::Conf(Conf::Cyclic_R) => return Ok(Value::BOOL(c.cyclic)),
+                Msg::Conf(Conf::Time_R) => return Ok(Value::U32(c.time)),
+                Msg::Signal(Signal::Ecgf(Ecgf::Frq_R)) => return Ok(Value::U8(c.ecgf)),
+                Msg::Signal(Signal::Reof(Reof::Frq_R)) => return Ok(Value::U8(c.reof)),
+                _ => return Err(AnswerCode::ERR_UNIMPL.into()),
+            }
+            Ok(Value::UNIT(()))
+        }
+
+        let mut pipe = Pipe::new();
+        request(&mut pipe, RequestCode::BEGIN, "/", Value::UNIT(()));
+        request(&mut pipe, RequestCode::WRITE, "/conf/time", Value::U32(120));
+        request(&mut pipe, RequestCode::WRITE, "/signal/ecgf/frq", Value::U8(50));
+        request(&mut pipe, RequestCode::WRITE, "/signal/reof/frq", Value::U8(0));
+        request(&mut pipe, RequestCode::COMMIT, "/", Value::UNIT(()));
+        request(&mut pipe, RequestCode::BEGIN, "/", Value::UNIT(()));
+        request(&mut pipe, RequestCode::WRITE, "/conf/cyclic", Value::BOOL(true));
+        request(&mut pipe, RequestCode::WRITE, "/signal/reof/frq", Value::U8(25));
+        request(&mut pipe, RequestCode::COMMIT, "/", Value::UNIT(()));
+
+        let mut conf = Recording { time: 60, ecgf: 10, reof: 10, ..Default::default() };
+        let mut txn = Txn::<Staging>::new();
+        let mut server = Server::new(pipe);
+        let _ = server.serve(|code, path, v| {
+            txn.answer(code, req2msg(&mut Unimpl, code, path, v, PrivLvl::NORMAL_LVL), |msg| handle(&mut conf, msg))
+        });
+
+        let codes: Vec<AnswerCode> = {
+            let out = server.io().take();
+            let mut pos = 0;
+            let mut codes = Vec::new();
+            while pos < out.len() {
+                let (msg, sz) = answer(&out[pos..]);
+                codes.push(msg.0);
+                pos += sz;
+            }
+            codes
+        };
+        assert_eq!(codes, vec![
+            AnswerCode::OK_BEGIN, AnswerCode::OK_WRITE, AnswerCode::OK_WRITE, AnswerCode::OK_WRITE, AnswerCode::ERR_CUSTOM,
+            AnswerCode::OK_BEGIN, AnswerCode::OK_WRITE, AnswerCode::OK_WRITE, AnswerCode::OK_COMMIT,
+        ]);
+        // First commit rolled back entirely
+        assert_eq!((conf.cyclic, conf.time, conf.ecgf, conf.reof), (true, 60, 10, 25));
+    }
+
+    #[test]
+    fn txn_cas_and_reset() {
+        let mut pipe = Pipe::new();
+        let mut buf = [0x00u8; MAX_MSG_SZ];
+        request(&mut pipe, RequestCode::BEGIN, "/", Value::UNIT(()));
+        let sz = RequestBuilder::new(&mut buf).path("/conf/time").cas(Value::U32(60), Value::U32(90)).build().unwrap();
+        pipe.push(&buf[..sz]);
+        request(&mut pipe, RequestCode::RESET, "/conf", Value::UNIT(()));
+        request(&mut pipe, RequestCode::ABORT, "/", Value::UNIT(()));
+
+        let mut conf = (true, 60u32);
+        let mut txn = Txn::<Staging>::new();
+        let mut server = Server::new(pipe);
+        let _ = server.serve(|code, path, v| {
+            txn.answer(code, req2msg(&mut Unimpl, code, path, v, PrivLvl::NORMAL_LVL), |msg| match msg {
+                Msg::Conf(Conf::Time_R) => Ok(Value::U32(conf.1)),
+                Msg::Conf(Conf::Cyclic_W(v)) => { conf.0 = v; Ok(Value::UNIT(())) }
+                Msg::Conf(Conf::Time_W(v)) => { conf.1 = v; Ok(Value::UNIT(())) }
+                _ => Err(AnswerErr::from(AnswerCode::ERR_UNIMPL)),
+            })
+        });
+
+        let out = server.io().take();
+        let mut pos = 0;
+        let mut codes = Vec::new();
+        while pos < out.len() {
+            let (msg, sz) = answer(&out[pos..]);
+            codes.push(msg.0);
+            pos += sz;
+        }
+        // CAS write half is staged and dropped, RESET is refused
+        assert_eq!(codes, vec![AnswerCode::OK_BEGIN, AnswerCode::OK_CAS, AnswerCode::ERR_TXN, AnswerCode::OK_ABORT]);
+        assert_eq!(conf, (true, 60));
+    }
+
+    #[test]
+    fn privileged_registers() {
+        let read = |path, lvl| req2msg(&mut Unimpl, RequestCode::READ, path, Value::UNIT(()), lvl);
+        let write = |path, v, lvl| req2msg(&mut Unimpl, RequestCode::WRITE, path, v, lvl);
+
+        assert_eq!(read("/calib/ecg/k", PrivLvl::NORMAL_LVL), DispatchResult::Msg(Msg::Calib(Calib::Ecg(Ecg::K_R))));
+        assert_eq!(write("/calib/ecg/k", Value::I32(2), PrivLvl::MODE3_LVL), DispatchResult::Err(AnswerCode::ERR_PRIV));
+        assert_eq!(write("/calib/ecg/k", Value::I32(2), PrivLvl::SECUR_LVL), DispatchResult::Msg(Msg::Calib(Calib::Ecg(Ecg::K_W(2)))));
+        assert_eq!(write("/ctrl/goto_loader", Value::UNIT(()), PrivLvl::SECUR_LVL), DispatchResult::Err(AnswerCode::ERR_PRIV));
+        assert_eq!(write("/ctrl/goto_loader", Value::UNIT(()), PrivLvl::DEVEL_LVL), DispatchResult::Msg(Msg::Ctrl(Ctrl::GotoLoader_W(()))));
+
+        // RESET needs the level of every register it writes
+        let reset = |path, lvl| req2msg(&mut Unimpl, RequestCode::RESET, path, Value::UNIT(()), lvl);
+        assert_eq!(reset_priv("/"), PrivLvl::SECUR_LVL);
+        assert_eq!(reset_priv("/conf"), PrivLvl::NORMAL_LVL);
+        assert_eq!(reset("/calib/reo", PrivLvl::NORMAL_LVL), DispatchResult::Err(AnswerCode::ERR_PRIV));
+        assert_eq!(reset("/", PrivLvl::NORMAL_LVL), DispatchResult::Err(AnswerCode::ERR_PRIV));
+        assert!(matches!(reset("/calib/reo", PrivLvl::SECUR_LVL), DispatchResult::Reset(d) if d.len() == 2));
+        assert!(matches!(reset("/conf", PrivLvl::NORMAL_LVL), DispatchResult::Reset(_)));
+    }
+
+    #[test]
+    fn root_annotations() {
+        assert_eq!(PROTOCOL_VERSION, "1.0");
+        assert_eq!((PROTOCOL_VERSION_MAJOR, PROTOCOL_VERSION_MINOR), (1, 0));
+        assert_eq!(VISIBILITY, "public");
+
+        // `ctrl` and `io` are RW by `@control`, `/io/file/max` keeps its own RO
+        let write = |path, v| req2msg(&mut Unimpl, RequestCode::WRITE, path, v, PrivLvl::NORMAL_LVL);
+        assert_eq!(write("/ctrl/vis", Value::BOOL(true)), DispatchResult::Msg(Msg::Ctrl(Ctrl::Vis_W(true))));
+        assert_eq!(write("/io/file/pos", Value::U32(1)), DispatchResult::Msg(Msg::Io(Io::File(File::Pos_W(1)))));
+        assert_eq!(write("/io/file/max", Value::U32(1)), DispatchResult::Err(AnswerCode::ERR_ACCESS));
+        assert_eq!(write("/state/voltage", Value::I32(1)), DispatchResult::Err(AnswerCode::ERR_ACCESS));
+    }
+
+    #[test]
+    fn section_arrays() {
+        let write = |path, v| req2msg(&mut Unimpl, RequestCode::WRITE, path, v, PrivLvl::NORMAL_LVL);
+
+        // Every element shares `Slot` enum, variant carries the index
+        let mut used = [false; 4];
+        for path in ["/test/slot/0/used", "/test/slot/3/used"] {
+            match write(path, Value::BOOL(true)) {
+                DispatchResult::Msg(Msg::Test(Test::Slot(i, Slot::Used_W(v)))) => used[i as usize] = v,
+                other => panic!("{}: {:?}", path, other),
+            }
+        }
+        assert_eq!(used, [true, false, false, true]);
+        assert_eq!(write("/test/slot/4/used", Value::BOOL(true)), DispatchResult::Err(AnswerCode::ERR_PATH));
+        assert_eq!(reset2msgs("/test/slot/2"), Ok(&[Msg::Test(Test::Slot(2, Slot::Used_W(false)))][..]));
+
+        // Nested arrays keep index of every level
+        assert_eq!(
+            write("/test/slot/1/pin/1/level", Value::BOOL(true)),
+            DispatchResult::Msg(Msg::Test(Test::Slot(1, Slot::Pin(1, Pin::Level_W(true))))),
+        );
+
+        // Template bodies are ordinary registers
+        assert_eq!(reset2msgs("/calib/acc"), Ok(&[
+            Msg::Calib(Calib::Acc(Acc::B_W(0))),
+            Msg::Calib(Calib::Acc(Acc::K_W(1))),
+        ][..]));
+    }
+
+    #[test]
+    fn decode_custom_error() {
+        use std::convert::TryFrom;
+
+        let mut buf = [0x00u8; MAX_MSG_SZ];
+        let _ = RequestBuilder::new(&mut buf)
+            .code(RequestCode::WRITE)
+            .path("/ctrl/erase")
+            .build().unwrap();
+        let sz = AnswerBuilder::new(&mut buf)
+            .error(CustomErr::NoMedia.into())
+            .build();
+
+        let ellocopo2::Msg(code, _, v) = ParseMsg::new().try_parse(&buf[..sz]).unwrap();
+        let detail = ErrDetail::try_from(v).unwrap();
+        assert_eq!(detail, ErrDetail::Code(CustomErr::NoMedia as u16));
+        if let ErrDetail::Code(c) = detail {
+            assert_eq!(CustomErr::try_from(c), Ok(CustomErr::NoMedia));
+            assert_eq!(format!("{}", CustomErr::try_from(c).unwrap()), "no_media");
+        }
+        assert_eq!(format!("{}", AnswerErr(code, detail)), "ERR_CUSTOM: #2");
+    }
+
+    #[test]
+    fn all_types_dispatch() {
+        use super::all_types::*;
+
+        let write = |path, v| req2msg(&mut Unimpl, RequestCode::WRITE, path, v, PrivLvl::NORMAL_LVL);
+        assert_eq!(write("/num/bool", Value::BOOL(true)), DispatchResult::Msg(Msg::Num(Num::Bool_W(true))));
+        assert_eq!(write("/num/i8", Value::I8(-1)), DispatchResult::Msg(Msg::Num(Num::I8_W(-1))));
+        assert_eq!(write("/num/i16", Value::I16(-300)), DispatchResult::Msg(Msg::Num(Num::I16_W(-300))));
+        assert_eq!(write("/num/i32", Value::I32(-70000)), DispatchResult::Msg(Msg::Num(Num::I32_W(-70000))));
+        assert_eq!(write("/num/u8", Value::U8(200)), DispatchResult::Msg(Msg::Num(Num::U8_W(200))));
+        assert_eq!(write("/num/u16", Value::U16(60000)), DispatchResult::Msg(Msg::Num(Num::U16_W(60000))));
+        assert_eq!(write("/num/u32", Value::U32(1 << 31)), DispatchResult::Msg(Msg::Num(Num::U32_W(1 << 31))));
+        assert_eq!(write("/blob/unit", Value::UNIT(())), DispatchResult::Msg(Msg::Blob(Blob::Unit_W(()))));
+        assert_eq!(write("/blob/str", Value::STR("s")), DispatchResult::Msg(Msg::Blob(Blob::Str_W("s"))));
+        assert_eq!(write("/blob/bytes", Value::BYTES(&[1])), DispatchResult::Msg(Msg::Blob(Blob::Bytes_W(&[1]))));
+        assert_eq!(write("/num/i16", Value::U16(1)), DispatchResult::Err(AnswerCode::ERR_TYPE));
+        assert_eq!(write("/num/u16", Value::I16(1)), DispatchResult::Err(AnswerCode::ERR_TYPE));
+
+        assert_eq!(reset2msgs("/num").unwrap().len(), 7);
+        assert_eq!(reset2msgs("/num/i16"), Ok(&[Msg::Num(Num::I16_W(-16))][..]));
+        assert_eq!(reset2msgs("/blob/bytes"), Ok(&[Msg::Blob(Blob::Bytes_W(&[1, 2, 3]))][..]));
+
+        let mut staging = Staging::default();
+        assert_eq!(staging.stage(Msg::Num(Num::I8_W(-2))), Ok(()));
+        assert_eq!(staging.stage(Msg::Blob(Blob::Unit_W(()))), Err(Msg::Blob(Blob::Unit_W(()))));
+        assert_eq!(staging.num_i8, Some(-2));
+        assert_eq!(<Staging as Stage<Msg>>::LEN, 7);
+    }
+}